@@ -0,0 +1,44 @@
+// Retro CRT look: curved glass, chromatic aberration and scanlines, applied after tonemapping
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct Crt {
+    scanlines: f32,
+    curvature: f32,
+    chromatic_aberration: f32,
+    // Keeps the uniform at 16 bytes, which WebGL2 requires
+    _padding: f32,
+}
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var texture_sampler: sampler;
+@group(0) @binding(2) var<uniform> crt: Crt;
+
+const PI: f32 = 3.14159265;
+// Height of one scanline, in pixels of the render target
+const SCANLINE_HEIGHT: f32 = 3.0;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // Bend the image outwards, as if it was on a curved tube, leaving black corners
+    let centered = in.uv * 2.0 - 1.0;
+    let curved = centered * (1.0 + centered.yx * centered.yx * crt.curvature * 0.1);
+    let uv = curved * 0.5 + 0.5;
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    // Split the color channels apart, more so towards the edges
+    let shift = curved * crt.chromatic_aberration * 0.004;
+    let color = vec3<f32>(
+        textureSample(screen_texture, texture_sampler, uv + shift).r,
+        textureSample(screen_texture, texture_sampler, uv).g,
+        textureSample(screen_texture, texture_sampler, uv - shift).b,
+    );
+
+    // Darken the gaps between scanlines
+    let rows = f32(textureDimensions(screen_texture).y) / SCANLINE_HEIGHT;
+    let scanline = 0.5 + 0.5 * cos(uv.y * rows * 2.0 * PI);
+    let brightness = 1.0 - crt.scanlines * 0.5 * (1.0 - scanline);
+
+    return vec4<f32>(color * brightness, 1.0);
+}
@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 use bevy::window::WindowMode;
 use bevy::winit::WinitSettings;
-use bevy_pong::GamePlugin;
+use bevy_pong::PongPlugin;
 
 #[bevy_main]
 fn main() {
@@ -28,7 +28,7 @@ fn main() {
                 }),
                 ..default()
             }),
-            GamePlugin,
+            PongPlugin::default(),
         ))
         .run();
 }
@@ -0,0 +1,411 @@
+use crate::actions::Controller;
+use crate::data::RonAssetAppExt;
+use crate::events::{BallHitPaddle, Goal, MatchEnded};
+use crate::game::{handle_round_end, AiSkill, MatchStats, PaddleSide, Velocity};
+use crate::handicap::Handicap;
+use crate::loading::AchievementAssets;
+use crate::policy::ActivePolicy;
+use crate::rules::MatchRules;
+use crate::storage::{load_json, save_json};
+use crate::theme::ThemeRole;
+use crate::ui::{spawn_button, ChangeState};
+use crate::{GameSet, GameState};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct AchievementsPlugin;
+
+/// This plugin keeps track of the [`AchievementProgress`] of the people playing, from the gameplay events of
+/// every match they take part in, and unlocks the [`Achievement`]s whose condition it meets.
+/// Unlocking one shows a toast, and the achievements screen lists them all.
+/// Progress is saved at the end of every match, so it carries over between runs.
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_ron_asset::<Achievement>(&["achievement.ron"])
+            .insert_resource(load_json::<AchievementProgress>(SAVE_NAME).unwrap_or_default())
+            .add_systems(
+                Update,
+                (
+                    track_progress
+                        .after(handle_round_end)
+                        .in_set(GameSet::Scoring)
+                        .run_if(in_state(GameState::Playing)),
+                    (
+                        unlock_achievements
+                            .run_if(resource_exists::<AchievementAssets>)
+                            .run_if(resource_changed::<AchievementProgress>),
+                        expire_toasts,
+                    )
+                        .in_set(GameSet::Presentation),
+                ),
+            )
+            .add_systems(OnExit(GameState::Playing), save_progress)
+            .add_systems(OnEnter(GameState::Achievements), setup_achievements_menu)
+            .add_systems(
+                Update,
+                handle_achievements_escape_key
+                    .in_set(GameSet::Input)
+                    .run_if(in_state(GameState::Achievements)),
+            )
+            .add_systems(OnExit(GameState::Achievements), cleanup_achievements_menu);
+    }
+}
+
+/// An achievement, loaded from `assets/achievements/*.achievement.ron`
+#[derive(Asset, TypePath, Deserialize)]
+pub struct Achievement {
+    pub name: String,
+    pub description: String,
+    pub condition: Condition,
+}
+
+#[derive(Deserialize)]
+pub enum Condition {
+    // Win this many matches in total
+    Wins { count: u32 },
+    // Score this many goals in total
+    Goals { count: u32 },
+    // Win a match with at least this many points, without the others scoring any
+    Shutout { points: u32 },
+    // Keep a rally going for this many paddle hits
+    Rally { hits: u32 },
+    // Return a ball going at least this fast
+    FastReturn { speed: f32 },
+    // Beat the computer opponent with this name
+    Beat { opponent: String },
+}
+
+impl Condition {
+    fn is_met(&self, progress: &AchievementProgress) -> bool {
+        match self {
+            Condition::Wins { count } => progress.wins >= *count,
+            Condition::Goals { count } => progress.goals >= *count,
+            Condition::Shutout { points } => progress.best_shutout >= *points,
+            Condition::Rally { hits } => progress.longest_rally >= *hits,
+            Condition::FastReturn { speed } => progress.fastest_return >= *speed,
+            Condition::Beat { opponent } => progress.beaten.contains(opponent),
+        }
+    }
+
+    // How far along the way to a total people are
+    fn progress(&self, progress: &AchievementProgress) -> Option<String> {
+        match self {
+            Condition::Wins { count } => Some(format!("{}/{}", progress.wins.min(*count), count)),
+            Condition::Goals { count } => Some(format!("{}/{}", progress.goals.min(*count), count)),
+            _ => None,
+        }
+    }
+}
+
+const SAVE_NAME: &str = "achievements";
+// Seconds a toast stays on screen
+const TOAST_DURATION: f32 = 4.0;
+
+// Everything the achievement conditions are checked against, over all matches played by people
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct AchievementProgress {
+    // Names of the achievements unlocked so far
+    unlocked: Vec<String>,
+    wins: u32,
+    goals: u32,
+    // The most points of a match won without the others scoring
+    best_shutout: u32,
+    longest_rally: u32,
+    fastest_return: f32,
+    // Names of the computer opponents beaten
+    beaten: Vec<String>,
+}
+
+/// All loaded achievements, in the order they are listed
+#[derive(SystemParam)]
+pub struct Achievements<'w> {
+    assets: Res<'w, Assets<Achievement>>,
+    collection: Res<'w, AchievementAssets>,
+}
+
+impl Achievements<'_> {
+    fn iter(&self) -> impl Iterator<Item = &Achievement> {
+        self.collection
+            .achievements
+            .iter()
+            .filter_map(|handle| self.assets.get(handle))
+    }
+}
+
+#[derive(Component)]
+struct Toast(Timer);
+
+#[derive(Component)]
+struct AchievementsMenu;
+
+// Only what people do counts, so matches between computers unlock nothing. Wins with a handicap
+// don't count either, and only a computer playing with its own personality counts as beaten.
+#[allow(clippy::too_many_arguments)]
+fn track_progress(
+    rules: Res<MatchRules>,
+    ai_skill: Res<AiSkill>,
+    active_policy: Option<Res<ActivePolicy>>,
+    stats: Res<MatchStats>,
+    mut progress: ResMut<AchievementProgress>,
+    mut paddle_hit_events: EventReader<BallHitPaddle>,
+    mut goal_events: EventReader<Goal>,
+    mut match_ended_events: EventReader<MatchEnded>,
+    paddle_query: Query<&Controller>,
+    ball_query: Query<&Velocity>,
+) {
+    let is_person = |side: PaddleSide| rules.controllers[side.index()] != Controller::Ai;
+    if !rules.mode.sides().iter().any(|side| is_person(*side)) {
+        paddle_hit_events.clear();
+        goal_events.clear();
+        match_ended_events.clear();
+        return;
+    }
+
+    for event in paddle_hit_events.read() {
+        if paddle_query.get(event.paddle) == Ok(&Controller::Ai) {
+            continue;
+        }
+        if let Ok(velocity) = ball_query.get(event.ball) {
+            if velocity.speed > progress.fastest_return {
+                progress.fastest_return = velocity.speed;
+            }
+        }
+    }
+    for goal in goal_events.read() {
+        if goal.scorer.is_some_and(is_person) {
+            progress.goals += 1;
+        }
+    }
+    if stats.longest_rally > progress.longest_rally {
+        progress.longest_rally = stats.longest_rally;
+    }
+
+    for event in match_ended_events.read() {
+        let Some(winner) = event.winner.filter(|winner| is_person(*winner)) else {
+            continue;
+        };
+        if stats.handicaps[winner.index()] != Handicap::default() {
+            continue;
+        }
+        progress.wins += 1;
+        let others = rules.mode.sides().iter().filter(|side| **side != winner);
+        let points = event.points[winner.index()];
+        if others.clone().all(|side| event.points[side.index()] == 0)
+            && points > progress.best_shutout
+        {
+            progress.best_shutout = points;
+        }
+        let opponent = &ai_skill.personality.name;
+        let is_opponent = |side: &PaddleSide| {
+            !is_person(*side)
+                && active_policy
+                    .as_ref()
+                    .is_none_or(|active_policy| active_policy.for_side(*side).is_none())
+        };
+        if others.clone().any(is_opponent) && !progress.beaten.contains(opponent) {
+            progress.beaten.push(opponent.clone());
+        }
+    }
+}
+
+fn unlock_achievements(
+    mut commands: Commands,
+    achievements: Achievements,
+    mut progress: ResMut<AchievementProgress>,
+    toast_query: Query<(), With<Toast>>,
+) {
+    let mut toasts = toast_query.iter().count();
+    let mut unlocked_any = false;
+    for achievement in achievements.iter() {
+        if progress.unlocked.contains(&achievement.name) || !achievement.condition.is_met(&progress)
+        {
+            continue;
+        }
+        progress.unlocked.push(achievement.name.clone());
+        spawn_toast(&mut commands, achievement, toasts);
+        toasts += 1;
+        unlocked_any = true;
+    }
+    if unlocked_any {
+        save_json(SAVE_NAME, &*progress);
+    }
+}
+
+fn save_progress(progress: Res<AchievementProgress>) {
+    save_json(SAVE_NAME, &*progress);
+}
+
+// Toasts stack down from the top right corner, `index` being the number of toasts above this one
+fn spawn_toast(commands: &mut Commands, achievement: &Achievement, index: usize) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(80.0 + 90.0 * index as f32),
+                right: Val::Px(20.0),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(12.0)),
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgba(0.1, 0.1, 0.1, 0.85)),
+            Toast(Timer::from_seconds(TOAST_DURATION, TimerMode::Once)),
+        ))
+        .with_children(|children| {
+            for (text, font_size) in [
+                (format!("Achievement unlocked: {}", achievement.name), 25.0),
+                (achievement.description.clone(), 20.0),
+            ] {
+                children.spawn((
+                    Text::new(text),
+                    TextFont {
+                        font_size,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+            }
+        });
+}
+
+fn expire_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut toast_query: Query<(Entity, &mut Toast)>,
+) {
+    for (entity, mut toast) in &mut toast_query {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn setup_achievements_menu(
+    mut commands: Commands,
+    achievements: Achievements,
+    progress: Res<AchievementProgress>,
+) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            AchievementsMenu,
+        ))
+        .with_children(|children| {
+            children.spawn((
+                Text::new("Achievements"),
+                TextFont {
+                    font_size: 60.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                ThemeRole::UiText,
+                Node {
+                    margin: UiRect::bottom(Val::Px(40.0)),
+                    ..default()
+                },
+            ));
+            for achievement in achievements.iter() {
+                let unlocked = progress.unlocked.contains(&achievement.name);
+                let mut line = format!(
+                    "[{}] {}: {}",
+                    if unlocked { "x" } else { " " },
+                    achievement.name,
+                    achievement.description
+                );
+                if let Some(so_far) = achievement.condition.progress(&progress) {
+                    line.push_str(&format!(" ({so_far})"));
+                }
+                let color = if unlocked {
+                    Color::WHITE
+                } else {
+                    Color::linear_rgb(0.5, 0.5, 0.5)
+                };
+                children.spawn((
+                    Text::new(line),
+                    TextFont {
+                        font_size: 25.0,
+                        ..default()
+                    },
+                    TextColor(color),
+                    Node {
+                        margin: UiRect::bottom(Val::Px(10.0)),
+                        ..default()
+                    },
+                ));
+            }
+            children
+                .spawn(Node {
+                    margin: UiRect::top(Val::Px(30.0)),
+                    ..default()
+                })
+                .with_children(|row| {
+                    spawn_button(row, "Back", 30.0, ChangeState(GameState::Menu));
+                });
+        });
+}
+
+fn handle_achievements_escape_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Menu);
+    }
+}
+
+fn cleanup_achievements_menu(mut commands: Commands, menu: Query<Entity, With<AchievementsMenu>>) {
+    for entity in menu.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditions_are_met_once_progress_reaches_them() {
+        let mut progress = AchievementProgress {
+            wins: 2,
+            best_shutout: 5,
+            fastest_return: 600.0,
+            beaten: vec!["Rookie".to_string()],
+            ..default()
+        };
+        let wins = Condition::Wins { count: 3 };
+        assert!(!wins.is_met(&progress));
+        assert_eq!(wins.progress(&progress).as_deref(), Some("2/3"));
+        progress.wins = 4;
+        assert!(wins.is_met(&progress));
+        // Going past the total doesn't show more than it
+        assert_eq!(wins.progress(&progress).as_deref(), Some("3/3"));
+
+        assert!(Condition::Shutout { points: 5 }.is_met(&progress));
+        assert!(!Condition::Shutout { points: 11 }.is_met(&progress));
+        assert!(!Condition::Goals { count: 1 }.is_met(&progress));
+        assert!(!Condition::Rally { hits: 1 }.is_met(&progress));
+        assert!(Condition::FastReturn { speed: 600.0 }.is_met(&progress));
+        assert!(Condition::Beat {
+            opponent: "Rookie".to_string()
+        }
+        .is_met(&progress));
+        assert!(!Condition::Beat {
+            opponent: "Champion".to_string()
+        }
+        .is_met(&progress));
+        assert_eq!(
+            Condition::Beat {
+                opponent: "Rookie".to_string()
+            }
+            .progress(&progress),
+            None
+        );
+    }
+}
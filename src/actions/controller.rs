@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+
+use crate::game::PaddleSide;
+
+// Who moves a paddle
+#[derive(Component, Clone, Copy, PartialEq)]
+pub enum Controller {
+    // Keyboard, mouse and touch, through the `Actions` resource
+    Player,
+    // The pair of keys reserved for the paddle's side, so several players can share one keyboard
+    Keys,
+    // One of the connected gamepads, by its position among them
+    Gamepad(usize),
+    Ai,
+}
+
+impl Controller {
+    // Every controller a side can be given, in the order the rules screen cycles through them
+    pub const CHOICES: [Controller; 7] = [
+        Controller::Player,
+        Controller::Keys,
+        Controller::Gamepad(0),
+        Controller::Gamepad(1),
+        Controller::Gamepad(2),
+        Controller::Gamepad(3),
+        Controller::Ai,
+    ];
+
+    // `keys` is the side whose keys a `Controller::Keys` paddle listens to
+    pub fn label(&self, keys: PaddleSide) -> String {
+        match self {
+            Controller::Player => "Player".to_string(),
+            Controller::Keys => {
+                let (lower, higher) = side_keys(keys);
+                format!("Keys {}/{}", key_name(lower), key_name(higher))
+            }
+            Controller::Gamepad(index) => format!("Gamepad {}", index + 1),
+            Controller::Ai => "Computer".to_string(),
+        }
+    }
+}
+
+// Forward paddles in doubles are given the keys of a side that is not playing
+pub fn partner_keys(side: PaddleSide) -> PaddleSide {
+    match side {
+        PaddleSide::Left | PaddleSide::Top => PaddleSide::Top,
+        PaddleSide::Right | PaddleSide::Bottom => PaddleSide::Bottom,
+    }
+}
+
+// The keys moving a `Controller::Keys` paddle towards lower and higher coordinates along its side.
+// Note that the `Controller::Player` listens to both W/S and the arrow keys.
+fn side_keys(side: PaddleSide) -> (KeyCode, KeyCode) {
+    match side {
+        PaddleSide::Left => (KeyCode::KeyS, KeyCode::KeyW),
+        PaddleSide::Right => (KeyCode::ArrowDown, KeyCode::ArrowUp),
+        PaddleSide::Top => (KeyCode::KeyC, KeyCode::KeyV),
+        PaddleSide::Bottom => (KeyCode::KeyN, KeyCode::KeyM),
+    }
+}
+
+fn key_name(key: KeyCode) -> &'static str {
+    match key {
+        KeyCode::KeyS => "S",
+        KeyCode::KeyW => "W",
+        KeyCode::ArrowDown => "Down",
+        KeyCode::ArrowUp => "Up",
+        KeyCode::KeyC => "C",
+        KeyCode::KeyV => "V",
+        KeyCode::KeyN => "N",
+        KeyCode::KeyM => "M",
+        _ => "?",
+    }
+}
+
+// Movement from -1 to 1 for a paddle on the keyboard, using the keys of the side `keys`
+pub fn key_input(keys: PaddleSide, keyboard_input: &ButtonInput<KeyCode>) -> f32 {
+    let (lower, higher) = side_keys(keys);
+    let pressed = |key| {
+        if keyboard_input.pressed(key) {
+            1.0
+        } else {
+            0.0
+        }
+    };
+    pressed(higher) - pressed(lower)
+}
+
+// Movement from -1 to 1 along the side's axis for a paddle on a gamepad, from the left stick or the d-pad
+pub fn gamepad_input(index: usize, side: PaddleSide, gamepads: &Query<&Gamepad>) -> f32 {
+    let Some(gamepad) = gamepads.iter().nth(index) else {
+        return 0.;
+    };
+    let input = gamepad.left_stick() + gamepad.dpad();
+    input.dot(side.axis()).clamp(-1.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forward_paddles_use_the_keys_of_a_side_not_playing() {
+        assert_eq!(partner_keys(PaddleSide::Left), PaddleSide::Top);
+        assert_eq!(partner_keys(PaddleSide::Right), PaddleSide::Bottom);
+        assert_eq!(
+            Controller::Keys.label(partner_keys(PaddleSide::Left)),
+            "Keys C/V"
+        );
+
+        let mut keyboard_input = ButtonInput::default();
+        keyboard_input.press(KeyCode::KeyV);
+        assert_eq!(
+            key_input(partner_keys(PaddleSide::Left), &keyboard_input),
+            1.
+        );
+        assert_eq!(key_input(PaddleSide::Left, &keyboard_input), 0.);
+        keyboard_input.press(KeyCode::KeyC);
+        assert_eq!(key_input(PaddleSide::Top, &keyboard_input), 0.);
+    }
+}
@@ -1,23 +1,35 @@
-use bevy::math::Vec3Swizzles;
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
 
 use crate::actions::game_control::{get_movement, GameControl};
-use crate::game::Player;
-use crate::GameState;
+use crate::actions::mouse::grab_cursor;
+pub use crate::actions::mouse::set_mouse_actions;
+use crate::camera::MainCamera;
+use crate::game::{Paddle, PaddleSide, Player, TouchButton};
+use crate::settings::{Settings, TouchSettings};
+use crate::{GameSet, GameState};
 
-mod game_control;
+pub use crate::actions::controller::{gamepad_input, key_input, partner_keys, Controller};
 
-pub const FOLLOW_EPSILON: f32 = 5.;
+mod controller;
+mod game_control;
+mod mouse;
 
 pub struct ActionsPlugin;
 
-// This plugin listens for keyboard input and converts the input into Actions.
+// This plugin listens for keyboard, mouse and touch input and converts the input into Actions.
 // Actions can then be used as a resource in other systems to act on the player input.
 impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Actions>().add_systems(
             Update,
-            set_movement_actions.run_if(in_state(GameState::Playing)),
+            (
+                (set_movement_actions, set_mouse_actions)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+                grab_cursor,
+            )
+                .in_set(GameSet::Input),
         );
     }
 }
@@ -25,14 +37,23 @@ impl Plugin for ActionsPlugin {
 #[derive(Default, Resource)]
 pub struct Actions {
     pub player_movement: Option<Vec2>,
+    // Height the player paddle is being dragged to, if it is under direct (touch or mouse) control
+    pub player_target: Option<f32>,
+    // Height the right paddle is being dragged to by a second player in split screen touch mode
+    pub opponent_target: Option<f32>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn set_movement_actions(
     mut actions: ResMut<Actions>,
+    settings: Res<Settings>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
     touch_input: Res<Touches>,
     player: Query<&Transform, With<Player>>,
-    camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    paddles: Query<(&Transform, &Paddle)>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    touch_buttons: Query<&Interaction, With<TouchButton>>,
 ) {
     let mut player_movement = Vec2::new(
         get_movement(GameControl::Right, &keyboard_input)
@@ -40,20 +61,91 @@ pub fn set_movement_actions(
         get_movement(GameControl::Up, &keyboard_input)
             - get_movement(GameControl::Down, &keyboard_input),
     );
+    let mut player_target = None;
+    let mut opponent_target = None;
 
-    if let Some(touch_position) = touch_input.first_pressed_position() {
-        let (camera, camera_transform) = camera.single();
-        if let Ok(touch_position) = camera.viewport_to_world_2d(camera_transform, touch_position) {
-            let diff = touch_position - player.single().translation.xy();
-            if diff.length() > FOLLOW_EPSILON {
-                player_movement = diff.normalize();
+    // Touches that land on the on-screen buttons are not meant for the paddles
+    let pressing_button = touch_buttons
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+    if let (Ok((camera, camera_transform)), Ok(window), false) =
+        (camera.get_single(), window.get_single(), pressing_button)
+    {
+        for touch in touch_input.iter() {
+            let Ok(touch_position) =
+                camera.viewport_to_world_2d(camera_transform, touch.position())
+            else {
+                continue;
+            };
+            // The first touch on each half of the screen claims the paddle on that side
+            if claims_opponent(&settings.touch, touch.position().x, window.width()) {
+                opponent_target.get_or_insert(touch_position.y);
+            } else {
+                player_target.get_or_insert(touch_position.y);
             }
         }
     }
 
+    // Dragging holds the paddle still once it is within the deadzone, instead of jittering around the touch
+    if let (Some(target), Ok(player_transform)) = (player_target, player.get_single()) {
+        match drag_direction(
+            target,
+            player_transform.translation.y,
+            settings.touch.deadzone,
+        ) {
+            Some(direction) => player_movement = Vec2::new(0., direction),
+            None => player_target = None,
+        }
+    }
+    let opponent = paddles
+        .iter()
+        .find(|(_, paddle)| paddle.side == PaddleSide::Right && !paddle.forward);
+    if let (Some(target), Some((opponent_transform, _))) = (opponent_target, opponent) {
+        let position = opponent_transform.translation.y;
+        if drag_direction(target, position, settings.touch.deadzone).is_none() {
+            // Still claimed by the second player, so hold the paddle where it is
+            opponent_target = Some(position);
+        }
+    }
+
     if player_movement != Vec2::ZERO {
         actions.player_movement = Some(player_movement.normalize());
     } else {
         actions.player_movement = None;
     }
+    actions.player_target = player_target;
+    actions.opponent_target = opponent_target;
+}
+
+// Whether a touch `x` pixels from the left of the screen is meant for the right paddle rather than the player's
+fn claims_opponent(settings: &TouchSettings, x: f32, window_width: f32) -> bool {
+    settings.split_screen && x > window_width / 2.
+}
+
+// The direction a paddle at `position` moves in to follow a drag to `target`, none within the deadzone
+fn drag_direction(target: f32, position: f32, deadzone: f32) -> Option<f32> {
+    let diff = target - position;
+    (diff.abs() > deadzone).then_some(diff.signum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drag_holds_still_within_the_deadzone() {
+        assert_eq!(drag_direction(100., 50., 8.), Some(1.));
+        assert_eq!(drag_direction(-100., 50., 8.), Some(-1.));
+        assert_eq!(drag_direction(57., 50., 8.), None);
+        assert_eq!(drag_direction(42., 50., 8.), None);
+    }
+
+    #[test]
+    fn split_screen_gives_the_right_half_to_the_opponent() {
+        let mut settings = TouchSettings::default();
+        assert!(!claims_opponent(&settings, 900., 1000.));
+        settings.split_screen = true;
+        assert!(claims_opponent(&settings, 900., 1000.));
+        assert!(!claims_opponent(&settings, 100., 1000.));
+    }
 }
@@ -0,0 +1,113 @@
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+
+use crate::actions::{drag_direction, Actions};
+use crate::camera::MainCamera;
+use crate::game::{Player, PlayingState, COURT_HEIGHT};
+use crate::settings::Settings;
+use crate::GameState;
+
+// Distance (in world units) between the pointer and the paddle within which the paddle holds still
+const MOUSE_DEADZONE: f32 = 2.0;
+
+// Steers the player paddle towards the mouse, whenever the mouse was used more recently than the keyboard or touch.
+// The paddle still moves at its usual speed, the mouse only decides where it is heading.
+#[allow(clippy::too_many_arguments)]
+pub fn set_mouse_actions(
+    mut actions: ResMut<Actions>,
+    settings: Res<Settings>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_active: Local<bool>,
+    mut locked_target: Local<Option<f32>>,
+    player: Query<&Transform, With<Player>>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+) {
+    let motion: Vec2 = mouse_motion.read().map(|motion| motion.delta).sum();
+    if !settings.mouse.enabled || actions.player_movement.is_some() {
+        *mouse_active = false;
+        *locked_target = None;
+        return;
+    }
+    if motion != Vec2::ZERO {
+        *mouse_active = true;
+    }
+    let (true, Ok(player_transform), Ok(window)) =
+        (*mouse_active, player.get_single(), window.get_single())
+    else {
+        return;
+    };
+
+    let target = if window.cursor_options.grab_mode == CursorGrabMode::Locked {
+        // The locked pointer stays put, so follow a virtual pointer driven by the relative motion
+        let target = locked_target.get_or_insert(player_transform.translation.y);
+        *target = move_locked_target(*target, motion, settings.mouse.sensitivity);
+        *target
+    } else {
+        *locked_target = None;
+        let (Some(cursor_position), Ok((camera, camera_transform))) =
+            (window.cursor_position(), camera.get_single())
+        else {
+            return;
+        };
+        let Ok(cursor_position) = camera.viewport_to_world_2d(camera_transform, cursor_position)
+        else {
+            return;
+        };
+        cursor_position.y * settings.mouse.sensitivity
+    };
+
+    if let Some(direction) = drag_direction(target, player_transform.translation.y, MOUSE_DEADZONE)
+    {
+        actions.player_movement = Some(Vec2::new(0., direction));
+        actions.player_target = Some(target);
+    }
+}
+
+// Where the virtual pointer of a locked mouse ends up after `motion`, kept within the court
+fn move_locked_target(target: f32, motion: Vec2, sensitivity: f32) -> f32 {
+    // Window coordinates grow downwards
+    (target - motion.y * sensitivity).clamp(-COURT_HEIGHT, COURT_HEIGHT)
+}
+
+// Locks the pointer only while a round is being played, so it is free to click through the menus and pause screen
+pub fn grab_cursor(
+    settings: Res<Settings>,
+    game_state: Res<State<GameState>>,
+    playing_state: Res<State<PlayingState>>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Ok(mut window) = window.get_single_mut() else {
+        return;
+    };
+    let lock = settings.mouse.enabled
+        && settings.mouse.pointer_lock
+        && *game_state.get() == GameState::Playing
+        && *playing_state.get() == PlayingState::Playing
+        && window.focused;
+    let grab_mode = if lock {
+        CursorGrabMode::Locked
+    } else {
+        CursorGrabMode::None
+    };
+    if window.cursor_options.grab_mode != grab_mode {
+        window.cursor_options.grab_mode = grab_mode;
+        window.cursor_options.visible = !lock;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locked_pointer_moves_against_window_coordinates_within_the_court() {
+        assert_eq!(move_locked_target(0., Vec2::new(5., 10.), 1.0), -10.);
+        assert_eq!(move_locked_target(20., Vec2::new(0., -10.), 0.5), 25.);
+        assert_eq!(
+            move_locked_target(COURT_HEIGHT - 1., Vec2::new(0., -50.), 2.0),
+            COURT_HEIGHT
+        );
+    }
+}
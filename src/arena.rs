@@ -0,0 +1,396 @@
+use crate::data::RonAssetAppExt;
+use crate::game::{
+    move_ball, Collider, CourtLine, PaddleSide, PlayingState, COURT_HALF_WIDTH, COURT_HEIGHT,
+    WALL_GOAL_HALF_WIDTH,
+};
+use crate::loading::ArenaAssets;
+use crate::rules::MatchRules;
+use crate::theme::ThemeRole;
+use crate::{GameSet, GameState};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+pub struct ArenaPlugin;
+
+/// This plugin builds the court of each match from the [`Arena`] picked in the [`MatchRules`]:
+/// the walls with their gaps, the goal mouths of the sides in play and any obstacles in mid-court.
+/// The shape of the court the ball has to respect is kept in the [`Court`] resource.
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_ron_asset::<Arena>(&["arena.ron"])
+            .init_resource::<Court>()
+            .add_systems(OnEnter(GameState::Playing), spawn_arena)
+            .add_systems(
+                Update,
+                move_obstacles
+                    .before(move_ball)
+                    .in_set(GameSet::Physics)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PlayingState::Playing)),
+            );
+    }
+}
+
+/// A court layout, loaded from `assets/arenas/*.arena.ron`
+#[derive(Asset, TypePath, Deserialize)]
+pub struct Arena {
+    pub name: String,
+    #[serde(default)]
+    pub obstacles: Vec<ArenaObstacle>,
+    // Ranges of x where the top and bottom walls are open
+    #[serde(default)]
+    pub top_gaps: Vec<(f32, f32)>,
+    #[serde(default)]
+    pub bottom_gaps: Vec<(f32, f32)>,
+    // Height of the opening at each end that counts as a goal, the whole end if not set
+    #[serde(default)]
+    pub goal_height: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub struct ArenaObstacle {
+    // Center of the obstacle
+    pub position: (f32, f32),
+    pub size: (f32, f32),
+    // Moving obstacles go back and forth between `position` and `position + travel`
+    #[serde(default)]
+    pub travel: (f32, f32),
+    // Seconds for a moving obstacle to go there and back
+    #[serde(default = "default_period")]
+    pub period: f32,
+}
+
+fn default_period() -> f32 {
+    4.0
+}
+
+// The shape of the court in the current match
+#[derive(Resource)]
+pub struct Court {
+    pub goal_half_height: f32,
+    pub top_gaps: Vec<(f32, f32)>,
+    pub bottom_gaps: Vec<(f32, f32)>,
+    // Sides with an open goal, the others are walled off
+    pub goals: Vec<PaddleSide>,
+}
+
+impl Default for Court {
+    fn default() -> Self {
+        Court {
+            goal_half_height: COURT_HEIGHT,
+            top_gaps: Vec::new(),
+            bottom_gaps: Vec::new(),
+            goals: vec![PaddleSide::Left, PaddleSide::Right],
+        }
+    }
+}
+
+impl Court {
+    // Whether the wall on the side of `y` is open at `x`
+    pub fn is_gap(&self, x: f32, y: f32) -> bool {
+        let gaps = if y > 0. {
+            &self.top_gaps
+        } else {
+            &self.bottom_gaps
+        };
+        gaps.iter().any(|(from, to)| (*from..=*to).contains(&x))
+    }
+
+    // Whether a ball leaving the court past `side` at `position` goes into that side's goal
+    pub fn is_goal_mouth(&self, side: PaddleSide, position: Vec2) -> bool {
+        let half_width = match side {
+            PaddleSide::Left | PaddleSide::Right => self.goal_half_height,
+            PaddleSide::Top | PaddleSide::Bottom => WALL_GOAL_HALF_WIDTH,
+        };
+        self.goals.contains(&side) && position.dot(side.axis()).abs() <= half_width
+    }
+
+    // Closes the goal of a side that is out of the match
+    pub fn close_goal(&mut self, commands: &mut Commands, side: PaddleSide) {
+        self.goals.retain(|goal| *goal != side);
+        self.spawn_goal_wall(commands, side);
+    }
+
+    fn spawn_goal_wall(&self, commands: &mut Commands, side: PaddleSide) {
+        let half_width = match side {
+            PaddleSide::Left | PaddleSide::Right => self.goal_half_height,
+            PaddleSide::Top | PaddleSide::Bottom => WALL_GOAL_HALF_WIDTH,
+        };
+        spawn_line(
+            commands,
+            side.home(),
+            side.axis() * half_width * 2.0 + side.facing().abs() * 2.0,
+        );
+    }
+}
+
+// Anything in mid-court the ball bounces off
+#[derive(Component)]
+pub struct Obstacle {
+    origin: Vec2,
+    travel: Vec2,
+    period: f32,
+    elapsed: f32,
+}
+
+impl Obstacle {
+    // An obstacle that stays where it is put
+    pub fn fixed(origin: Vec2) -> Self {
+        Obstacle {
+            origin,
+            travel: Vec2::ZERO,
+            period: 0.,
+            elapsed: 0.,
+        }
+    }
+}
+
+/// All loaded arenas, in the order they are offered in the menu
+#[derive(SystemParam)]
+pub struct Arenas<'w> {
+    assets: Res<'w, Assets<Arena>>,
+    collection: Res<'w, ArenaAssets>,
+}
+
+impl Arenas<'_> {
+    fn iter(&self) -> impl Iterator<Item = &Arena> {
+        self.collection
+            .arenas
+            .iter()
+            .filter_map(|handle| self.assets.get(handle))
+    }
+
+    // The arena picked in the match rules, falling back to the first one if it is unknown
+    pub fn active(&self, rules: &MatchRules) -> Option<&Arena> {
+        self.iter()
+            .find(|arena| arena.name == rules.arena)
+            .or_else(|| self.iter().next())
+    }
+
+    // The name of the arena after `current`, wrapping around to the first
+    pub fn next_name(&self, current: &str) -> Option<String> {
+        let names: Vec<&str> = self.iter().map(|arena| arena.name.as_str()).collect();
+        let next = names
+            .iter()
+            .position(|name| *name == current)
+            .map_or(0, |index| (index + 1) % names.len());
+        names.get(next).map(|name| name.to_string())
+    }
+}
+
+fn spawn_arena(mut commands: Commands, rules: Res<MatchRules>, arenas: Arenas) {
+    let Some(arena) = arenas.active(&rules) else {
+        return;
+    };
+    let court = Court {
+        goal_half_height: arena
+            .goal_height
+            .map_or(COURT_HEIGHT, |height| height / 2.0),
+        top_gaps: arena.top_gaps.clone(),
+        bottom_gaps: arena.bottom_gaps.clone(),
+        goals: rules.mode.sides().to_vec(),
+    };
+
+    // Spawn center line
+    spawn_line(
+        &mut commands,
+        Vec2::ZERO,
+        Vec2::new(2.0, COURT_HEIGHT * 2.0),
+    );
+
+    // Spawn top and bottom walls, leaving out the gaps and any goal in them
+    for (side, gaps) in [
+        (PaddleSide::Top, &court.top_gaps),
+        (PaddleSide::Bottom, &court.bottom_gaps),
+    ] {
+        let y = side.home().y;
+        let mut gaps = gaps.clone();
+        if court.goals.contains(&side) {
+            gaps.push((-WALL_GOAL_HALF_WIDTH, WALL_GOAL_HALF_WIDTH));
+        }
+        for (from, to) in wall_segments(&gaps) {
+            spawn_line(
+                &mut commands,
+                Vec2::new((from + to) / 2.0, y),
+                Vec2::new(to - from, 2.0),
+            );
+        }
+    }
+
+    // Wall off the ends without a goal
+    for side in [PaddleSide::Left, PaddleSide::Right] {
+        if !court.goals.contains(&side) {
+            court.spawn_goal_wall(&mut commands, side);
+        }
+    }
+
+    // Spawn the walls on either side of narrow goals
+    if court.goal_half_height < COURT_HEIGHT {
+        let height = COURT_HEIGHT - court.goal_half_height;
+        for x in [-COURT_HALF_WIDTH, COURT_HALF_WIDTH] {
+            for y in [-1.0, 1.0] {
+                spawn_line(
+                    &mut commands,
+                    Vec2::new(x, y * (court.goal_half_height + height / 2.0)),
+                    Vec2::new(2.0, height),
+                );
+            }
+        }
+    }
+
+    for obstacle in &arena.obstacles {
+        let origin = Vec2::new(obstacle.position.0, obstacle.position.1);
+        let size = Vec2::new(obstacle.size.0, obstacle.size.1);
+        commands.spawn((
+            Sprite {
+                color: Color::WHITE,
+                custom_size: Some(size),
+                ..default()
+            },
+            Transform::from_translation(origin.extend(0.)),
+            Collider(size),
+            Obstacle {
+                origin,
+                travel: Vec2::new(obstacle.travel.0, obstacle.travel.1),
+                period: obstacle.period,
+                elapsed: 0.,
+            },
+            CourtLine,
+            ThemeRole::CourtLine,
+        ));
+    }
+
+    commands.insert_resource(court);
+}
+
+fn spawn_line(commands: &mut Commands, position: Vec2, size: Vec2) {
+    commands.spawn((
+        Sprite {
+            color: Color::WHITE,
+            custom_size: Some(size),
+            ..default()
+        },
+        Transform::from_translation(position.extend(0.)),
+        CourtLine,
+        ThemeRole::CourtLine,
+    ));
+}
+
+// The parts of a wall running the full width of the court that are not in one of the `gaps`
+fn wall_segments(gaps: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let mut gaps = gaps.to_vec();
+    gaps.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut segments = Vec::new();
+    let mut start = -COURT_HALF_WIDTH;
+    for (from, to) in gaps {
+        if from > start {
+            segments.push((start, from.min(COURT_HALF_WIDTH)));
+        }
+        start = start.max(to);
+    }
+    if start < COURT_HALF_WIDTH {
+        segments.push((start, COURT_HALF_WIDTH));
+    }
+    segments
+}
+
+fn move_obstacles(time: Res<Time>, mut obstacle_query: Query<(&mut Transform, &mut Obstacle)>) {
+    for (mut transform, mut obstacle) in &mut obstacle_query {
+        if obstacle.travel == Vec2::ZERO || obstacle.period <= 0. {
+            continue;
+        }
+        obstacle.elapsed += time.delta_secs();
+        // Eases in and out at both ends of the path
+        let phase = obstacle.elapsed / obstacle.period * std::f32::consts::TAU;
+        let progress = (1.0 - phase.cos()) / 2.0;
+        let position = obstacle.origin + obstacle.travel * progress;
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::read_ron_assets;
+
+    #[test]
+    fn every_arena_loads() {
+        let arenas = read_ron_assets::<Arena>("arenas", "arena.ron").unwrap();
+        assert!(!arenas.is_empty());
+    }
+
+    #[test]
+    fn only_open_goals_within_the_mouth_score() {
+        let mut court = Court {
+            goal_half_height: 100.,
+            ..default()
+        };
+        assert!(court.is_goal_mouth(PaddleSide::Left, Vec2::new(-COURT_HALF_WIDTH, 90.)));
+        assert!(!court.is_goal_mouth(PaddleSide::Right, Vec2::new(COURT_HALF_WIDTH, -110.)));
+        // The top goal goes by its own width, but is closed unless four sides play
+        let top = Vec2::new(WALL_GOAL_HALF_WIDTH - 10., COURT_HEIGHT);
+        assert!(!court.is_goal_mouth(PaddleSide::Top, top));
+        court.goals.push(PaddleSide::Top);
+        assert!(court.is_goal_mouth(PaddleSide::Top, top));
+    }
+
+    #[test]
+    fn gaps_open_the_wall_they_are_on() {
+        let court = Court {
+            top_gaps: vec![(-50., 50.)],
+            ..default()
+        };
+        assert!(court.is_gap(0., COURT_HEIGHT));
+        assert!(!court.is_gap(0., -COURT_HEIGHT));
+        assert!(!court.is_gap(60., COURT_HEIGHT));
+    }
+
+    #[test]
+    fn wall_segments_leave_out_the_gaps() {
+        assert_eq!(wall_segments(&[]), [(-COURT_HALF_WIDTH, COURT_HALF_WIDTH)]);
+        // Unsorted and overlapping gaps, one of them reaching past the end of the court
+        assert_eq!(
+            wall_segments(&[(100., 200.), (-300., -100.), (150., 250.), (500., 700.)]),
+            [(-COURT_HALF_WIDTH, -300.), (-100., 100.), (250., 500.)]
+        );
+    }
+
+    #[test]
+    fn moving_obstacles_ease_there_and_back() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(
+                std::time::Duration::from_secs_f32(0.25),
+            ))
+            .add_systems(Update, move_obstacles);
+        let obstacle = app
+            .world_mut()
+            .spawn((
+                Transform::default(),
+                Obstacle {
+                    travel: Vec2::new(0., 100.),
+                    period: 1.0,
+                    ..Obstacle::fixed(Vec2::ZERO)
+                },
+            ))
+            .id();
+        let mut heights = Vec::new();
+        for _ in 0..6 {
+            app.update();
+            heights.push(
+                app.world()
+                    .get::<Transform>(obstacle)
+                    .unwrap()
+                    .translation
+                    .y,
+            );
+        }
+        // The first update doesn't move time on
+        let expected = [0., 50., 100., 50., 0., 50.];
+        for (height, expected) in heights.iter().zip(expected) {
+            assert!((height - expected).abs() < 0.01, "{heights:?}");
+        }
+    }
+}
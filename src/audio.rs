@@ -1,6 +1,7 @@
-use crate::actions::{set_movement_actions, Actions};
+use crate::actions::Actions;
+use crate::lifecycle::LifecycleEvent;
 use crate::loading::AudioAssets;
-use crate::GameState;
+use crate::{GameSet, GameState};
 use bevy::prelude::*;
 use bevy_kira_audio::prelude::*;
 
@@ -13,9 +14,11 @@ impl Plugin for InternalAudioPlugin {
             .add_systems(OnEnter(GameState::Playing), start_audio)
             .add_systems(
                 Update,
-                control_flying_sound
-                    .after(set_movement_actions)
-                    .run_if(in_state(GameState::Playing)),
+                (
+                    control_flying_sound.run_if(in_state(GameState::Playing)),
+                    suspend_audio,
+                )
+                    .in_set(GameSet::Presentation),
             );
     }
 }
@@ -54,3 +57,17 @@ fn control_flying_sound(
         }
     }
 }
+
+// Nothing should keep playing while the game is in the background
+fn suspend_audio(mut lifecycle_events: EventReader<LifecycleEvent>, audio: Res<Audio>) {
+    for event in lifecycle_events.read() {
+        match event {
+            LifecycleEvent::Suspended => {
+                audio.pause();
+            }
+            LifecycleEvent::Resumed => {
+                audio.resume();
+            }
+        }
+    }
+}
@@ -0,0 +1,432 @@
+//! Plays a batch of matches between two computer players without a window, and reports how they did:
+//! win rates, points, rally lengths and final scores, with 95% confidence intervals.
+//!
+//! `pong_eval FIRST SECOND [--seeds START..END] [--points N] [--max-seconds S] [--json PATH]`
+//!
+//! Each player is an opponent of the career by name, `script:NAME` for a scripted bot or `policy:PATH` for a
+//! policy file, see [`Competitor::parse`]. One match is played for every seed in the range, 0..100 by default,
+//! with the players swapping sides from one seed to the next so neither gets the better of the serve.
+//! The report is printed as a table, and written as JSON to `PATH` if given, or to stdout instead of the table
+//! for `--json -`.
+
+use bevy_pong::eval::{play_match, Competitor, MatchReport};
+use bevy_pong::game::PaddleSide;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::ops::Range;
+use std::process;
+
+const USAGE: &str =
+    "Usage: pong_eval FIRST SECOND [--seeds START..END] [--points N] [--max-seconds S] [--json PATH]";
+
+// How many standard errors either side of an estimate a 95% confidence interval reaches
+const Z_95: f64 = 1.96;
+
+struct Options {
+    first: Competitor,
+    second: Competitor,
+    seeds: Range<u64>,
+    points_to_win: u32,
+    // Seconds of game time after which a match is given up as unfinished, for players that never miss
+    max_seconds: f32,
+    json: Option<String>,
+}
+
+/// A mean or rate with its 95% confidence interval
+#[derive(Serialize)]
+struct Estimate {
+    value: f64,
+    low: f64,
+    high: f64,
+}
+
+#[derive(Serialize)]
+struct ScoreCount {
+    // Points of the first and second player
+    score: [u32; 2],
+    matches: u32,
+}
+
+#[derive(Serialize)]
+struct Report {
+    players: [String; 2],
+    seeds: [u64; 2],
+    points_to_win: u32,
+    matches: u32,
+    wins: [u32; 2],
+    // Matches that ran out of time before either player won
+    unfinished: u32,
+    win_rate: [Estimate; 2],
+    points_per_match: [Estimate; 2],
+    // Paddle hits per point
+    rally_length: Estimate,
+    // Seconds of game time per match
+    match_duration: Estimate,
+    scores: Vec<ScoreCount>,
+}
+
+fn main() {
+    let options = parse_options(env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{error}\n{USAGE}");
+        process::exit(2);
+    });
+
+    let players = [&options.first, &options.second];
+    let mut results = Vec::new();
+    for seed in options.seeds.clone() {
+        let swapped = first_plays_right(seed);
+        let (left, right) = if swapped {
+            (players[1], players[0])
+        } else {
+            (players[0], players[1])
+        };
+        let report = play_match(
+            left,
+            right,
+            options.points_to_win,
+            options.max_seconds,
+            seed,
+        );
+        results.push((report, swapped));
+    }
+    let report = summarize(&options, &results);
+
+    match options.json.as_deref() {
+        Some("-") => println!("{}", to_json(&report)),
+        Some(path) => {
+            print_table(&report);
+            if let Err(error) = fs::write(path, to_json(&report)) {
+                eprintln!("Failed to write {path}: {error}");
+                process::exit(1);
+            }
+        }
+        None => print_table(&report),
+    }
+}
+
+// The first player is on the left for even seeds and on the right for odd ones
+fn first_plays_right(seed: u64) -> bool {
+    seed % 2 == 1
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let first = args.next().ok_or("Missing the first player")?;
+    let second = args.next().ok_or("Missing the second player")?;
+    let mut options = Options {
+        first: Competitor::parse(&first)?,
+        second: Competitor::parse(&second)?,
+        seeds: 0..100,
+        points_to_win: 5,
+        max_seconds: 600.,
+        json: None,
+    };
+    while let Some(option) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing the value of {option}"))?;
+        let invalid = || format!("Invalid value {value} for {option}");
+        match option.as_str() {
+            "--seeds" => {
+                let (start, end) = value.split_once("..").ok_or_else(invalid)?;
+                let start = start.parse().map_err(|_| invalid())?;
+                let end = end.parse().map_err(|_| invalid())?;
+                if end <= start {
+                    return Err(invalid());
+                }
+                options.seeds = start..end;
+            }
+            "--points" => options.points_to_win = value.parse().map_err(|_| invalid())?,
+            "--max-seconds" => options.max_seconds = value.parse().map_err(|_| invalid())?,
+            "--json" => options.json = Some(value),
+            _ => return Err(format!("Unknown argument {option}")),
+        }
+    }
+    Ok(options)
+}
+
+// The results are given as (report, whether the first player played on the right)
+fn summarize(options: &Options, results: &[(MatchReport, bool)]) -> Report {
+    let matches = results.len() as u32;
+    let mut wins = [0; 2];
+    let mut points = [Vec::new(), Vec::new()];
+    let mut rallies = Vec::new();
+    let mut durations = Vec::new();
+    let mut scores = BTreeMap::new();
+    for (report, swapped) in results {
+        // Points and the winner as first and second player instead of left and right
+        let player_points = if *swapped {
+            [report.points[1], report.points[0]]
+        } else {
+            report.points
+        };
+        if let Some(winner) = report.winner {
+            let first_won = (winner == PaddleSide::Left) != *swapped;
+            wins[if first_won { 0 } else { 1 }] += 1;
+        }
+        for (player, points) in points.iter_mut().enumerate() {
+            points.push(player_points[player] as f64);
+        }
+        rallies.extend(report.rallies.iter().map(|rally| *rally as f64));
+        durations.push(report.duration as f64);
+        *scores.entry(player_points).or_insert(0) += 1;
+    }
+
+    let mut scores: Vec<_> = scores
+        .into_iter()
+        .map(|(score, matches)| ScoreCount { score, matches })
+        .collect();
+    scores.sort_by(|a, b| b.matches.cmp(&a.matches).then(b.score.cmp(&a.score)));
+    Report {
+        players: [
+            options.first.name().to_string(),
+            options.second.name().to_string(),
+        ],
+        seeds: [options.seeds.start, options.seeds.end],
+        points_to_win: options.points_to_win,
+        matches,
+        wins,
+        unfinished: matches - wins[0] - wins[1],
+        win_rate: wins.map(|wins| wilson_interval(wins, matches)),
+        points_per_match: points.map(|points| mean_interval(&points)),
+        rally_length: mean_interval(&rallies),
+        match_duration: mean_interval(&durations),
+        scores,
+    }
+}
+
+// The Wilson score interval, which stays within 0 and 1 even for rates close to them
+fn wilson_interval(successes: u32, trials: u32) -> Estimate {
+    if trials == 0 {
+        return Estimate {
+            value: 0.,
+            low: 0.,
+            high: 0.,
+        };
+    }
+    let n = trials as f64;
+    let rate = successes as f64 / n;
+    let z2 = Z_95 * Z_95;
+    let center = (rate + z2 / (2. * n)) / (1. + z2 / n);
+    let spread = Z_95 / (1. + z2 / n) * (rate * (1. - rate) / n + z2 / (4. * n * n)).sqrt();
+    Estimate {
+        value: rate,
+        low: (center - spread).max(0.),
+        high: (center + spread).min(1.),
+    }
+}
+
+// The mean with the normal approximation of its interval
+fn mean_interval(values: &[f64]) -> Estimate {
+    let n = values.len() as f64;
+    if values.is_empty() {
+        return Estimate {
+            value: 0.,
+            low: 0.,
+            high: 0.,
+        };
+    }
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = if values.len() > 1 {
+        values
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f64>()
+            / (n - 1.)
+    } else {
+        0.
+    };
+    let spread = Z_95 * (variance / n).sqrt();
+    Estimate {
+        value: mean,
+        low: mean - spread,
+        high: mean + spread,
+    }
+}
+
+fn to_json(report: &Report) -> String {
+    serde_json::to_string_pretty(report).expect("the report is plain data")
+}
+
+fn print_table(report: &Report) {
+    let [first, second] = &report.players;
+    let width = first.len().max(second.len()).max(18);
+    println!(
+        "{first} vs {second}: {} matches to {} points, seeds {}..{}",
+        report.matches, report.points_to_win, report.seeds[0], report.seeds[1]
+    );
+    println!();
+    println!("{:<20}{first:>width$}  {second:>width$}", "");
+    let percent = |rate: &Estimate| {
+        format!(
+            "{:.1}% ({:.1}-{:.1})",
+            rate.value * 100.,
+            rate.low * 100.,
+            rate.high * 100.
+        )
+    };
+    let mean = |estimate: &Estimate| {
+        format!(
+            "{:.2} ± {:.2}",
+            estimate.value,
+            estimate.high - estimate.value
+        )
+    };
+    let row = |label: &str, values: [String; 2]| {
+        println!("{label:<20}{:>width$}  {:>width$}", values[0], values[1]);
+    };
+    row("Wins", report.wins.map(|wins| wins.to_string()));
+    row(
+        "Win rate (95% CI)",
+        [0, 1].map(|player| percent(&report.win_rate[player])),
+    );
+    row(
+        "Points per match",
+        [0, 1].map(|player| mean(&report.points_per_match[player])),
+    );
+    println!();
+    println!("{:<20}{}", "Unfinished", report.unfinished);
+    println!("{:<20}{} hits", "Rally length", mean(&report.rally_length));
+    println!("{:<20}{} s", "Match duration", mean(&report.match_duration));
+    println!();
+    println!("Final scores ({first} - {second})");
+    for count in &report.scores {
+        println!(
+            "  {:>3} - {:<3}{:>6} ({:.1}%)",
+            count.score[0],
+            count.score[1],
+            count.matches,
+            count.matches as f64 * 100. / report.matches as f64
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn options(seeds: Range<u64>) -> Options {
+        Options {
+            seeds,
+            ..parse_options(args(&["script:follow", "script:idle"])).unwrap()
+        }
+    }
+
+    fn report(points: [u32; 2], winner: Option<PaddleSide>) -> MatchReport {
+        MatchReport {
+            points,
+            rallies: vec![2, 4],
+            duration: 10.,
+            winner,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "{actual} is not {expected}"
+        );
+    }
+
+    #[test]
+    fn wilson_interval_of_known_rates() {
+        let half = wilson_interval(5, 10);
+        assert_close(half.value, 0.5);
+        assert_close(half.low, 0.2366);
+        assert_close(half.high, 0.7634);
+
+        let most = wilson_interval(81, 100);
+        assert_close(most.value, 0.81);
+        assert_close(most.low, 0.7222);
+        assert_close(most.high, 0.8749);
+
+        // Stays within 0 and 1 at the ends
+        let none = wilson_interval(0, 10);
+        assert_close(none.low, 0.);
+        assert_close(none.high, 0.2775);
+        let all = wilson_interval(10, 10);
+        assert_close(all.low, 0.7225);
+        assert_close(all.high, 1.);
+
+        let empty = wilson_interval(0, 0);
+        assert_eq!([empty.value, empty.low, empty.high], [0.; 3]);
+    }
+
+    #[test]
+    fn mean_interval_of_known_values() {
+        // A sample standard deviation of 1.5811, so a standard error of 0.7071 and 1.3859 either side
+        let estimate = mean_interval(&[1., 2., 3., 4., 5.]);
+        assert_close(estimate.value, 3.);
+        assert_close(estimate.low, 1.61409);
+        assert_close(estimate.high, 4.38591);
+
+        let single = mean_interval(&[4.]);
+        assert_eq!([single.value, single.low, single.high], [4.; 3]);
+        let empty = mean_interval(&[]);
+        assert_eq!([empty.value, empty.low, empty.high], [0.; 3]);
+    }
+
+    #[test]
+    fn players_swap_sides_on_odd_seeds() {
+        assert!(!first_plays_right(0));
+        assert!(first_plays_right(1));
+        assert!(!first_plays_right(42));
+        assert!(first_plays_right(43));
+    }
+
+    #[test]
+    fn summarize_counts_swapped_matches_for_the_right_player() {
+        let results = [
+            // The first player wins 5 - 2 on the left
+            (report([5, 2], Some(PaddleSide::Left)), false),
+            // The first player wins 5 - 3 on the right
+            (report([3, 5], Some(PaddleSide::Right)), true),
+            // The second player wins 5 - 1 on the left, with the first player on the right
+            (report([5, 1], Some(PaddleSide::Left)), true),
+            // Unfinished at 2 - 2
+            (report([2, 2], None), false),
+        ];
+        let report = summarize(&options(0..4), &results);
+        assert_eq!(report.matches, 4);
+        assert_eq!(report.wins, [2, 1]);
+        assert_eq!(report.unfinished, 1);
+        assert_close(report.points_per_match[0].value, (5. + 5. + 1. + 2.) / 4.);
+        assert_close(report.points_per_match[1].value, (2. + 3. + 5. + 2.) / 4.);
+        assert_close(report.rally_length.value, 3.);
+        assert_close(report.match_duration.value, 10.);
+        let scores: Vec<_> = report.scores.iter().map(|count| count.score).collect();
+        assert_eq!(scores, [[5, 3], [5, 2], [2, 2], [1, 5]]);
+    }
+
+    #[test]
+    fn parse_options_reads_the_seed_range() {
+        let options = parse_options(args(&[
+            "script:follow",
+            "script:idle",
+            "--seeds",
+            "10..20",
+            "--points",
+            "3",
+        ]))
+        .unwrap();
+        assert_eq!(options.seeds, 10..20);
+        assert_eq!(options.points_to_win, 3);
+    }
+
+    #[test]
+    fn parse_options_rejects_bad_seeds() {
+        for seeds in ["5..5", "10..2", "a..b", "5", "1..", "..5", "-1..3"] {
+            let result = parse_options(args(&["script:follow", "script:idle", "--seeds", seeds]));
+            assert!(result.is_err(), "accepted --seeds {seeds}");
+        }
+        assert!(parse_options(args(&["script:follow", "script:idle", "--seeds"])).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! Serves the training environment over stdin and stdout, one JSON object per line, so trainers in other
+//! languages can drive it as a subprocess.
+//!
+//! Requests and their replies:
+//! - `{"cmd": "reset", "seed": 42}` replies `{"observation": [...]}`. It can also take a `"config"` object
+//!   with any of the fields of `EnvConfig`, which applies from this episode on.
+//! - `{"cmd": "step", "action": 1.0}` replies `{"observation": [...], "reward": 0.0, "done": false, "info": {...}}`
+//! - `{"cmd": "close"}` ends the process, like closing stdin does.
+//!
+//! A request that cannot be handled replies `{"error": "..."}`, as does a reset with a config the environment
+//! cannot be played with, which keeps the config from before.
+
+use bevy_pong::env::{EnvConfig, Observation, PongEnv};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Write};
+
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Request {
+    Reset {
+        #[serde(default)]
+        seed: u64,
+        config: Option<EnvConfig>,
+    },
+    Step {
+        action: f32,
+    },
+    Close,
+}
+
+#[derive(Serialize)]
+struct ResetReply {
+    observation: Observation,
+}
+
+#[derive(Serialize)]
+struct ErrorReply {
+    error: String,
+}
+
+fn main() -> io::Result<()> {
+    let mut env = PongEnv::new(EnvConfig::default());
+    let mut episode_started = false;
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Reset { seed, config }) => {
+                match config.as_ref().map_or(Ok(()), EnvConfig::check) {
+                    Ok(()) => {
+                        if let Some(config) = config {
+                            env = PongEnv::new(config);
+                        }
+                        episode_started = true;
+                        serde_json::to_string(&ResetReply {
+                            observation: env.reset(seed),
+                        })
+                    }
+                    Err(error) => serde_json::to_string(&ErrorReply { error }),
+                }
+            }
+            Ok(Request::Step { action }) if episode_started => {
+                serde_json::to_string(&env.step(action))
+            }
+            Ok(Request::Step { .. }) => serde_json::to_string(&ErrorReply {
+                error: "reset the environment before stepping it".to_string(),
+            }),
+            Ok(Request::Close) => break,
+            Err(error) => serde_json::to_string(&ErrorReply {
+                error: error.to_string(),
+            }),
+        }
+        .map_err(io::Error::other)?;
+        writeln!(stdout, "{reply}")?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
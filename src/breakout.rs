@@ -0,0 +1,371 @@
+use crate::arena::Obstacle;
+use crate::data::RonAssetAppExt;
+use crate::events::{BallHitWall, Goal, MatchEnded, RoundEnd};
+use crate::game::{
+    handle_round_end, move_ball, Collider, PaddleSide, PlayingState, COURT_HALF_WIDTH, COURT_HEIGHT,
+};
+use crate::loading::LevelAssets;
+use crate::rules::{GameMode, MatchRules};
+use crate::theme::ThemeRole;
+use crate::ui::spawn_end_screen;
+use crate::{GameSet, GameState};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+pub struct BreakoutPlugin;
+
+/// This plugin turns the court into a game of breakout: the far half is filled with the bricks of a [`Level`],
+/// which break after being hit by the ball often enough.
+/// The player goes through the levels in order with the lives set in the [`MatchRules`],
+/// losing one for every ball that gets past the paddle.
+impl Plugin for BreakoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_ron_asset::<Level>(&["level.ron"])
+            .init_resource::<BreakoutRun>()
+            .add_systems(OnEnter(GameState::Playing), start_run.run_if(is_breakout))
+            .add_systems(
+                Update,
+                (
+                    break_bricks.after(move_ball).in_set(GameSet::Physics),
+                    (lose_lives.after(handle_round_end), advance_level).in_set(GameSet::Scoring),
+                    update_breakout_text.in_set(GameSet::Presentation),
+                )
+                    .run_if(is_breakout)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PlayingState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), cleanup_breakout);
+    }
+}
+
+/// A breakout level, loaded from `assets/levels/*.level.ron`.
+/// Each row of the layout is a row of bricks from the top of the court down, and each character a brick
+/// from the net to the far end: a digit is a brick breaking after that many hits, `#` a brick that never
+/// breaks and anything else a gap.
+#[derive(Asset, TypePath, Deserialize)]
+pub struct Level {
+    pub name: String,
+    pub rows: Vec<String>,
+}
+
+// Progress through the levels in the current match
+#[derive(Resource, Default)]
+pub struct BreakoutRun {
+    level: usize,
+    lives: u32,
+}
+
+#[derive(Component)]
+struct Brick {
+    hits_left: u32,
+}
+
+// Everything belonging to the level being played
+#[derive(Component)]
+struct LevelPiece;
+
+#[derive(Component)]
+struct BreakoutText;
+
+// Everything spawned for a breakout match, removed when the match is left
+#[derive(Component)]
+struct BreakoutEntity;
+
+// The bricks fill the far half of the court between these x coordinates
+const BRICKS_FROM_X: f32 = 120.0;
+const BRICKS_TO_X: f32 = COURT_HALF_WIDTH - 40.0;
+// Space between the bricks
+const BRICK_GAP: f32 = 4.0;
+
+/// All loaded levels, in the order they are played
+#[derive(SystemParam)]
+pub struct Levels<'w> {
+    assets: Res<'w, Assets<Level>>,
+    collection: Res<'w, LevelAssets>,
+}
+
+impl Levels<'_> {
+    fn get(&self, index: usize) -> Option<&Level> {
+        self.collection
+            .levels
+            .get(index)
+            .and_then(|handle| self.assets.get(handle))
+    }
+
+    fn count(&self) -> usize {
+        self.collection.levels.len()
+    }
+}
+
+fn is_breakout(rules: Res<MatchRules>) -> bool {
+    rules.mode == GameMode::Breakout
+}
+
+fn start_run(
+    mut commands: Commands,
+    rules: Res<MatchRules>,
+    levels: Levels,
+    mut run: ResMut<BreakoutRun>,
+) {
+    *run = BreakoutRun {
+        level: 0,
+        lives: rules.lives,
+    };
+    if let Some(level) = levels.get(0) {
+        spawn_level(&mut commands, level);
+    }
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 30.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        ThemeRole::UiText,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            left: Val::Px(20.0),
+            ..default()
+        },
+        BreakoutText,
+        BreakoutEntity,
+    ));
+}
+
+fn spawn_level(commands: &mut Commands, level: &Level) {
+    let rows = level.rows.len();
+    let columns = level
+        .rows
+        .iter()
+        .map(|row| row.chars().count())
+        .max()
+        .unwrap_or(0);
+    if rows == 0 || columns == 0 {
+        return;
+    }
+    let cell = Vec2::new(
+        (BRICKS_TO_X - BRICKS_FROM_X) / columns as f32,
+        COURT_HEIGHT * 2.0 / rows as f32,
+    );
+    let size = cell - Vec2::splat(BRICK_GAP);
+    for (row, bricks) in level.rows.iter().enumerate() {
+        for (column, brick) in bricks.chars().enumerate() {
+            let hits = match brick {
+                '#' => None,
+                '1'..='9' => brick.to_digit(10),
+                _ => continue,
+            };
+            let position = Vec2::new(
+                BRICKS_FROM_X + cell.x * (column as f32 + 0.5),
+                COURT_HEIGHT - cell.y * (row as f32 + 0.5),
+            );
+            let mut entity = commands.spawn((
+                Sprite {
+                    color: brick_color(hits),
+                    custom_size: Some(size),
+                    ..default()
+                },
+                Transform::from_translation(position.extend(0.)),
+                Collider(size),
+                Obstacle::fixed(position),
+                LevelPiece,
+                BreakoutEntity,
+            ));
+            if let Some(hits_left) = hits {
+                entity.insert(Brick { hits_left });
+            }
+        }
+    }
+}
+
+// Unbreakable bricks are grey, the others go from blue to orange as they wear down
+fn brick_color(hits_left: Option<u32>) -> Color {
+    match hits_left {
+        None => Color::linear_rgb(0.4, 0.4, 0.4),
+        Some(1) => Color::linear_rgb(0.9, 0.5, 0.2),
+        Some(2) => Color::linear_rgb(0.9, 0.8, 0.2),
+        Some(_) => Color::linear_rgb(0.3, 0.7, 0.9),
+    }
+}
+
+fn break_bricks(
+    mut commands: Commands,
+    mut wall_hit_events: EventReader<BallHitWall>,
+    mut brick_query: Query<(&mut Brick, &mut Sprite)>,
+) {
+    for event in wall_hit_events.read() {
+        let Some(obstacle) = event.obstacle else {
+            continue;
+        };
+        let Ok((mut brick, mut sprite)) = brick_query.get_mut(obstacle) else {
+            continue;
+        };
+        // Two balls can hit a brick in the same frame, after which it is already on its way out
+        if brick.hits_left == 0 {
+            continue;
+        }
+        brick.hits_left -= 1;
+        if brick.hits_left == 0 {
+            commands.entity(obstacle).despawn();
+        } else {
+            sprite.color = brick_color(Some(brick.hits_left));
+        }
+    }
+}
+
+// Runs after the round was reset, so a lost run ends up over instead of paused
+fn lose_lives(
+    mut commands: Commands,
+    mut goal_events: EventReader<Goal>,
+    mut run: ResMut<BreakoutRun>,
+    mut match_ended_events: EventWriter<MatchEnded>,
+    mut next_state: ResMut<NextState<PlayingState>>,
+) {
+    for goal in goal_events.read() {
+        if goal.conceded != PaddleSide::Left || run.lives == 0 {
+            continue;
+        }
+        run.lives -= 1;
+        if run.lives == 0 {
+            next_state.set(PlayingState::Over);
+            match_ended_events.send(MatchEnded {
+                winner: None,
+                points: [0; 4],
+            });
+            spawn_end_screen(
+                &mut commands,
+                "Game over",
+                vec![format!("Reached level {}", run.level + 1)],
+                BreakoutEntity,
+            );
+        }
+    }
+}
+
+// Once every breakable brick is gone the next level is set up, and the round is reset for a new serve
+#[allow(clippy::too_many_arguments)]
+fn advance_level(
+    mut commands: Commands,
+    levels: Levels,
+    mut run: ResMut<BreakoutRun>,
+    brick_query: Query<(), With<Brick>>,
+    level_query: Query<Entity, With<LevelPiece>>,
+    mut round_end_events: EventWriter<RoundEnd>,
+    mut match_ended_events: EventWriter<MatchEnded>,
+    mut next_state: ResMut<NextState<PlayingState>>,
+) {
+    if !brick_query.is_empty() || run.lives == 0 {
+        return;
+    }
+    for entity in &level_query {
+        commands.entity(entity).despawn();
+    }
+    run.level += 1;
+    match levels.get(run.level) {
+        Some(level) => {
+            spawn_level(&mut commands, level);
+            round_end_events.send(RoundEnd);
+        }
+        None => {
+            next_state.set(PlayingState::Over);
+            match_ended_events.send(MatchEnded {
+                winner: Some(PaddleSide::Left),
+                points: [0; 4],
+            });
+            spawn_end_screen(
+                &mut commands,
+                "You win!",
+                vec![format!("Cleared all {} levels", levels.count())],
+                BreakoutEntity,
+            );
+        }
+    }
+}
+
+fn update_breakout_text(
+    run: Res<BreakoutRun>,
+    levels: Levels,
+    brick_query: Query<(), With<Brick>>,
+    mut text_query: Query<&mut Text, With<BreakoutText>>,
+) {
+    let name = levels
+        .get(run.level)
+        .map_or("", |level| level.name.as_str());
+    for mut text in &mut text_query {
+        text.0 = format!(
+            "Level {}/{} {}  Lives {}  Bricks {}",
+            (run.level + 1).min(levels.count()),
+            levels.count(),
+            name,
+            run.lives,
+            brick_query.iter().count()
+        );
+    }
+}
+
+fn cleanup_breakout(mut commands: Commands, breakout_query: Query<Entity, With<BreakoutEntity>>) {
+    for entity in breakout_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::read_ron_assets;
+
+    #[test]
+    fn every_level_has_bricks_to_break() {
+        let levels = read_ron_assets::<Level>("levels", "level.ron").unwrap();
+        assert!(!levels.is_empty());
+        for (_, level) in levels {
+            assert!(
+                level.rows.iter().any(|row| row
+                    .chars()
+                    .any(|brick| brick.is_ascii_digit() && brick != '0')),
+                "{} can't be cleared",
+                level.name
+            );
+        }
+    }
+
+    #[test]
+    fn bricks_break_after_their_hits() {
+        let mut app = App::new();
+        app.add_event::<BallHitWall>()
+            .add_systems(Update, break_bricks);
+        let level = Level {
+            name: "Test".to_string(),
+            rows: vec!["2#".to_string(), " x".to_string()],
+        };
+        spawn_level(&mut app.world_mut().commands(), &level);
+        app.world_mut().flush();
+        let world = app.world_mut();
+        assert_eq!(world.query::<&LevelPiece>().iter(world).count(), 2);
+        let (brick, transform) = world
+            .query_filtered::<(Entity, &Transform), With<Brick>>()
+            .single(world);
+        assert!((BRICKS_FROM_X..BRICKS_TO_X).contains(&transform.translation.x));
+        assert!(transform.translation.y > 0.);
+        let wall = world
+            .query_filtered::<Entity, (With<LevelPiece>, Without<Brick>)>()
+            .single(world);
+
+        let hit = |app: &mut App, obstacle| {
+            app.world_mut().send_event(BallHitWall {
+                ball: Entity::PLACEHOLDER,
+                position: Vec2::ZERO,
+                obstacle: Some(obstacle),
+            });
+            app.update();
+        };
+        hit(&mut app, brick);
+        hit(&mut app, wall);
+        assert_eq!(app.world().get::<Brick>(brick).unwrap().hits_left, 1);
+        hit(&mut app, brick);
+        assert!(app.world().get_entity(brick).is_err());
+        assert!(app.world().get_entity(wall).is_ok());
+    }
+}
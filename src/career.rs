@@ -0,0 +1,439 @@
+use crate::actions::Controller;
+use crate::data::{read_ron_assets, RonAssetAppExt};
+use crate::events::MatchEnded;
+use crate::game::{
+    decided_winner, handle_round_end, AiSkill, MatchExit, MatchStats, PaddleSide, PlayingState,
+    Score,
+};
+use crate::handicap::Handicap;
+use crate::loading::OpponentAssets;
+use crate::rules::{GameMode, MatchRules};
+use crate::storage::{load_json, save_json};
+use crate::theme::ThemeRole;
+use crate::ui::{spawn_button, spawn_end_screen};
+use crate::{GameSet, GameState};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::mem;
+
+pub struct CareerPlugin;
+
+/// This plugin runs the career: a ladder of computer opponents, each with its own [`Personality`],
+/// played from the first to the last. Beating an opponent unlocks the next one, and the
+/// [`CareerProgress`] is saved so the climb can go on after restarting the game.
+/// Career matches are even, and played against the opponent itself: the handicaps and the computer
+/// policy of the setup screen are left out of them.
+impl Plugin for CareerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_ron_asset::<Personality>(&[OPPONENT_EXTENSION])
+            .insert_resource(load_json::<CareerProgress>(SAVE_NAME).unwrap_or_default())
+            .init_resource::<CareerSession>()
+            .add_systems(OnEnter(GameState::Career), setup_career_menu)
+            .add_systems(
+                Update,
+                (handle_opponent_click, handle_career_escape_key)
+                    .in_set(GameSet::Input)
+                    .run_if(in_state(GameState::Career)),
+            )
+            .add_systems(OnExit(GameState::Career), cleanup_career_menu)
+            .add_systems(
+                OnEnter(GameState::Playing),
+                spawn_opponent_text.run_if(in_career_match),
+            )
+            .add_systems(
+                Update,
+                finish_career_match
+                    .after(handle_round_end)
+                    .in_set(GameSet::Scoring)
+                    .run_if(in_career_match)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PlayingState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), cleanup_career_match);
+    }
+}
+
+/// How a computer paddle plays, loaded from `assets/opponents/*.opponent.ron` for the career ladder.
+/// Outside the career computer paddles play with the default personality.
+#[derive(Asset, TypePath, Deserialize, Clone)]
+pub struct Personality {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub speed: f32,
+    // How far in front of its goal the paddle stands, towards the net
+    #[serde(default)]
+    pub advance: f32,
+    // Goes to where the ball will reach the paddle, instead of following the ball
+    #[serde(default)]
+    pub anticipate: bool,
+    // Seconds between the paddle looking where the ball is
+    #[serde(default)]
+    pub reaction: f32,
+    // Up to how far off the paddle is from where it means to go
+    #[serde(default)]
+    pub error: f32,
+    // Degrees per second that the balls it returns curve by
+    #[serde(default)]
+    pub spin: f32,
+}
+
+impl Default for Personality {
+    fn default() -> Self {
+        Personality {
+            name: "Computer".to_string(),
+            description: String::new(),
+            speed: 250.0,
+            advance: 0.,
+            anticipate: false,
+            reaction: 0.,
+            error: 0.,
+            spin: 0.,
+        }
+    }
+}
+
+// Opponents are loaded as assets by the game, and read straight from `assets/opponents` by tools
+const OPPONENT_FOLDER: &str = "opponents";
+const OPPONENT_EXTENSION: &str = "opponent.ron";
+
+/// Every opponent in `assets/opponents` with the name of its file, read without the asset server
+pub fn read_opponents() -> Result<Vec<(String, Personality)>, String> {
+    read_ron_assets(OPPONENT_FOLDER, OPPONENT_EXTENSION)
+}
+
+const SAVE_NAME: &str = "career";
+// Points a career match is played to
+const POINTS_TO_WIN: u32 = 5;
+
+// The opponents beaten so far, by name
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct CareerProgress {
+    beaten: Vec<String>,
+}
+
+impl CareerProgress {
+    fn has_beaten(&self, opponent: &Personality) -> bool {
+        self.beaten.contains(&opponent.name)
+    }
+}
+
+// What only matters while the game is running
+#[derive(Resource, Default)]
+struct CareerSession {
+    // The ladder position of the opponent being played
+    opponent: Option<usize>,
+    // The match rules and score from before the career took them over, given back once it is left
+    saved_rules: Option<MatchRules>,
+    saved_score: Option<Score>,
+}
+
+/// The opponents of the career, in ladder order
+#[derive(SystemParam)]
+pub struct Opponents<'w> {
+    assets: Res<'w, Assets<Personality>>,
+    collection: Res<'w, OpponentAssets>,
+}
+
+impl Opponents<'_> {
+    fn get(&self, index: usize) -> Option<&Personality> {
+        self.collection
+            .opponents
+            .get(index)
+            .and_then(|handle| self.assets.get(handle))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Personality> {
+        (0..self.collection.opponents.len()).filter_map(|index| self.get(index))
+    }
+
+    // The first opponent is always open, and every other one once the one before is beaten
+    fn is_unlocked(&self, index: usize, progress: &CareerProgress) -> bool {
+        index == 0
+            || self
+                .get(index - 1)
+                .is_some_and(|previous| progress.has_beaten(previous))
+    }
+}
+
+#[derive(Component)]
+struct CareerMenu;
+
+#[derive(Component)]
+struct OpponentButton(usize);
+
+#[derive(Component)]
+struct BackButton;
+
+// Everything spawned for a career match, removed when the match is left
+#[derive(Component)]
+struct CareerEntity;
+
+fn in_career_match(session: Res<CareerSession>) -> bool {
+    session.opponent.is_some()
+}
+
+fn setup_career_menu(mut commands: Commands, opponents: Opponents, progress: Res<CareerProgress>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            CareerMenu,
+        ))
+        .with_children(|children| {
+            children.spawn((
+                Text::new("Career"),
+                TextFont {
+                    font_size: 60.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                ThemeRole::UiText,
+                Node {
+                    margin: UiRect::bottom(Val::Px(40.0)),
+                    ..default()
+                },
+            ));
+            for (index, opponent) in opponents.iter().enumerate() {
+                if !opponents.is_unlocked(index, &progress) {
+                    children.spawn((
+                        Text::new(format!("{}. Locked", index + 1)),
+                        TextFont {
+                            font_size: 25.0,
+                            ..default()
+                        },
+                        TextColor(Color::linear_rgb(0.5, 0.5, 0.5)),
+                        Node {
+                            margin: UiRect::bottom(Val::Px(20.0)),
+                            ..default()
+                        },
+                    ));
+                    continue;
+                }
+                let beaten = if progress.has_beaten(opponent) {
+                    "  (beaten)"
+                } else {
+                    ""
+                };
+                spawn_button(
+                    children,
+                    format!(
+                        "{}. {}: {}{}",
+                        index + 1,
+                        opponent.name,
+                        opponent.description,
+                        beaten
+                    ),
+                    25.0,
+                    OpponentButton(index),
+                );
+            }
+            spawn_button(children, "Back", 30.0, BackButton);
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_opponent_click(
+    opponents: Opponents,
+    progress: Res<CareerProgress>,
+    mut session: ResMut<CareerSession>,
+    mut rules: ResMut<MatchRules>,
+    mut score: ResMut<Score>,
+    mut ai_skill: ResMut<AiSkill>,
+    mut match_exit: ResMut<MatchExit>,
+    opponent_query: Query<(&Interaction, &OpponentButton), Changed<Interaction>>,
+    back_query: Query<&Interaction, (Changed<Interaction>, With<BackButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if back_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        leave_career(&mut session, &mut rules, &mut score, &mut match_exit);
+        next_state.set(GameState::Menu);
+        return;
+    }
+    for (interaction, button) in &opponent_query {
+        if *interaction != Interaction::Pressed || !opponents.is_unlocked(button.0, &progress) {
+            continue;
+        }
+        let Some(opponent) = opponents.get(button.0) else {
+            continue;
+        };
+        if session.saved_rules.is_none() {
+            session.saved_rules = Some(rules.clone());
+        }
+        rules.mode = GameMode::Classic;
+        rules.handicaps = [Handicap::default(); 4];
+        rules.policy = None;
+        rules.controllers[PaddleSide::Left.index()] = Controller::Player;
+        rules.controllers[PaddleSide::Right.index()] = Controller::Ai;
+        ai_skill.personality = opponent.clone();
+        // Every match starts from nothing, so the score shown is the one of the match
+        let saved_score = mem::take(&mut *score);
+        session.saved_score.get_or_insert(saved_score);
+        match_exit.0 = GameState::Career;
+        session.opponent = Some(button.0);
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn leave_career(
+    session: &mut CareerSession,
+    rules: &mut MatchRules,
+    score: &mut Score,
+    match_exit: &mut MatchExit,
+) {
+    if let Some(saved_rules) = session.saved_rules.take() {
+        *rules = saved_rules;
+    }
+    if let Some(saved_score) = session.saved_score.take() {
+        *score = saved_score;
+    }
+    match_exit.0 = GameState::Menu;
+}
+
+fn handle_career_escape_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut session: ResMut<CareerSession>,
+    mut rules: ResMut<MatchRules>,
+    mut score: ResMut<Score>,
+    mut match_exit: ResMut<MatchExit>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        leave_career(&mut session, &mut rules, &mut score, &mut match_exit);
+        next_state.set(GameState::Menu);
+    }
+}
+
+fn cleanup_career_menu(mut commands: Commands, menu: Query<Entity, With<CareerMenu>>) {
+    for entity in menu.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn spawn_opponent_text(mut commands: Commands, ai_skill: Res<AiSkill>) {
+    commands.spawn((
+        Text::new(format!("vs {}", ai_skill.personality.name)),
+        TextFont {
+            font_size: 30.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        ThemeRole::UiText,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            left: Val::Px(20.0),
+            ..default()
+        },
+        CareerEntity,
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn finish_career_match(
+    mut commands: Commands,
+    score: Res<Score>,
+    stats: Res<MatchStats>,
+    ai_skill: Res<AiSkill>,
+    opponents: Opponents,
+    session: Res<CareerSession>,
+    mut progress: ResMut<CareerProgress>,
+    mut match_ended_events: EventWriter<MatchEnded>,
+    mut next_state: ResMut<NextState<PlayingState>>,
+) {
+    let points = stats.match_points(&score);
+    let Some(winner) = decided_winner(
+        &points,
+        &[PaddleSide::Left, PaddleSide::Right],
+        POINTS_TO_WIN,
+    ) else {
+        return;
+    };
+    let [player, computer, ..] = points;
+    next_state.set(PlayingState::Over);
+    match_ended_events.send(MatchEnded {
+        winner: Some(winner),
+        points: [player, computer, 0, 0],
+    });
+
+    let opponent = &ai_skill.personality;
+    let mut details = vec![format!("{player} - {computer}")];
+    let title = if winner == PaddleSide::Left {
+        if !progress.has_beaten(opponent) {
+            progress.beaten.push(opponent.name.clone());
+            save_json(SAVE_NAME, &*progress);
+            let next = session.opponent.and_then(|index| opponents.get(index + 1));
+            details.push(match next {
+                Some(next) => format!("Unlocked: {}", next.name),
+                None => "You reached the top of the ladder!".to_string(),
+            });
+        }
+        format!("You beat {}!", opponent.name)
+    } else {
+        format!("{} wins", opponent.name)
+    };
+    spawn_end_screen(&mut commands, title, details, CareerEntity);
+}
+
+fn cleanup_career_match(
+    mut commands: Commands,
+    mut session: ResMut<CareerSession>,
+    career_query: Query<Entity, With<CareerEntity>>,
+) {
+    for entity in career_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    session.opponent = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_opponent_loads_with_its_own_name() {
+        let opponents = read_opponents().unwrap();
+        assert!(!opponents.is_empty());
+        // Progress is kept by name
+        for (index, (_, opponent)) in opponents.iter().enumerate() {
+            assert!(opponents[..index]
+                .iter()
+                .all(|(_, other)| other.name != opponent.name));
+        }
+    }
+
+    #[test]
+    fn leaving_gives_back_the_rules_and_score() {
+        let mut menu_score = Score::default();
+        menu_score.add_point(PaddleSide::Right);
+        let mut session = CareerSession {
+            opponent: None,
+            saved_rules: Some(MatchRules {
+                lives: 7,
+                ..default()
+            }),
+            saved_score: Some(menu_score),
+        };
+        let mut rules = MatchRules::default();
+        let mut score = Score::default();
+        score.add_point(PaddleSide::Left);
+        let mut match_exit = MatchExit(GameState::Career);
+
+        leave_career(&mut session, &mut rules, &mut score, &mut match_exit);
+        assert_eq!(rules.lives, 7);
+        assert_eq!(score.get(PaddleSide::Left), 0);
+        assert_eq!(score.get(PaddleSide::Right), 1);
+        assert!(match_exit.0 == GameState::Menu);
+        assert!(session.saved_rules.is_none() && session.saved_score.is_none());
+    }
+}
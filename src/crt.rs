@@ -0,0 +1,288 @@
+use crate::camera::MainCamera;
+use crate::settings::Settings;
+use crate::GameSet;
+use bevy::core_pipeline::bloom::Bloom;
+use bevy::core_pipeline::core_2d::graph::{Core2d, Node2d};
+use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
+use bevy::ecs::query::QueryItem;
+use bevy::image::BevyDefault;
+use bevy::prelude::*;
+use bevy::render::extract_component::{
+    ComponentUniforms, DynamicUniformIndex, ExtractComponent, ExtractComponentPlugin,
+    UniformComponentPlugin,
+};
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+};
+use bevy::render::render_resource::binding_types::{sampler, texture_2d, uniform_buffer};
+use bevy::render::render_resource::*;
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use bevy::render::view::ViewTarget;
+use bevy::render::RenderApp;
+
+pub struct CrtPlugin;
+
+/// This plugin adds an optional retro CRT look to the `MainCamera`: scanlines, screen curvature and chromatic
+/// aberration in a single full screen pass after tonemapping, plus Bevy's bloom on top of the bright sprites.
+/// Bloom needs an HDR camera, which the WebGL2 build cannot rely on, so there it is skipped.
+impl Plugin for CrtPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ExtractComponentPlugin::<Crt>::default(),
+            UniformComponentPlugin::<Crt>::default(),
+        ))
+        .add_systems(Update, apply_crt_settings.in_set(GameSet::Presentation));
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<CrtNode>>(Core2d, CrtLabel)
+            .add_render_graph_edges(
+                Core2d,
+                (
+                    Node2d::Tonemapping,
+                    CrtLabel,
+                    Node2d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<CrtPipeline>();
+    }
+}
+
+// Bloom needs an HDR render target, which WebGL2 does not guarantee
+pub const BLOOM_SUPPORTED: bool = !cfg!(target_arch = "wasm32");
+
+const SHADER_ASSET_PATH: &str = "shaders/crt.wgsl";
+
+pub struct CrtSettings {
+    pub enabled: bool,
+    // Each of these goes from 0 (off) to 1 (strongest)
+    pub scanlines: f32,
+    pub curvature: f32,
+    pub chromatic_aberration: f32,
+    pub bloom: f32,
+}
+
+impl Default for CrtSettings {
+    fn default() -> Self {
+        CrtSettings {
+            enabled: false,
+            scanlines: 0.5,
+            curvature: 0.5,
+            chromatic_aberration: 0.5,
+            bloom: 0.5,
+        }
+    }
+}
+
+// The parameters of the CRT pass for one camera, as the shader sees them
+#[derive(Component, Default, Clone, Copy, ExtractComponent, ShaderType)]
+pub struct Crt {
+    pub scanlines: f32,
+    pub curvature: f32,
+    pub chromatic_aberration: f32,
+    // Keeps the uniform at 16 bytes, which WebGL2 requires
+    _padding: f32,
+}
+
+fn apply_crt_settings(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut camera_query: Query<(Entity, &mut Camera), With<MainCamera>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    let crt = &settings.crt;
+    for (entity, mut camera) in &mut camera_query {
+        let mut camera_commands = commands.entity(entity);
+        if crt.enabled {
+            camera_commands.insert(Crt {
+                scanlines: crt.scanlines,
+                curvature: crt.curvature,
+                chromatic_aberration: crt.chromatic_aberration,
+                _padding: 0.,
+            });
+        } else {
+            camera_commands.remove::<Crt>();
+        }
+
+        let bloom = crt.enabled && crt.bloom > 0. && BLOOM_SUPPORTED;
+        if bloom {
+            camera_commands.insert(Bloom {
+                intensity: 0.3 * crt.bloom,
+                ..Bloom::NATURAL
+            });
+        } else {
+            camera_commands.remove::<Bloom>();
+        }
+        if camera.hdr != bloom {
+            camera.hdr = bloom;
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct CrtLabel;
+
+#[derive(Default)]
+struct CrtNode;
+
+impl ViewNode for CrtNode {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static Crt,
+        &'static DynamicUniformIndex<Crt>,
+    );
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, _crt, crt_index): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let crt_pipeline = world.resource::<CrtPipeline>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        // The main texture has a different format on HDR cameras, so each needs its own pipeline
+        let pipeline_id = if view_target.is_hdr() {
+            crt_pipeline.hdr_pipeline_id
+        } else {
+            crt_pipeline.pipeline_id
+        };
+        // Still compiling, or not supported
+        let Some(pipeline) = pipeline_cache.get_render_pipeline(pipeline_id) else {
+            return Ok(());
+        };
+        let crt_uniforms = world.resource::<ComponentUniforms<Crt>>();
+        let Some(crt_binding) = crt_uniforms.uniforms().binding() else {
+            return Ok(());
+        };
+
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "crt_bind_group",
+            &crt_pipeline.layout,
+            &BindGroupEntries::sequential((
+                post_process.source,
+                &crt_pipeline.sampler,
+                crt_binding.clone(),
+            )),
+        );
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("crt_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[crt_index.index()]);
+        render_pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+struct CrtPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    pipeline_id: CachedRenderPipelineId,
+    hdr_pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for CrtPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "crt_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    uniform_buffer::<Crt>(true),
+                ),
+            ),
+        );
+        let sampler = render_device.create_sampler(&SamplerDescriptor::default());
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_descriptor = |format: TextureFormat| RenderPipelineDescriptor {
+            label: Some("crt_pipeline".into()),
+            layout: vec![layout.clone()],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: shader.clone(),
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            push_constant_ranges: vec![],
+            zero_initialize_workgroup_memory: false,
+        };
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let pipeline_id = pipeline_cache
+            .queue_render_pipeline(pipeline_descriptor(TextureFormat::bevy_default()));
+        let hdr_pipeline_id = pipeline_cache
+            .queue_render_pipeline(pipeline_descriptor(ViewTarget::TEXTURE_FORMAT_HDR));
+
+        CrtPipeline {
+            layout,
+            sampler,
+            pipeline_id,
+            hdr_pipeline_id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_switch_the_crt_pass_and_bloom_on_the_camera() {
+        let mut app = App::new();
+        app.init_resource::<Settings>()
+            .add_systems(Update, apply_crt_settings);
+        let camera = app.world_mut().spawn((MainCamera, Camera::default())).id();
+        {
+            let mut settings = app.world_mut().resource_mut::<Settings>();
+            settings.crt.enabled = true;
+            settings.crt.scanlines = 0.25;
+        }
+        app.update();
+        let world = app.world();
+        assert_eq!(world.get::<Crt>(camera).unwrap().scanlines, 0.25);
+        assert_eq!(world.get::<Bloom>(camera).is_some(), BLOOM_SUPPORTED);
+        assert_eq!(world.get::<Camera>(camera).unwrap().hdr, BLOOM_SUPPORTED);
+
+        // No bloom leaves the camera without HDR
+        app.world_mut().resource_mut::<Settings>().crt.bloom = 0.;
+        app.update();
+        assert!(app.world().get::<Bloom>(camera).is_none());
+        assert!(!app.world().get::<Camera>(camera).unwrap().hdr);
+
+        app.world_mut().resource_mut::<Settings>().crt.enabled = false;
+        app.update();
+        assert!(app.world().get::<Crt>(camera).is_none());
+    }
+}
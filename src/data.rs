@@ -0,0 +1,134 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::Path;
+
+// Where the asset server loads assets from, relative to where the game was started
+const ASSET_DIR: &str = "assets";
+
+/// Game content defined as data (themes, arenas, ...) is written in RON and loaded as assets.
+/// Each asset type gets its own file extension, so `classic.theme.ron` is loaded as a `Theme`.
+pub trait RonAssetAppExt {
+    fn add_ron_asset<A: Asset + DeserializeOwned>(
+        &mut self,
+        extensions: &'static [&'static str],
+    ) -> &mut Self;
+}
+
+impl RonAssetAppExt for App {
+    fn add_ron_asset<A: Asset + DeserializeOwned>(
+        &mut self,
+        extensions: &'static [&'static str],
+    ) -> &mut Self {
+        self.init_asset::<A>()
+            .register_asset_loader(RonAssetLoader::<A> {
+                extensions,
+                _marker: PhantomData,
+            })
+    }
+}
+
+struct RonAssetLoader<A> {
+    extensions: &'static [&'static str],
+    _marker: PhantomData<fn() -> A>,
+}
+
+impl<A: Asset + DeserializeOwned> AssetLoader for RonAssetLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = RonAssetError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<A, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        parse_ron(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
+
+fn parse_ron<A: DeserializeOwned>(bytes: &[u8]) -> Result<A, RonAssetError> {
+    Ok(ron::de::from_bytes(bytes)?)
+}
+
+/// Reads the assets with the given extension in a folder of `assets` straight from their files, for tools that
+/// run without the asset server. Every asset comes with the name of its file without the extension, in the
+/// order of the file names.
+pub fn read_ron_assets<A: DeserializeOwned>(
+    folder: &str,
+    extension: &str,
+) -> Result<Vec<(String, A)>, String> {
+    let dir = Path::new(ASSET_DIR).join(folder);
+    let entries = fs::read_dir(&dir).map_err(|error| format!("{}: {error}", dir.display()))?;
+    let suffix = format!(".{extension}");
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let stem = path
+                .file_name()?
+                .to_str()?
+                .strip_suffix(&suffix)?
+                .to_string();
+            Some((stem, path))
+        })
+        .collect();
+    files.sort();
+    files
+        .into_iter()
+        .map(|(stem, path)| {
+            fs::read(&path)
+                .map_err(RonAssetError::from)
+                .and_then(|bytes| parse_ron(&bytes))
+                .map(|asset| (stem, asset))
+                .map_err(|error| format!("{}: {error}", path.display()))
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum RonAssetError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for RonAssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RonAssetError::Io(error) => write!(f, "could not read asset: {error}"),
+            RonAssetError::Ron(error) => write!(f, "could not parse asset: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for RonAssetError {}
+
+impl From<std::io::Error> for RonAssetError {
+    fn from(error: std::io::Error) -> Self {
+        RonAssetError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for RonAssetError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        RonAssetError::Ron(error)
+    }
+}
+
+/// Reads a color written as a hex string, like `"#ff8800"`
+pub fn hex_color<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let hex = <String as serde::Deserialize>::deserialize(deserializer)?;
+    Srgba::hex(&hex)
+        .map(Color::from)
+        .map_err(|error| serde::de::Error::custom(format!("invalid color {hex:?}: {error}")))
+}
@@ -0,0 +1,391 @@
+//! Matches between computer paddles, played without anyone at the controls.
+//!
+//! When the menu sits idle for a while, an attract mode match between two random computer opponents plays
+//! behind it, dimmed, until a key, click or touch brings the menu back. An [`Exhibition`] plays a series of
+//! them between two chosen opponents instead of showing the menu, sped up, and sends how each one went as
+//! events, which the game binary prints.
+
+use crate::actions::Controller;
+use crate::career::{Opponents, Personality};
+use crate::events::MatchEnded;
+use crate::game::{
+    decided_winner, end_unfinished_match, handle_round_end, AiSkill, GameRng, MatchExit,
+    MatchStats, PaddleSide, PlayingState, Score,
+};
+use crate::handicap::Handicap;
+use crate::rules::{GameMode, MatchRules};
+use crate::ui::ChangeState;
+use crate::{ExitGame, GameSet, GameState};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use rand::seq::IteratorRandom;
+use std::mem;
+
+pub struct DemoPlugin {
+    // Whether an idle menu starts an attract mode match, which needs the menu of the game
+    pub attract: bool,
+}
+
+/// This plugin plays attract mode matches behind the idle menu, and the matches of an [`Exhibition`]
+impl Plugin for DemoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuIdle>()
+            .init_resource::<ExhibitionResults>()
+            .add_event::<ExhibitionMatchPlayed>()
+            .add_event::<ExhibitionFinished>()
+            .add_systems(
+                Update,
+                (
+                    start_exhibition_match
+                        .in_set(GameSet::Input)
+                        .run_if(resource_exists::<Exhibition>)
+                        .run_if(in_state(GameState::Menu)),
+                    (
+                        (
+                            end_attract_match.run_if(attract_running),
+                            resume_demo_match.run_if(in_state(PlayingState::Paused)),
+                        )
+                            .in_set(GameSet::Input),
+                        finish_demo_match
+                            .after(handle_round_end)
+                            .in_set(GameSet::Scoring)
+                            .run_if(in_state(PlayingState::Playing)),
+                    )
+                        .run_if(resource_exists::<DemoMatch>)
+                        .run_if(in_state(GameState::Playing)),
+                ),
+            )
+            .add_systems(
+                OnExit(GameState::Playing),
+                restore_after_demo
+                    .after(end_unfinished_match)
+                    .run_if(resource_exists::<DemoMatch>),
+            );
+        if self.attract {
+            app.add_systems(OnEnter(GameState::Menu), reset_menu_idle)
+                .add_systems(
+                    Update,
+                    start_attract_match
+                        .in_set(GameSet::Input)
+                        .run_if(not(resource_exists::<Exhibition>))
+                        .run_if(in_state(GameState::Menu)),
+                );
+        }
+    }
+}
+
+/// The fastest an [`Exhibition`] can run. Any faster and a fast ball can move further than it and a paddle
+/// are wide in one frame at 60 frames per second, slipping through the paddle.
+pub const MAX_TIME_SCALE: f32 = 4.0;
+
+// Seconds the menu has to sit idle before an attract mode match starts
+const ATTRACT_DELAY: f32 = 20.0;
+// Points that decide an attract mode match
+const ATTRACT_POINTS: u32 = 5;
+// Seconds a demo match waits before every serve
+const SERVE_DELAY: f32 = 1.0;
+
+/// A series of matches between two computer opponents, played in place of the menu, after which the game
+/// closes. The result of every match and the totals are sent as [`ExhibitionMatchPlayed`] and
+/// [`ExhibitionFinished`], to compare how the opponents play.
+#[derive(Resource, Clone)]
+pub struct Exhibition {
+    /// Name of the opponent playing the left paddle, one of `assets/opponents` or "Computer" for the default
+    pub left: String,
+    /// Name of the opponent playing the right paddle
+    pub right: String,
+    pub matches: u32,
+    pub points_to_win: u32,
+    /// How many times faster than normal the matches run, up to [`MAX_TIME_SCALE`]
+    pub time_scale: f32,
+}
+
+impl Default for Exhibition {
+    fn default() -> Self {
+        Exhibition {
+            left: "Computer".to_string(),
+            right: "Computer".to_string(),
+            matches: 1,
+            points_to_win: 5,
+            time_scale: MAX_TIME_SCALE,
+        }
+    }
+}
+
+/// A match of an [`Exhibition`] was decided
+#[derive(Event, Clone, Debug)]
+pub struct ExhibitionMatchPlayed {
+    /// Matches played so far, this one included
+    pub number: u32,
+    /// Points of the left and right opponents
+    pub points: [u32; 2],
+    /// Seconds of game time the match lasted
+    pub duration: f32,
+    pub longest_rally: u32,
+}
+
+/// An [`Exhibition`] is over, sent as the game starts to close
+#[derive(Event, Clone, Debug)]
+pub struct ExhibitionFinished {
+    pub played: u32,
+    /// Matches won by the left and right opponents
+    pub wins: [u32; 2],
+    /// Points scored by the left and right opponents over all matches
+    pub points: [u32; 2],
+}
+
+/// A match between computers running in place of the menu. What it changed to play is given back when it
+/// ends.
+#[derive(Resource)]
+pub struct DemoMatch {
+    // The rules of the next match, which the menu shows
+    pub saved_rules: MatchRules,
+    // The score of the matches played before, which the menu shows
+    pub saved_score: Score,
+    // Shown behind the menu, as opposed to the matches of an exhibition
+    attract: bool,
+    since_round_end: f32,
+}
+
+// Seconds without any input on the menu
+#[derive(Resource, Default)]
+struct MenuIdle(f32);
+
+#[derive(Resource, Default)]
+struct ExhibitionResults {
+    played: u32,
+    wins: [u32; 2],
+    points: [u32; 2],
+    // Set while a match is on, so a match left before it is decided ends the exhibition
+    in_match: bool,
+}
+
+pub fn attract_running(demo: Option<Res<DemoMatch>>) -> bool {
+    demo.is_some_and(|demo| demo.attract)
+}
+
+// Everything a demo match takes over from the menu
+#[derive(SystemParam)]
+struct DemoSetup<'w, 's> {
+    commands: Commands<'w, 's>,
+    rules: ResMut<'w, MatchRules>,
+    score: ResMut<'w, Score>,
+    ai_skill: ResMut<'w, AiSkill>,
+    match_exit: ResMut<'w, MatchExit>,
+    next_state: ResMut<'w, NextState<GameState>>,
+}
+
+impl DemoSetup<'_, '_> {
+    fn start(&mut self, left: Personality, right: Personality, attract: bool) {
+        self.commands.insert_resource(DemoMatch {
+            saved_rules: self.rules.clone(),
+            saved_score: mem::take(&mut *self.score),
+            attract,
+            since_round_end: 0.,
+        });
+        self.rules.mode = GameMode::Classic;
+        for side in [PaddleSide::Left, PaddleSide::Right] {
+            self.rules.controllers[side.index()] = Controller::Ai;
+            self.rules.handicaps[side.index()] = Handicap::default();
+        }
+        // Policies are picked for the player's matches, the demo shows the opponents as they are
+        self.rules.policy = None;
+        self.ai_skill.personality = right;
+        self.ai_skill.sides[PaddleSide::Left.index()] = Some(left);
+        self.match_exit.0 = GameState::Menu;
+        self.next_state.set(GameState::Playing);
+    }
+}
+
+// The opponent of the career with this name, or the default computer
+fn find_opponent(opponents: &Opponents, name: &str) -> Option<Personality> {
+    let default = Personality::default();
+    if default.name.eq_ignore_ascii_case(name) {
+        return Some(default);
+    }
+    opponents
+        .iter()
+        .find(|opponent| opponent.name.eq_ignore_ascii_case(name))
+        .cloned()
+}
+
+fn reset_menu_idle(mut idle: ResMut<MenuIdle>) {
+    idle.0 = 0.;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_attract_match(
+    time: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    touch_input: Res<Touches>,
+    interaction_query: Query<(), Changed<Interaction>>,
+    opponents: Opponents,
+    mut rng: ResMut<GameRng>,
+    mut idle: ResMut<MenuIdle>,
+    mut setup: DemoSetup,
+) {
+    // Hovering over the buttons counts as using the menu too
+    if keyboard_input.get_pressed().next().is_some()
+        || mouse_input.get_pressed().next().is_some()
+        || touch_input.iter().next().is_some()
+        || !interaction_query.is_empty()
+    {
+        idle.0 = 0.;
+        return;
+    }
+    idle.0 += time.delta_secs();
+    if idle.0 < ATTRACT_DELAY {
+        return;
+    }
+    idle.0 = 0.;
+    let mut pick = || {
+        opponents
+            .iter()
+            .choose(&mut rng.0)
+            .cloned()
+            .unwrap_or_default()
+    };
+    let (left, right) = (pick(), pick());
+    setup.start(left, right, true);
+}
+
+// The game takes a few frames to close, in which the menu must not start another match
+#[allow(clippy::too_many_arguments)]
+fn start_exhibition_match(
+    exhibition: Res<Exhibition>,
+    opponents: Opponents,
+    mut results: ResMut<ExhibitionResults>,
+    mut closing: Local<bool>,
+    mut time: ResMut<Time<Virtual>>,
+    mut exit_events: EventWriter<ExitGame>,
+    mut finished_events: EventWriter<ExhibitionFinished>,
+    mut setup: DemoSetup,
+) {
+    if *closing {
+        return;
+    }
+    // A match left before it was decided ends the exhibition early
+    if results.in_match || results.played >= exhibition.matches {
+        finished_events.send(ExhibitionFinished {
+            played: results.played,
+            wins: results.wins,
+            points: results.points,
+        });
+        *closing = true;
+        exit_events.send(ExitGame);
+        return;
+    }
+    let left = find_opponent(&opponents, &exhibition.left);
+    let right = find_opponent(&opponents, &exhibition.right);
+    let (Some(left), Some(right)) = (left, right) else {
+        let mut names = vec![Personality::default().name];
+        names.extend(opponents.iter().map(|opponent| opponent.name.clone()));
+        error!(
+            "Unknown opponent in {} vs {}, choose from: {}",
+            exhibition.left,
+            exhibition.right,
+            names.join(", ")
+        );
+        *closing = true;
+        exit_events.send(ExitGame);
+        return;
+    };
+    results.in_match = true;
+    time.set_relative_speed(exhibition.time_scale.clamp(0.1, MAX_TIME_SCALE));
+    setup.start(left, right, false);
+}
+
+// Any key, click or touch brings the menu back, unless it pressed one of the menu's buttons
+fn end_attract_match(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    touch_input: Res<Touches>,
+    button_query: Query<&Interaction, (Changed<Interaction>, With<ChangeState>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let pressed = keyboard_input.get_just_pressed().next().is_some()
+        || mouse_input.get_just_pressed().next().is_some()
+        || touch_input.any_just_pressed();
+    let on_button = button_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed);
+    if pressed && !on_button {
+        next_state.set(GameState::Menu);
+    }
+}
+
+// Nobody is there to serve, so the match goes on by itself after every point
+fn resume_demo_match(
+    time: Res<Time>,
+    mut demo: ResMut<DemoMatch>,
+    mut next_state: ResMut<NextState<PlayingState>>,
+) {
+    demo.since_round_end += time.delta_secs();
+    if demo.since_round_end >= SERVE_DELAY {
+        demo.since_round_end = 0.;
+        next_state.set(PlayingState::Playing);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn finish_demo_match(
+    score: Res<Score>,
+    demo: Res<DemoMatch>,
+    stats: Res<MatchStats>,
+    exhibition: Option<Res<Exhibition>>,
+    mut results: ResMut<ExhibitionResults>,
+    mut match_ended_events: EventWriter<MatchEnded>,
+    mut played_events: EventWriter<ExhibitionMatchPlayed>,
+    mut next_playing_state: ResMut<NextState<PlayingState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let points_to_win = match &exhibition {
+        Some(exhibition) if !demo.attract => exhibition.points_to_win,
+        _ => ATTRACT_POINTS,
+    };
+    let points = stats.match_points(&score);
+    let Some(winner) = decided_winner(
+        &points,
+        &[PaddleSide::Left, PaddleSide::Right],
+        points_to_win,
+    ) else {
+        return;
+    };
+    let [left, right, ..] = points;
+    next_playing_state.set(PlayingState::Over);
+    match_ended_events.send(MatchEnded {
+        winner: Some(winner),
+        points: [left, right, 0, 0],
+    });
+    // Back to the menu, which starts the next match of an exhibition
+    next_state.set(GameState::Menu);
+
+    if exhibition.is_none() || demo.attract {
+        return;
+    }
+    results.played += 1;
+    results.wins[winner.index()] += 1;
+    results.points[0] += left;
+    results.points[1] += right;
+    results.in_match = false;
+    played_events.send(ExhibitionMatchPlayed {
+        number: results.played,
+        points: [left, right],
+        duration: stats.duration,
+        longest_rally: stats.longest_rally,
+    });
+}
+
+fn restore_after_demo(
+    mut commands: Commands,
+    mut demo: ResMut<DemoMatch>,
+    mut rules: ResMut<MatchRules>,
+    mut score: ResMut<Score>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    *rules = demo.saved_rules.clone();
+    *score = mem::take(&mut demo.saved_score);
+    time.set_relative_speed(1.0);
+    commands.remove_resource::<DemoMatch>();
+}
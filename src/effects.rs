@@ -0,0 +1,336 @@
+use crate::camera::MainCamera;
+use crate::events::{BallHitPaddle, BallHitWall, Goal};
+use crate::game::{Ball, PlayingState};
+use crate::settings::Settings;
+use crate::theme::ThemeRole;
+use crate::{GameSet, GameState};
+use bevy::prelude::*;
+use rand::Rng;
+
+pub struct EffectsPlugin;
+
+/// This plugin adds purely cosmetic effects on top of the gameplay: a motion trail behind the ball,
+/// particle bursts where the ball bounces, a paddle flash on hits and a camera shake on goals.
+/// Everything is driven by the gameplay events, and each effect can be turned off in the [`Settings`].
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_ball_trail
+                    .run_if(in_state(PlayingState::Playing))
+                    .run_if(|settings: Res<Settings>| settings.effects.trail_enabled()),
+                spawn_hit_particles
+                    .run_if(|settings: Res<Settings>| settings.effects.particles_enabled()),
+                flash_hit_paddles.run_if(|settings: Res<Settings>| settings.effects.paddle_flash),
+                shake_camera_on_goal
+                    .run_if(|settings: Res<Settings>| settings.effects.screen_shake_enabled()),
+                move_particles,
+                fade_effects,
+                fade_paddle_flash,
+                update_camera_shake,
+            )
+                .in_set(GameSet::Presentation)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), cleanup_effects);
+    }
+}
+
+pub struct EffectSettings {
+    pub trail: bool,
+    pub particles: bool,
+    pub screen_shake: bool,
+    pub paddle_flash: bool,
+    // Master switch for everything that moves on its own (trail, particles and shake)
+    pub reduced_motion: bool,
+}
+
+impl Default for EffectSettings {
+    fn default() -> Self {
+        EffectSettings {
+            trail: true,
+            particles: true,
+            screen_shake: true,
+            paddle_flash: true,
+            reduced_motion: false,
+        }
+    }
+}
+
+impl EffectSettings {
+    pub fn trail_enabled(&self) -> bool {
+        self.trail && !self.reduced_motion
+    }
+
+    pub fn particles_enabled(&self) -> bool {
+        self.particles && !self.reduced_motion
+    }
+
+    pub fn screen_shake_enabled(&self) -> bool {
+        self.screen_shake && !self.reduced_motion
+    }
+}
+
+const TRAIL_INTERVAL: f32 = 0.02;
+const TRAIL_LIFETIME: f32 = 0.25;
+const PARTICLES_PER_HIT: usize = 10;
+const PARTICLE_LIFETIME: f32 = 0.4;
+const PARTICLE_SPEED: f32 = 250.0;
+const FLASH_DURATION: f32 = 0.15;
+const FLASH_COLOR: Color = Color::linear_rgb(1.0, 0.8, 0.3);
+const SHAKE_DURATION: f32 = 0.3;
+const SHAKE_STRENGTH: f32 = 12.0;
+
+// Effect sprites fade out over their lifetime and are despawned at the end of it
+#[derive(Component)]
+struct Fade {
+    timer: Timer,
+    start_alpha: f32,
+}
+
+impl Fade {
+    fn new(lifetime: f32, start_alpha: f32) -> Self {
+        Fade {
+            timer: Timer::from_seconds(lifetime, TimerMode::Once),
+            start_alpha,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+}
+
+#[derive(Component)]
+struct Flash {
+    timer: Timer,
+    base_color: Color,
+}
+
+#[derive(Component)]
+struct Shake {
+    timer: Timer,
+    // Where the camera was before it started shaking, which it goes back to
+    base: Vec3,
+}
+
+fn spawn_ball_trail(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut since_last: Local<f32>,
+    ball_query: Query<(&Transform, &Sprite), With<Ball>>,
+) {
+    *since_last += time.delta_secs();
+    if *since_last < TRAIL_INTERVAL {
+        return;
+    }
+    *since_last = 0.;
+    for (transform, sprite) in &ball_query {
+        commands.spawn((
+            Sprite {
+                color: sprite.color.with_alpha(0.4),
+                ..sprite.clone()
+            },
+            // Just behind the ball
+            Transform::from_translation(transform.translation.with_z(0.5)),
+            Fade::new(TRAIL_LIFETIME, 0.4),
+        ));
+    }
+}
+
+fn spawn_hit_particles(
+    mut commands: Commands,
+    mut paddle_hit_events: EventReader<BallHitPaddle>,
+    mut wall_hit_events: EventReader<BallHitWall>,
+) {
+    let mut rng = rand::thread_rng();
+    let positions = paddle_hit_events
+        .read()
+        .map(|event| event.position)
+        .chain(wall_hit_events.read().map(|event| event.position));
+    for position in positions {
+        for _ in 0..PARTICLES_PER_HIT {
+            let direction = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU));
+            commands.spawn((
+                Sprite {
+                    color: Color::WHITE,
+                    custom_size: Some(Vec2::splat(4.0)),
+                    ..default()
+                },
+                Transform::from_translation(position.extend(0.5)),
+                Particle {
+                    velocity: direction * PARTICLE_SPEED * rng.gen_range(0.3..1.0),
+                },
+                Fade::new(PARTICLE_LIFETIME, 1.0),
+                ThemeRole::Ball,
+            ));
+        }
+    }
+}
+
+fn flash_hit_paddles(
+    mut commands: Commands,
+    mut paddle_hit_events: EventReader<BallHitPaddle>,
+    mut paddle_query: Query<(&mut Sprite, Option<&Flash>)>,
+) {
+    for event in paddle_hit_events.read() {
+        let Ok((mut sprite, flash)) = paddle_query.get_mut(event.paddle) else {
+            continue;
+        };
+        // A paddle hit again mid flash keeps the color it had before the first one
+        let base_color = flash.map_or(sprite.color, |flash| flash.base_color);
+        sprite.color = FLASH_COLOR;
+        commands.entity(event.paddle).insert(Flash {
+            timer: Timer::from_seconds(FLASH_DURATION, TimerMode::Once),
+            base_color,
+        });
+    }
+}
+
+fn shake_camera_on_goal(
+    mut commands: Commands,
+    mut goal_events: EventReader<Goal>,
+    camera_query: Query<(Entity, &Transform, Option<&Shake>), With<MainCamera>>,
+) {
+    if goal_events.read().count() == 0 {
+        return;
+    }
+    for (camera, transform, shake) in &camera_query {
+        // A camera still shaking from the last goal keeps the position it started from
+        let base = shake.map_or(transform.translation, |shake| shake.base);
+        commands.entity(camera).insert(Shake {
+            timer: Timer::from_seconds(SHAKE_DURATION, TimerMode::Once),
+            base,
+        });
+    }
+}
+
+fn move_particles(time: Res<Time>, mut particle_query: Query<(&mut Transform, &Particle)>) {
+    for (mut transform, particle) in &mut particle_query {
+        transform.translation += (particle.velocity * time.delta_secs()).extend(0.);
+    }
+}
+
+fn fade_effects(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut fade_query: Query<(Entity, &mut Fade, &mut Sprite)>,
+) {
+    for (entity, mut fade, mut sprite) in &mut fade_query {
+        fade.timer.tick(time.delta());
+        if fade.timer.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            sprite
+                .color
+                .set_alpha(fade.start_alpha * fade.timer.fraction_remaining());
+        }
+    }
+}
+
+fn fade_paddle_flash(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut flash_query: Query<(Entity, &mut Flash, &mut Sprite)>,
+) {
+    for (entity, mut flash, mut sprite) in &mut flash_query {
+        flash.timer.tick(time.delta());
+        if flash.timer.finished() {
+            sprite.color = flash.base_color;
+            commands.entity(entity).remove::<Flash>();
+        } else {
+            sprite.color = FLASH_COLOR.mix(&flash.base_color, flash.timer.fraction());
+        }
+    }
+}
+
+fn update_camera_shake(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut camera_query: Query<(Entity, &mut Shake, &mut Transform), With<MainCamera>>,
+) {
+    let mut rng = rand::thread_rng();
+    for (entity, mut shake, mut transform) in &mut camera_query {
+        shake.timer.tick(time.delta());
+        transform.translation = shake.base;
+        if shake.timer.finished() {
+            commands.entity(entity).remove::<Shake>();
+            continue;
+        }
+        let strength = SHAKE_STRENGTH * shake.timer.fraction_remaining();
+        transform.translation.x += rng.gen_range(-strength..=strength);
+        transform.translation.y += rng.gen_range(-strength..=strength);
+    }
+}
+
+fn cleanup_effects(
+    mut commands: Commands,
+    effects: Query<Entity, Or<(With<Fade>, With<Particle>)>>,
+    mut camera_query: Query<(Entity, &Shake, &mut Transform), With<MainCamera>>,
+) {
+    for entity in effects.iter() {
+        commands.entity(entity).despawn();
+    }
+    for (entity, shake, mut transform) in &mut camera_query {
+        transform.translation = shake.base;
+        commands.entity(entity).remove::<Shake>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::PaddleSide;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    #[test]
+    fn reduced_motion_turns_off_moving_effects() {
+        let mut settings = EffectSettings::default();
+        assert!(settings.trail_enabled() && settings.particles_enabled());
+        settings.reduced_motion = true;
+        assert!(!settings.trail_enabled());
+        assert!(!settings.particles_enabled());
+        assert!(!settings.screen_shake_enabled());
+        // The flash stays in place, so it is left on
+        assert!(settings.paddle_flash);
+    }
+
+    #[test]
+    fn camera_shakes_around_where_it_was() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                0.05,
+            )))
+            .add_event::<Goal>()
+            .add_systems(Update, (shake_camera_on_goal, update_camera_shake).chain());
+        let base = Vec3::new(40., -25., 0.);
+        let camera = app
+            .world_mut()
+            .spawn((MainCamera, Transform::from_translation(base)))
+            .id();
+        let goal = Goal {
+            ball: Entity::PLACEHOLDER,
+            conceded: PaddleSide::Left,
+            scorer: Some(PaddleSide::Right),
+        };
+        app.update();
+        for _ in 0..3 {
+            app.world_mut().send_event(goal.clone());
+            app.update();
+            let translation = app.world().get::<Transform>(camera).unwrap().translation;
+            assert!((translation - base).abs().max_element() <= SHAKE_STRENGTH);
+        }
+        for _ in 0..10 {
+            app.update();
+        }
+        assert_eq!(
+            app.world().get::<Transform>(camera).unwrap().translation,
+            base
+        );
+        assert!(app.world().get::<Shake>(camera).is_none());
+    }
+}
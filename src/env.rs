@@ -0,0 +1,503 @@
+//! A headless training environment over the pong simulation, in the style of a gym environment.
+//!
+//! The agent plays the left paddle of a classic match against a computer paddle on the right. Matches run
+//! the same systems as the game, so the ball, collisions and scoring are exactly those of a real match,
+//! but without a window and in fixed steps of [`FRAME_TIME`] instead of real time.
+//!
+//! ```no_run
+//! # use bevy_pong::env::{EnvConfig, PongEnv};
+//! let mut env = PongEnv::new(EnvConfig::default());
+//! let mut observation = env.reset(42);
+//! loop {
+//!     // Follow the ball, which is at index 1 of the default observation
+//!     let step = env.step(observation[1] - observation[4]);
+//!     observation = step.observation;
+//!     if step.done {
+//!         break;
+//!     }
+//! }
+//! ```
+
+use crate::actions::Actions;
+use crate::arena::Court;
+use crate::events::BallHitPaddle;
+use crate::game::{
+    AiSkill, Ball, GameRng, Paddle, PaddleSide, PlayingState, PongGamePlugin, Score, Velocity,
+    BALL_SPEED, COURT_HALF_WIDTH, COURT_HEIGHT,
+};
+use crate::policy::PolicyPlugin;
+use crate::rules::MatchRules;
+use crate::GameState;
+use bevy::ecs::event::EventCursor;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub use crate::career::Personality;
+
+/// Seconds of game time every frame of the simulation lasts
+pub const FRAME_TIME: f32 = 1.0 / 60.0;
+
+/// What the agent sees, as the values of the [`ObservationField`]s in the order they are configured
+pub type Observation = Vec<f32>;
+
+/// How an environment plays
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct EnvConfig {
+    /// The values making up the observation, in order
+    pub observation: Vec<ObservationField>,
+    /// Frames every action is repeated for, with the rewards of all of them added up
+    pub frame_skip: u32,
+    /// Points either side needs to end the episode
+    pub points_to_win: u32,
+    /// Steps after which the episode is cut short, if any
+    pub max_steps: Option<u32>,
+    /// Reward for every return of the agent's paddle, on top of +1 for a point won and -1 for a point lost
+    pub hit_reward: f32,
+    /// How the computer paddle plays
+    pub opponent: Personality,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            observation: ObservationField::ALL.to_vec(),
+            frame_skip: 4,
+            points_to_win: 5,
+            max_steps: None,
+            hit_reward: 0.,
+            opponent: Personality::default(),
+        }
+    }
+}
+
+impl EnvConfig {
+    /// Checks the environment can be played with this config
+    pub fn check(&self) -> Result<(), String> {
+        if self.observation.is_empty() {
+            return Err("observation should have at least one field".to_string());
+        }
+        if self.frame_skip == 0 {
+            return Err("frame_skip should be at least 1".to_string());
+        }
+        if self.points_to_win == 0 {
+            return Err("points_to_win should be at least 1".to_string());
+        }
+        if self.max_steps == Some(0) {
+            return Err("max_steps should be at least 1".to_string());
+        }
+        if !self.hit_reward.is_finite() {
+            return Err("hit_reward should be a number".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A part of the observation. Positions are scaled to the court, so they range from -1 to 1 within it,
+/// and velocities to the serve speed of the ball.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ObservationField {
+    /// Two values, x and y. Zero while there is no ball on the court.
+    BallPosition,
+    /// Two values, x and y. Zero while there is no ball on the court.
+    BallVelocity,
+    /// How high the agent's paddle is, or how far to the right for a paddle playing the top or bottom side
+    PaddlePosition,
+    /// How high the opponent's paddle is, measured like the agent's
+    OpponentPosition,
+}
+
+impl ObservationField {
+    pub const ALL: [ObservationField; 4] = [
+        ObservationField::BallPosition,
+        ObservationField::BallVelocity,
+        ObservationField::PaddlePosition,
+        ObservationField::OpponentPosition,
+    ];
+
+    /// How many values this adds to the observation
+    pub fn size(&self) -> usize {
+        match self {
+            ObservationField::BallPosition | ObservationField::BallVelocity => 2,
+            ObservationField::PaddlePosition | ObservationField::OpponentPosition => 1,
+        }
+    }
+}
+
+/// The observation of the paddle at `paddle` playing for `side`, turned so that its goal is on the left like
+/// the agent's. This lets policies trained on the left side play any side.
+/// `ball` is the position and velocity of the ball the paddle is watching, if there is one.
+pub fn observation_for(
+    fields: &[ObservationField],
+    side: PaddleSide,
+    ball: Option<(Vec2, Vec2)>,
+    paddle: Vec2,
+    opponent: Option<Vec2>,
+) -> Observation {
+    let facing = side.facing();
+    let axis = side.axis();
+    let court = Vec2::new(COURT_HALF_WIDTH, COURT_HEIGHT);
+    let to_frame = |vector: Vec2| Vec2::new(vector.dot(facing), vector.dot(axis));
+    let scale = to_frame(court).abs();
+
+    let mut observation = Vec::new();
+    for field in fields {
+        match field {
+            ObservationField::BallPosition => {
+                let position = ball.map_or(Vec2::ZERO, |(position, _)| to_frame(position) / scale);
+                observation.extend([position.x, position.y]);
+            }
+            ObservationField::BallVelocity => {
+                let velocity =
+                    ball.map_or(Vec2::ZERO, |(_, velocity)| to_frame(velocity) / BALL_SPEED);
+                observation.extend([velocity.x, velocity.y]);
+            }
+            ObservationField::PaddlePosition => observation.push(paddle.dot(axis) / scale.y),
+            ObservationField::OpponentPosition => {
+                observation.push(opponent.map_or(0., |opponent| opponent.dot(axis) / scale.y));
+            }
+        }
+    }
+    observation
+}
+
+/// What happened in a step
+#[derive(Serialize, Clone, Debug)]
+pub struct Step {
+    pub observation: Observation,
+    pub reward: f32,
+    /// Whether the episode is over, because a side won or it ran out of steps
+    pub done: bool,
+    pub info: StepInfo,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct StepInfo {
+    /// Points of the agent and the computer
+    pub score: [u32; 2],
+    /// Returns of the agent's paddle in this step
+    pub hits: u32,
+    /// Steps since the episode started
+    pub steps: u32,
+    /// Whether the episode was cut short by `max_steps` instead of won by a side
+    pub truncated: bool,
+}
+
+/// A match against the computer, stepped by the agent
+pub struct PongEnv {
+    config: EnvConfig,
+    app: App,
+    hit_cursor: EventCursor<BallHitPaddle>,
+    steps: u32,
+}
+
+impl PongEnv {
+    pub fn new(config: EnvConfig) -> Self {
+        PongEnv {
+            app: build_app(&config, 0),
+            config,
+            hit_cursor: EventCursor::default(),
+            steps: 0,
+        }
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    /// Starts a new episode, with the computer's randomness seeded by `seed`
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.app = build_app(&self.config, seed);
+        self.hit_cursor = EventCursor::default();
+        self.steps = 0;
+        // The first frame sets up the court and serves
+        self.app.update();
+        self.observe()
+    }
+
+    /// Plays `frame_skip` frames with the agent's paddle moving at `action`, from -1 for full speed
+    /// down to 1 for full speed up
+    pub fn step(&mut self, action: f32) -> Step {
+        let movement = action.clamp(-1.0, 1.0);
+        let before = self.score();
+        let mut hits = 0;
+        for _ in 0..self.config.frame_skip.max(1) {
+            if self.is_decided() {
+                break;
+            }
+            self.play_frame(movement);
+            let events = self.app.world().resource::<Events<BallHitPaddle>>();
+            hits += self
+                .hit_cursor
+                .read(events)
+                .filter(|event| event.side == PaddleSide::Left)
+                .count() as u32;
+        }
+        self.steps += 1;
+
+        let score = self.score();
+        let points_won = (score[0] - before[0]) as f32;
+        let points_lost = (score[1] - before[1]) as f32;
+        let truncated = !self.is_decided()
+            && self
+                .config
+                .max_steps
+                .is_some_and(|max_steps| self.steps >= max_steps);
+        Step {
+            observation: self.observe(),
+            reward: points_won - points_lost + self.config.hit_reward * hits as f32,
+            done: self.is_decided() || truncated,
+            info: StepInfo {
+                score,
+                hits,
+                steps: self.steps,
+                truncated,
+            },
+        }
+    }
+
+    fn play_frame(&mut self, movement: f32) {
+        self.app
+            .world_mut()
+            .resource_mut::<Actions>()
+            .player_movement = (movement != 0.).then_some(Vec2::new(0., movement));
+        play_headless_frame(&mut self.app);
+    }
+
+    fn score(&self) -> [u32; 2] {
+        let score = self.app.world().resource::<Score>();
+        [score.get(PaddleSide::Left), score.get(PaddleSide::Right)]
+    }
+
+    fn is_decided(&self) -> bool {
+        self.score()
+            .iter()
+            .any(|points| *points >= self.config.points_to_win)
+    }
+
+    fn observe(&mut self) -> Observation {
+        let world = self.app.world_mut();
+        let ball = world
+            .query_filtered::<(&Transform, &Velocity), With<Ball>>()
+            .iter(world)
+            .next()
+            .map(|(transform, velocity)| {
+                (
+                    transform.translation.truncate(),
+                    velocity.direction * velocity.speed,
+                )
+            });
+        let mut paddles = [None; 2];
+        for (transform, paddle) in world.query::<(&Transform, &Paddle)>().iter(world) {
+            if let Some(position) = paddles.get_mut(paddle.side.index()) {
+                *position = Some(transform.translation.truncate());
+            }
+        }
+        observation_for(
+            &self.config.observation,
+            PaddleSide::Left,
+            ball,
+            paddles[0].unwrap_or(PaddleSide::Left.home()),
+            paddles[1],
+        )
+    }
+}
+
+// The agent plays the left paddle as the player, without power-ups getting in the way of learning
+fn build_app(config: &EnvConfig, seed: u64) -> App {
+    let rules = MatchRules {
+        power_ups: false,
+        ..default()
+    };
+    let ai_skill = AiSkill {
+        personality: config.opponent.clone(),
+        ..default()
+    };
+    headless_app(rules, ai_skill, seed)
+}
+
+/// A match with these rules in an app of its own, without a window, moving on by [`FRAME_TIME`] every
+/// update. The randomness of the match is seeded by `seed`, so the same inputs play the same match.
+/// Computer paddles can be given policies by inserting an [`ActivePolicy`](crate::policy::ActivePolicy).
+pub fn headless_app(rules: MatchRules, ai_skill: AiSkill, seed: u64) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+            FRAME_TIME,
+        )))
+        .insert_state(GameState::Playing)
+        .insert_resource(rules)
+        .insert_resource(ai_skill)
+        .insert_resource(GameRng(StdRng::seed_from_u64(seed)))
+        .init_resource::<Court>()
+        .init_resource::<Actions>()
+        .init_resource::<ButtonInput<KeyCode>>()
+        .add_plugins((PongGamePlugin, PolicyPlugin));
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// Plays a frame of an app made by [`headless_app`]. Nobody is there to serve, so play goes on right away
+/// after every point.
+pub fn play_headless_frame(app: &mut App) {
+    let world = app.world_mut();
+    if *world.resource::<State<PlayingState>>() != PlayingState::Playing {
+        world
+            .resource_mut::<NextState<PlayingState>>()
+            .set(PlayingState::Playing);
+    }
+    app.update();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Plays until the episode is over, or `max_steps` steps if it is not over by then
+    fn play(env: &mut PongEnv, action: f32, max_steps: u32) -> Vec<Step> {
+        let mut steps = Vec::new();
+        for _ in 0..max_steps {
+            let step = env.step(action);
+            let done = step.done;
+            steps.push(step);
+            if done {
+                break;
+            }
+        }
+        steps
+    }
+
+    #[test]
+    fn same_seed_and_actions_play_the_same_episode() {
+        let mut first = PongEnv::new(EnvConfig::default());
+        let mut second = PongEnv::new(EnvConfig::default());
+        assert_eq!(first.reset(7), second.reset(7));
+        for index in 0..300 {
+            let action = (index as f32 * 0.1).sin();
+            let (a, b) = (first.step(action), second.step(action));
+            assert_eq!(a.observation, b.observation);
+            assert_eq!(a.reward, b.reward);
+            assert_eq!(a.done, b.done);
+        }
+    }
+
+    #[test]
+    fn frame_skip_adds_up_the_rewards() {
+        let config = EnvConfig {
+            points_to_win: 100,
+            hit_reward: 0.5,
+            ..default()
+        };
+        let mut single = PongEnv::new(EnvConfig {
+            frame_skip: 1,
+            ..config.clone()
+        });
+        let mut skipping = PongEnv::new(EnvConfig {
+            frame_skip: 4,
+            ..config
+        });
+        single.reset(3);
+        skipping.reset(3);
+        for _ in 0..400 {
+            let skipped = skipping.step(0.5);
+            let steps: Vec<_> = (0..4).map(|_| single.step(0.5)).collect();
+            let reward: f32 = steps.iter().map(|step| step.reward).sum();
+            let hits: u32 = steps.iter().map(|step| step.info.hits).sum();
+            assert_eq!(skipped.reward, reward);
+            assert_eq!(skipped.info.hits, hits);
+            assert_eq!(skipped.observation, steps[3].observation);
+        }
+        assert_eq!(skipping.score(), single.score());
+        assert_ne!(skipping.score(), [0, 0], "no point was played");
+    }
+
+    #[test]
+    fn episode_is_done_when_a_side_wins() {
+        let mut env = PongEnv::new(EnvConfig {
+            points_to_win: 1,
+            ..default()
+        });
+        env.reset(1);
+        // Staying in a corner of the court lets the computer score
+        let steps = play(&mut env, -1., 10_000);
+        let last = steps.last().unwrap();
+        assert!(last.done);
+        assert!(!last.info.truncated);
+        assert_eq!(last.info.score, [0, 1]);
+        assert_eq!(last.reward, -1.);
+        assert!(steps[..steps.len() - 1].iter().all(|step| !step.done));
+    }
+
+    #[test]
+    fn episode_is_truncated_after_max_steps() {
+        let mut env = PongEnv::new(EnvConfig {
+            max_steps: Some(3),
+            ..default()
+        });
+        env.reset(1);
+        let steps = play(&mut env, 0., 10);
+        assert_eq!(steps.len(), 3);
+        assert!(steps[..2]
+            .iter()
+            .all(|step| !step.done && !step.info.truncated));
+        assert!(steps[2].done && steps[2].info.truncated);
+        assert_eq!(steps[2].info.steps, 3);
+    }
+
+    #[test]
+    fn observation_has_the_configured_fields() {
+        for observation in [
+            ObservationField::ALL.to_vec(),
+            vec![ObservationField::PaddlePosition],
+            vec![
+                ObservationField::BallVelocity,
+                ObservationField::OpponentPosition,
+            ],
+        ] {
+            let size: usize = observation.iter().map(ObservationField::size).sum();
+            let mut env = PongEnv::new(EnvConfig {
+                observation,
+                ..default()
+            });
+            assert_eq!(env.reset(0).len(), size);
+            assert_eq!(env.step(1.).observation.len(), size);
+        }
+    }
+
+    #[test]
+    fn check_rejects_unplayable_configs() {
+        assert!(EnvConfig::default().check().is_ok());
+        let invalid = [
+            EnvConfig {
+                observation: vec![],
+                ..default()
+            },
+            EnvConfig {
+                frame_skip: 0,
+                ..default()
+            },
+            EnvConfig {
+                points_to_win: 0,
+                ..default()
+            },
+            EnvConfig {
+                max_steps: Some(0),
+                ..default()
+            },
+            EnvConfig {
+                hit_reward: f32::NAN,
+                ..default()
+            },
+        ];
+        for config in invalid {
+            assert!(config.check().is_err());
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Matches between two computer players, played headless from the first serve to the last point, to
+//! compare how they play.
+//!
+//! A [`Competitor`] is a computer opponent with its [`Personality`], a [`Policy`] file or one of the
+//! [`Script`]ed bots, and [`play_match`] plays one match between two of them on the simulation of the
+//! [`env`](crate::env).
+
+use crate::actions::Controller;
+use crate::career::read_opponents;
+use crate::env::{headless_app, play_headless_frame, ObservationField, Personality, FRAME_TIME};
+use crate::events::PointScored;
+use crate::game::{AiSkill, PaddleSide, Score};
+use crate::policy::{ActivePolicy, Condition, Model, Policy, Rule};
+use crate::rules::MatchRules;
+use bevy::ecs::event::EventCursor;
+use bevy::prelude::*;
+use std::path::Path;
+
+/// Who plays a side
+#[derive(Clone)]
+pub enum Competitor {
+    Computer(Personality),
+    Policy(Policy),
+}
+
+impl Competitor {
+    /// Finds a competitor by how it is written on the command line:
+    /// - `script:NAME` for one of the [`Script`]s
+    /// - `policy:PATH`, or just a path ending in `.policy.json`, for a [`Policy`] file
+    /// - otherwise the name of a computer opponent in `assets/opponents`, or "Computer" for the default one
+    pub fn parse(spec: &str) -> Result<Competitor, String> {
+        if let Some(name) = spec.strip_prefix("script:") {
+            let script = Script::ALL
+                .into_iter()
+                .find(|script| script.name() == name)
+                .ok_or_else(|| {
+                    let names: Vec<_> = Script::ALL.iter().map(Script::name).collect();
+                    format!("Unknown script {name}, choose from: {}", names.join(", "))
+                })?;
+            return Ok(Competitor::Policy(script.policy()));
+        }
+        if let Some(path) = spec
+            .strip_prefix("policy:")
+            .or(spec.ends_with(".policy.json").then_some(spec))
+        {
+            let policy =
+                Policy::load(Path::new(path)).map_err(|error| format!("{path}: {error}"))?;
+            return Ok(Competitor::Policy(policy));
+        }
+        find_personality(spec).map(Competitor::Computer)
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Competitor::Computer(personality) => &personality.name,
+            Competitor::Policy(policy) => &policy.name,
+        }
+    }
+}
+
+// The opponent with this name, or whose file has it as its stem
+fn find_personality(name: &str) -> Result<Personality, String> {
+    let default = Personality::default();
+    if default.name.eq_ignore_ascii_case(name) {
+        return Ok(default);
+    }
+    let opponents = read_opponents()?;
+    if let Some((_, personality)) = opponents.iter().find(|(stem, personality)| {
+        personality.name.eq_ignore_ascii_case(name) || stem.eq_ignore_ascii_case(name)
+    }) {
+        return Ok(personality.clone());
+    }
+    let mut names = vec![default.name];
+    names.extend(
+        opponents
+            .into_iter()
+            .map(|(_, personality)| personality.name),
+    );
+    Err(format!(
+        "Unknown opponent {name}, choose from: {}",
+        names.join(", ")
+    ))
+}
+
+/// Bots playing by simple rules, to measure other competitors against
+#[derive(Clone, Copy)]
+pub enum Script {
+    /// Never moves from the middle of its goal
+    Idle,
+    /// Stays level with the ball at full speed
+    Follow,
+    /// Follows the ball while it comes towards the paddle, and goes back to the middle while it goes away
+    Lazy,
+}
+
+impl Script {
+    pub const ALL: [Script; 3] = [Script::Idle, Script::Follow, Script::Lazy];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Script::Idle => "idle",
+            Script::Follow => "follow",
+            Script::Lazy => "lazy",
+        }
+    }
+
+    /// The script as a rule table over the ball's position and velocity and the paddle's position
+    pub fn policy(&self) -> Policy {
+        // Weights picking out how far the ball is above the paddle, the paddle's height and the ball
+        // moving away from the paddle's goal
+        const BALL_ABOVE: [f32; 5] = [0., 1., 0., 0., -1.];
+        const PADDLE: [f32; 5] = [0., 0., 0., 0., 1.];
+        const AWAY: [f32; 5] = [0., 0., 1., 0., 0.];
+        // How far off the paddle is allowed to be before it moves, so it does not shake
+        const SLACK: f32 = 0.02;
+        let rule = |when: Vec<Condition>, action| Rule { when, action };
+        let above = |weights: [f32; 5], value| Condition {
+            weights: weights.to_vec(),
+            above: Some(value),
+            below: None,
+        };
+        let below = |weights: [f32; 5], value| Condition {
+            weights: weights.to_vec(),
+            above: None,
+            below: Some(value),
+        };
+        let rules = match self {
+            Script::Idle => vec![],
+            Script::Follow => vec![
+                rule(vec![above(BALL_ABOVE, SLACK)], 1.),
+                rule(vec![below(BALL_ABOVE, -SLACK)], -1.),
+            ],
+            Script::Lazy => vec![
+                rule(vec![below(AWAY, 0.), above(BALL_ABOVE, SLACK)], 1.),
+                rule(vec![below(AWAY, 0.), below(BALL_ABOVE, -SLACK)], -1.),
+                rule(vec![above(PADDLE, SLACK)], -1.),
+                rule(vec![below(PADDLE, -SLACK)], 1.),
+            ],
+        };
+        Policy {
+            name: format!("script:{}", self.name()),
+            observation: vec![
+                ObservationField::BallPosition,
+                ObservationField::BallVelocity,
+                ObservationField::PaddlePosition,
+            ],
+            model: Model::Table { rules, default: 0. },
+        }
+    }
+}
+
+/// How a match went
+#[derive(Clone, Debug)]
+pub struct MatchReport {
+    /// Points of the left and right sides
+    pub points: [u32; 2],
+    /// Paddle hits of every rally, in the order the points were played
+    pub rallies: Vec<u32>,
+    /// Seconds of game time the match lasted
+    pub duration: f32,
+    /// The side that won, or none if the match ran out of time first
+    pub winner: Option<PaddleSide>,
+}
+
+/// Plays a classic match of `left` against `right` up to `points_to_win`, or until `max_duration` seconds
+/// of game time have gone by. The randomness of the match is seeded by `seed`.
+pub fn play_match(
+    left: &Competitor,
+    right: &Competitor,
+    points_to_win: u32,
+    max_duration: f32,
+    seed: u64,
+) -> MatchReport {
+    let mut rules = MatchRules {
+        power_ups: false,
+        ..default()
+    };
+    let mut ai_skill = AiSkill::default();
+    let mut active_policy = ActivePolicy::default();
+    for (side, competitor) in [(PaddleSide::Left, left), (PaddleSide::Right, right)] {
+        rules.controllers[side.index()] = Controller::Ai;
+        match competitor {
+            Competitor::Computer(personality) => {
+                ai_skill.sides[side.index()] = Some(personality.clone())
+            }
+            Competitor::Policy(policy) => active_policy.sides[side.index()] = Some(policy.clone()),
+        }
+    }
+    let mut app = headless_app(rules, ai_skill, seed);
+    app.insert_resource(active_policy);
+
+    let mut point_cursor = EventCursor::<PointScored>::default();
+    let mut rallies = Vec::new();
+    let max_frames = (max_duration / FRAME_TIME) as u32;
+    let mut frames = 0;
+    loop {
+        let score = app.world().resource::<Score>();
+        let points = [score.get(PaddleSide::Left), score.get(PaddleSide::Right)];
+        let winner = if points[0] >= points_to_win {
+            Some(PaddleSide::Left)
+        } else if points[1] >= points_to_win {
+            Some(PaddleSide::Right)
+        } else {
+            None
+        };
+        if winner.is_some() || frames >= max_frames {
+            return MatchReport {
+                points,
+                rallies,
+                duration: frames as f32 * FRAME_TIME,
+                winner,
+            };
+        }
+        play_headless_frame(&mut app);
+        frames += 1;
+        let events = app.world().resource::<Events<PointScored>>();
+        rallies.extend(point_cursor.read(events).map(|event| event.rally));
+    }
+}
@@ -0,0 +1,158 @@
+//! Gameplay events, sent while a match is played.
+//!
+//! Anything that reacts to what happens on the court, like audio, effects or stats, reads these with an
+//! [`EventReader`] instead of hooking into the systems that move the ball. All of them are registered by the
+//! game plugin. Within a frame they are sent in the order things happen: hits, then goals and points, then
+//! the end of the round followed by the serve of the next ball, and finally the end of the match.
+
+use bevy::prelude::*;
+
+pub use crate::game::PaddleSide;
+pub use crate::rules::GameMode;
+
+/// A match started, once the court and paddles are set up
+#[derive(Event, Clone, Debug)]
+pub struct MatchStarted {
+    pub mode: GameMode,
+}
+
+/// A new ball was served from the middle of the court, at the start of a match and after every round,
+/// or by the opponent of a practice drill
+#[derive(Event, Clone, Debug)]
+pub struct ServeStarted {
+    pub ball: Entity,
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+/// A paddle returned a ball
+#[derive(Event, Clone, Debug)]
+pub struct BallHitPaddle {
+    pub ball: Entity,
+    pub paddle: Entity,
+    /// The side the paddle plays for
+    pub side: PaddleSide,
+    /// Where the ball left the paddle
+    pub position: Vec2,
+    /// Where along the paddle the ball hit it, from -1 at the lower end to 1 at the higher end
+    pub offset: f32,
+    /// Speed of the ball as it leaves the paddle
+    pub speed: f32,
+}
+
+/// A ball bounced off a wall of the court or an obstacle in it
+#[derive(Event, Clone, Debug)]
+pub struct BallHitWall {
+    pub ball: Entity,
+    pub position: Vec2,
+    /// The obstacle that was hit, if it was not one of the court's walls
+    pub obstacle: Option<Entity>,
+}
+
+/// A ball left the court through the goal of the `conceded` side.
+/// The ball is despawned in the same frame.
+#[derive(Event, Clone, Debug)]
+pub struct Goal {
+    pub ball: Entity,
+    pub conceded: PaddleSide,
+    /// The side the point goes to, if any
+    pub scorer: Option<PaddleSide>,
+}
+
+/// A goal gave a side a point
+#[derive(Event, Clone, Debug)]
+pub struct PointScored {
+    pub scorer: PaddleSide,
+    pub conceded: PaddleSide,
+    /// Paddle hits in the rally that led to the point
+    pub rally: u32,
+    /// Points of each side after this one, indexed by [`PaddleSide::index`]
+    pub score: [u32; 4],
+}
+
+/// The court is reset for a new serve, the points have already been scored by the goals
+#[derive(Event, Clone, Debug)]
+pub struct RoundEnd;
+
+/// A match ended, sent by the plugin running the mode being played as it decides the match. Classic and doubles
+/// matches have no end of their own, and send it without a winner when they are left, as do matches of the
+/// modes built on them, like the career, that are left before they were decided.
+#[derive(Event, Clone, Debug)]
+pub struct MatchEnded {
+    /// The side that won, in modes that have a winner
+    pub winner: Option<PaddleSide>,
+    /// Points each side had in the match, indexed by [`PaddleSide::index`], in modes that count them.
+    /// Head starts are included, as they count towards winning.
+    pub points: [u32; 4],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::Controller;
+    use crate::env::{headless_app, play_headless_frame};
+    use crate::rules::MatchRules;
+    use bevy::ecs::event::EventCursor;
+
+    // Reads the events of a kind sent since the last read
+    struct Recorder<E: Event>(EventCursor<E>);
+
+    impl<E: Event + Clone> Recorder<E> {
+        fn new() -> Self {
+            Recorder(EventCursor::default())
+        }
+
+        fn read(&mut self, app: &App) -> Vec<E> {
+            self.0
+                .read(app.world().resource::<Events<E>>())
+                .cloned()
+                .collect()
+        }
+    }
+
+    #[test]
+    fn a_rally_sends_its_events_in_order() {
+        // Nobody moves the left paddle, so the computer on the right scores before long
+        let rules = MatchRules {
+            controllers: [
+                Controller::Keys,
+                Controller::Ai,
+                Controller::Ai,
+                Controller::Ai,
+            ],
+            power_ups: false,
+            ..default()
+        };
+        let mut app = headless_app(rules, default(), 3);
+        let mut started = Recorder::<MatchStarted>::new();
+        let mut serves = Recorder::<ServeStarted>::new();
+        let mut hits = Recorder::<BallHitPaddle>::new();
+        let mut goals = Recorder::<Goal>::new();
+        let mut points = Recorder::<PointScored>::new();
+        let mut round_ends = Recorder::<RoundEnd>::new();
+
+        play_headless_frame(&mut app);
+        assert_eq!(started.read(&app).len(), 1);
+        assert_eq!(serves.read(&app).len(), 1);
+        let mut rally = 0;
+        for _ in 0..20_000 {
+            play_headless_frame(&mut app);
+            rally += hits.read(&app).len();
+            let point = points.read(&app);
+            if point.is_empty() {
+                assert!(goals.read(&app).is_empty());
+                continue;
+            }
+            // The goal, the point, the end of the round and the next serve all come in the same frame
+            let goal = &goals.read(&app)[0];
+            assert_eq!(goal.scorer, Some(point[0].scorer));
+            assert_eq!(goal.conceded, point[0].conceded);
+            assert_eq!(point[0].rally as usize, rally);
+            assert_eq!(point[0].score[point[0].scorer.index()], 1);
+            assert_eq!(round_ends.read(&app).len(), 1);
+            assert_eq!(serves.read(&app).len(), 1);
+            return;
+        }
+        panic!("nobody scored");
+    }
+}
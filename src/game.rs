@@ -1,26 +1,45 @@
-use crate::actions::Actions;
-use crate::GameState;
+use crate::actions::{
+    gamepad_input, key_input, partner_keys, set_mouse_actions, Actions, Controller,
+};
+use crate::arena::{Court, Obstacle};
+use crate::career::Personality;
+use crate::events::{
+    BallHitPaddle, BallHitWall, Goal, MatchEnded, MatchStarted, PointScored, RoundEnd, ServeStarted,
+};
+use crate::handicap::Handicap;
+use crate::policy::ActivePolicy;
+use crate::powerups::{Caught, ReversedControls};
+use crate::rules::{GameMode, MatchRules, RoundEndRule};
+use crate::theme::ThemeRole;
+use crate::{GameSet, GameState};
+use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 #[derive(States, Default, Clone, Eq, PartialEq, Debug, Hash)]
 pub enum PlayingState {
     #[default]
     Paused,
     Playing,
+    // The match has been decided, and only leaving it is left
+    Over,
 }
 
 #[derive(Component)]
 pub struct Ball;
 
-#[derive(Component)]
-pub struct RightPaddle;
+#[derive(Component, Clone, Copy)]
+pub struct Paddle {
+    pub side: PaddleSide,
+    // The second paddle of a side in doubles, guarding the net instead of the goal
+    pub forward: bool,
+}
 
+// The paddle steered by `Controller::Player`, which touch and mouse input is measured against
 #[derive(Component)]
 pub struct Player;
 
-#[derive(Component)]
-pub struct LeftPaddle;
-
 #[derive(Component)]
 pub struct CourtLine;
 
@@ -30,30 +49,231 @@ pub struct PauseText;
 #[derive(Component)]
 pub struct ScoreText;
 
+// On-screen buttons for touch devices, which have no keys for pausing or leaving the game
+#[derive(Component)]
+pub enum TouchButton {
+    Pause,
+    Back,
+}
+
 #[derive(Component)]
 pub struct InitialTransform(pub Vec3);
 
+// Size of the box used for collisions, which can differ from the sprite while power-ups resize it
+#[derive(Component, Clone, Copy)]
+pub struct Collider(pub Vec2);
+
+// The paddle that last hit a ball, if any paddle touched it since it was served
+#[derive(Component, Default)]
+pub struct LastHitBy(pub Option<Entity>);
+
 #[derive(Component)]
 pub struct Velocity {
     pub direction: Vec2,
     pub speed: f32,
 }
 
+// Points scored by each side, kept across matches until reset from the menu
 #[derive(Resource, Default)]
 pub struct Score {
-    pub player: u32,
-    pub computer: u32,
+    points: [u32; 4],
 }
 
-#[derive(Event)]
-pub struct RoundEnd {
-    pub winner: PaddleSide,
+impl Score {
+    pub fn get(&self, side: PaddleSide) -> u32 {
+        self.points[side.index()]
+    }
+
+    pub fn add_point(&mut self, side: PaddleSide) {
+        self.points[side.index()] += 1;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.iter().all(|points| *points == 0)
+    }
+}
+
+// Paddle hits since the last serve
+#[derive(Resource, Default)]
+pub struct Rally {
+    pub hits: u32,
+}
+
+// What happened in the current match, kept until the next one starts
+#[derive(Resource, Default)]
+pub struct MatchStats {
+    // The handicaps each side played with, indexed by `PaddleSide::index`
+    pub handicaps: [Handicap; 4],
+    // Seconds played, not counting pauses
+    pub duration: f32,
+    pub longest_rally: u32,
+    // Points each side had carried over from earlier matches when this one started
+    pub start_score: [u32; 4],
+}
+
+impl MatchStats {
+    // Points each side has in this match, indexed by `PaddleSide::index`: its head start and the points
+    // scored since the match started. The running `Score` leaves head starts out.
+    pub fn match_points(&self, score: &Score) -> [u32; 4] {
+        PaddleSide::ALL.map(|side| {
+            let index = side.index();
+            score.get(side).saturating_sub(self.start_score[index])
+                + self.handicaps[index].head_start
+        })
+    }
+}
+
+// The side that won a match between `sides` played to `points_to_win`, with `points` indexed by
+// `PaddleSide::index`. Several balls can score at once in multi-ball, so once any side reaches
+// `points_to_win` the one furthest ahead wins, and a tie for the lead plays on. Modes check it after
+// `handle_round_end`, so a decided match ends up over instead of paused.
+pub fn decided_winner(
+    points: &[u32],
+    sides: &[PaddleSide],
+    points_to_win: u32,
+) -> Option<PaddleSide> {
+    let leader = sides
+        .iter()
+        .copied()
+        .max_by_key(|side| points[side.index()])?;
+    let lead = points[leader.index()];
+    let tied = sides
+        .iter()
+        .filter(|side| points[side.index()] == lead)
+        .count()
+        > 1;
+    (lead >= points_to_win && !tied).then_some(leader)
+}
+
+// The screen a match goes back to when it is left
+#[derive(Resource)]
+pub struct MatchExit(pub GameState);
+
+impl Default for MatchExit {
+    fn default() -> Self {
+        MatchExit(GameState::Menu)
+    }
+}
+
+// How computer paddles play, which some modes change during a match
+#[derive(Resource, Default)]
+pub struct AiSkill {
+    pub personality: Personality,
+    // Sides playing with a personality of their own instead, indexed by `PaddleSide::index`
+    pub sides: [Option<Personality>; 4],
+}
+
+impl AiSkill {
+    pub fn for_side(&self, side: PaddleSide) -> &Personality {
+        self.sides[side.index()]
+            .as_ref()
+            .unwrap_or(&self.personality)
+    }
 }
 
-#[derive(PartialEq)]
+// Randomness of the computer paddles and the extra balls, which can be seeded to replay a match exactly
+#[derive(Resource)]
+pub struct GameRng(pub StdRng);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng(StdRng::from_entropy())
+    }
+}
+
+// Where a computer paddle is heading, which it only reconsiders every so often
+#[derive(Component, Default)]
+pub struct AiAim {
+    target: f32,
+    // How far off the paddle is about to be
+    offset: f32,
+    // Seconds until it picks a new spot
+    timer: f32,
+}
+
+// A ball curving until a paddle hits it, in degrees per second
+#[derive(Component)]
+pub struct Spin(pub f32);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum PaddleSide {
     Left,
     Right,
+    Top,
+    Bottom,
+}
+
+impl PaddleSide {
+    pub const ALL: [PaddleSide; 4] = [
+        PaddleSide::Left,
+        PaddleSide::Right,
+        PaddleSide::Top,
+        PaddleSide::Bottom,
+    ];
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaddleSide::Left => "Left",
+            PaddleSide::Right => "Right",
+            PaddleSide::Top => "Top",
+            PaddleSide::Bottom => "Bottom",
+        }
+    }
+
+    pub fn opposite(&self) -> PaddleSide {
+        match self {
+            PaddleSide::Left => PaddleSide::Right,
+            PaddleSide::Right => PaddleSide::Left,
+            PaddleSide::Top => PaddleSide::Bottom,
+            PaddleSide::Bottom => PaddleSide::Top,
+        }
+    }
+
+    // Direction from this side's goal into the court
+    pub fn facing(&self) -> Vec2 {
+        match self {
+            PaddleSide::Left => Vec2::X,
+            PaddleSide::Right => Vec2::NEG_X,
+            PaddleSide::Top => Vec2::NEG_Y,
+            PaddleSide::Bottom => Vec2::Y,
+        }
+    }
+
+    // Direction the paddles of this side move along
+    pub fn axis(&self) -> Vec2 {
+        match self {
+            PaddleSide::Left | PaddleSide::Right => Vec2::Y,
+            PaddleSide::Top | PaddleSide::Bottom => Vec2::X,
+        }
+    }
+
+    // Where the paddle of this side starts, in the middle of its goal
+    pub fn home(&self) -> Vec2 {
+        -self.facing()
+            * match self {
+                PaddleSide::Left | PaddleSide::Right => COURT_HALF_WIDTH,
+                PaddleSide::Top | PaddleSide::Bottom => COURT_HEIGHT,
+            }
+    }
+
+    // How far from the middle of the side its paddles can reach
+    fn reach(&self) -> f32 {
+        match self {
+            PaddleSide::Left | PaddleSide::Right => COURT_HEIGHT,
+            PaddleSide::Top | PaddleSide::Bottom => WALL_GOAL_HALF_WIDTH,
+        }
+    }
+
+    pub fn paddle_size(&self) -> Vec2 {
+        match self {
+            PaddleSide::Left | PaddleSide::Right => PADDLE_SIZE,
+            PaddleSide::Top | PaddleSide::Bottom => PADDLE_SIZE.yx(),
+        }
+    }
 }
 
 pub struct PongGamePlugin;
@@ -62,247 +282,687 @@ impl Plugin for PongGamePlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<PlayingState>()
             .init_resource::<Score>()
+            .init_resource::<Rally>()
+            .init_resource::<AiSkill>()
+            .init_resource::<MatchStats>()
+            .init_resource::<MatchExit>()
+            .init_resource::<GameRng>()
+            .configure_sets(
+                Update,
+                (
+                    GameSet::Input,
+                    GameSet::Ai,
+                    GameSet::Physics,
+                    GameSet::Scoring,
+                    GameSet::Presentation,
+                )
+                    .chain(),
+            )
+            .add_event::<Goal>()
+            .add_event::<MatchStarted>()
+            .add_event::<ServeStarted>()
+            .add_event::<BallHitPaddle>()
+            .add_event::<BallHitWall>()
+            .add_event::<PointScored>()
             .add_event::<RoundEnd>()
+            .add_event::<MatchEnded>()
             .add_systems(
                 OnEnter(GameState::Playing),
                 (
                     spawn_game_elements,
-                    spawn_player,
-                    spawn_pause_text,
-                    spawn_score_text,
+                    spawn_paddles,
+                    announce_match,
+                    (
+                        start_match_stats,
+                        spawn_score_text.run_if(|rules: Res<MatchRules>| {
+                            matches!(rules.mode, GameMode::Classic | GameMode::Doubles)
+                        }),
+                    )
+                        .chain(),
                 ),
             )
             .add_systems(
                 Update,
                 (handle_pause_key, handle_escape_key)
                     .chain()
+                    .in_set(GameSet::Input)
                     .run_if(in_state(GameState::Playing)),
             )
             .add_systems(
                 Update,
-                (move_player, move_ball, move_ai_paddle, handle_round_end)
-                    .chain()
+                (
+                    move_player.after(set_mouse_actions).in_set(GameSet::Input),
+                    move_ai_paddle.in_set(GameSet::Ai),
+                    (curve_spinning_balls, move_ball, spin_returns)
+                        .chain()
+                        .in_set(GameSet::Physics),
+                    (
+                        add_rally_balls,
+                        record_match_stats,
+                        score_goals,
+                        handle_round_end,
+                    )
+                        .chain()
+                        .in_set(GameSet::Scoring),
+                )
                     .run_if(in_state(GameState::Playing))
                     .run_if(in_state(PlayingState::Playing)),
             )
-            .add_systems(OnExit(GameState::Playing), cleanup_game);
+            .add_systems(
+                OnExit(GameState::Playing),
+                (end_unfinished_match, cleanup_game).chain(),
+            );
+
+        if cfg!(feature = "mobile") {
+            app.add_systems(OnEnter(GameState::Playing), spawn_touch_buttons)
+                .add_systems(
+                    Update,
+                    handle_touch_buttons
+                        .in_set(GameSet::Input)
+                        .run_if(in_state(GameState::Playing)),
+                );
+        } else {
+            app.add_systems(OnEnter(GameState::Playing), spawn_pause_text);
+        }
     }
 }
 
-const COURT_HEIGHT: f32 = 300.0; // Increased from 250.0
+pub const COURT_HEIGHT: f32 = 300.0; // Increased from 250.0
+pub const COURT_HALF_WIDTH: f32 = 600.0;
+// Half the width of the goals in the top and bottom walls when four sides play
+pub const WALL_GOAL_HALF_WIDTH: f32 = 300.0;
+pub const PADDLE_SIZE: Vec2 = Vec2::new(20.0, 100.0);
+pub const BALL_SIZE: Vec2 = Vec2::new(20.0, 20.0); // Square ball
+pub const PADDLE_SPEED: f32 = 500.0;
+pub const BALL_SPEED: f32 = 550.0;
+const SERVE_DIRECTION: Vec2 = Vec2::new(-1.0, 0.25);
+// How far from the net the forward paddles of doubles stand
+const FORWARD_PADDLE_DISTANCE: f32 = 240.0;
 
-fn spawn_player(mut commands: Commands) {
-    commands.spawn((
-        Sprite {
-            color: Color::WHITE,
-            custom_size: Some(Vec2::new(20.0, 100.0)),
-            ..default()
-        },
-        Transform::from_translation(Vec3::new(-600., 0., 1.)),
-        InitialTransform(Vec3::new(-600., 0., 1.)),
-        Player,
-        LeftPaddle,
-    ));
+// Every side in the match gets a paddle, moved by the controller the rules give that side.
+// In doubles a forward paddle joins each side between its goal and the net.
+fn spawn_paddles(mut commands: Commands, rules: Res<MatchRules>, ai_skill: Res<AiSkill>) {
+    for side in rules.mode.sides() {
+        let size = rules.handicaps[side.index()].paddle_size(*side);
+        let controller = rules.controllers[side.index()];
+        // Some computer opponents play further up the court than a person would
+        let advance = match controller {
+            Controller::Ai => ai_skill.for_side(*side).advance,
+            _ => 0.,
+        };
+        spawn_paddle(
+            &mut commands,
+            Paddle {
+                side: *side,
+                forward: false,
+            },
+            controller,
+            side.home() + side.facing() * advance,
+            size,
+        );
+        if rules.mode == GameMode::Doubles {
+            spawn_paddle(
+                &mut commands,
+                Paddle {
+                    side: *side,
+                    forward: true,
+                },
+                rules.partners[side.index()],
+                -side.facing() * FORWARD_PADDLE_DISTANCE,
+                size,
+            );
+        }
+    }
 }
 
-fn spawn_game_elements(mut commands: Commands) {
-    // Spawn center line
-    commands.spawn((
+fn spawn_paddle(
+    commands: &mut Commands,
+    paddle: Paddle,
+    controller: Controller,
+    position: Vec2,
+    size: Vec2,
+) {
+    let position = position.extend(1.);
+    let mut entity = commands.spawn((
         Sprite {
             color: Color::WHITE,
-            custom_size: Some(Vec2::new(2.0, 600.0)), // Thin vertical line
+            custom_size: Some(size),
             ..default()
         },
-        Transform::from_translation(Vec3::new(0., 0., 0.)),
-        CourtLine,
+        Transform::from_translation(position),
+        InitialTransform(position),
+        Collider(size),
+        paddle,
+        controller,
+        ThemeRole::Paddle,
     ));
+    match controller {
+        Controller::Player => {
+            entity.insert(Player);
+        }
+        Controller::Ai => {
+            entity.insert(AiAim::default());
+        }
+        _ => {}
+    }
+}
 
-    // Spawn top and bottom walls
-    commands.spawn((
-        Sprite {
-            color: Color::WHITE,
-            custom_size: Some(Vec2::new(1200.0, 2.0)), // Thin horizontal line
-            ..default()
-        },
-        Transform::from_translation(Vec3::new(0., COURT_HEIGHT, 0.)),
-        CourtLine,
-    ));
+// The court itself is built by the `ArenaPlugin`
+fn spawn_game_elements(mut commands: Commands, mut serve_events: EventWriter<ServeStarted>) {
+    serve(&mut commands, &mut serve_events);
+}
 
-    commands.spawn((
-        Sprite {
-            color: Color::WHITE,
-            custom_size: Some(Vec2::new(1200.0, 2.0)), // Thin horizontal line
-            ..default()
-        },
-        Transform::from_translation(Vec3::new(0., -COURT_HEIGHT, 0.)),
-        CourtLine,
-    ));
+fn announce_match(rules: Res<MatchRules>, mut match_started_events: EventWriter<MatchStarted>) {
+    match_started_events.send(MatchStarted { mode: rules.mode });
+}
 
-    // Spawn ball
-    commands.spawn((
-        Sprite {
-            color: Color::WHITE,
-            custom_size: Some(Vec2::new(20.0, 20.0)), // Square ball
-            ..default()
-        },
-        Transform::from_translation(Vec3::new(0., 0., 1.)),
-        InitialTransform(Vec3::new(0., 0., 1.)),
-        Ball,
+// Balls always start from the center of the court
+fn spawn_ball(commands: &mut Commands, direction: Vec2) -> Entity {
+    spawn_ball_at(
+        commands,
+        Vec2::ZERO,
         Velocity {
-            direction: Vec2::new(-1.0, 0.25).normalize(),
-            speed: 550.0,
+            direction: direction.normalize(),
+            speed: BALL_SPEED,
         },
-    ));
+    )
+    .id()
+}
+
+// A single ball starting a round
+fn serve(commands: &mut Commands, serve_events: &mut EventWriter<ServeStarted>) {
+    let ball = spawn_ball(commands, SERVE_DIRECTION);
+    serve_events.send(ServeStarted {
+        ball,
+        position: Vec2::ZERO,
+        velocity: SERVE_DIRECTION.normalize() * BALL_SPEED,
+    });
+}
 
-    // Spawn right paddle
+pub fn spawn_ball_at<'a>(
+    commands: &'a mut Commands,
+    position: Vec2,
+    velocity: Velocity,
+) -> EntityCommands<'a> {
     commands.spawn((
         Sprite {
             color: Color::WHITE,
-            custom_size: Some(Vec2::new(20.0, 100.0)),
+            custom_size: Some(BALL_SIZE),
             ..default()
         },
-        Transform::from_translation(Vec3::new(600., 0., 1.)),
-        InitialTransform(Vec3::new(600., 0., 1.)),
-        RightPaddle,
-    ));
+        Transform::from_translation(position.extend(1.)),
+        Collider(BALL_SIZE),
+        LastHitBy::default(),
+        Ball,
+        velocity,
+        ThemeRole::Ball,
+    ))
 }
 
 fn move_player(
     time: Res<Time>,
+    rules: Res<MatchRules>,
     actions: Res<Actions>,
-    mut player_query: Query<
-        &mut Transform,
-        (With<LeftPaddle>, Without<RightPaddle>, Without<Ball>),
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut paddle_query: Query<
+        (
+            &mut Transform,
+            &Paddle,
+            &Controller,
+            &Collider,
+            Has<ReversedControls>,
+        ),
+        Without<Ball>,
     >,
 ) {
-    if actions.player_movement.is_none() {
-        return;
-    }
-    let speed = 500.;
-    let movement = Vec3::new(
-        0.,
-        actions.player_movement.unwrap().y * speed * time.delta_secs(),
-        0.,
-    );
-    for mut player_transform in &mut player_query {
-        let new_y = (player_transform.translation.y + movement.y).clamp(
-            -COURT_HEIGHT + 50.0, // Half of paddle height (100/2)
-            COURT_HEIGHT - 50.0,  // Half of paddle height (100/2)
-        );
-        player_transform.translation.y = new_y;
+    for (mut transform, paddle, controller, collider, reversed) in &mut paddle_query {
+        let side = paddle.side;
+        let max_step = rules.handicaps[side.index()].paddle_speed() * time.delta_secs();
+        let axis = side.axis();
+        let current = transform.translation.truncate().dot(axis);
+        let direction = if reversed { -1.0 } else { 1.0 };
+        let movement = match controller {
+            Controller::Player => {
+                let Some(player_movement) = actions.player_movement else {
+                    continue;
+                };
+                match actions.player_target {
+                    // Dragged paddles stop on the touch rather than overshooting it
+                    Some(target_y) if axis == Vec2::Y => {
+                        (direction * target_y - current).clamp(-max_step, max_step)
+                    }
+                    _ => direction * player_movement.dot(axis) * max_step,
+                }
+            }
+            Controller::Keys => {
+                let keys = if paddle.forward {
+                    partner_keys(side)
+                } else {
+                    side
+                };
+                direction * key_input(keys, &keyboard_input) * max_step
+            }
+            Controller::Gamepad(index) => {
+                direction * gamepad_input(*index, side, &gamepads) * max_step
+            }
+            Controller::Ai => continue,
+        };
+        let position = clamp_to_court(current + movement, side, collider);
+        move_paddle_to(&mut transform, side, position);
     }
 }
 
-fn check_paddle_collision(ball_transform: &Transform, paddle_transform: &Transform) -> bool {
-    let ball_size = Vec2::new(20.0, 20.0);
-    let paddle_size = Vec2::new(20.0, 100.0);
+// Moves a paddle along its side, keeping its distance from the goal
+pub fn move_paddle_to(transform: &mut Transform, side: PaddleSide, position: f32) {
+    let axis = side.axis();
+    let current = transform.translation.truncate().dot(axis);
+    transform.translation.x += axis.x * (position - current);
+    transform.translation.y += axis.y * (position - current);
+}
 
-    let ball_min =
-        ball_transform.translation - Vec3::new(ball_size.x / 2.0, ball_size.y / 2.0, 0.0);
-    let ball_max =
-        ball_transform.translation + Vec3::new(ball_size.x / 2.0, ball_size.y / 2.0, 0.0);
+// Keeps a paddle of the given size within the reach of its side
+pub fn clamp_to_court(position: f32, side: PaddleSide, collider: &Collider) -> f32 {
+    let half_length = collider.0.dot(side.axis()) / 2.0;
+    let reach = side.reach();
+    position.clamp(-reach + half_length, reach - half_length)
+}
+
+pub fn check_collision(
+    transform: &Transform,
+    collider: &Collider,
+    other_transform: &Transform,
+    other_collider: &Collider,
+) -> bool {
+    let min = transform.translation.truncate() - collider.0 / 2.0;
+    let max = transform.translation.truncate() + collider.0 / 2.0;
 
-    let paddle_min =
-        paddle_transform.translation - Vec3::new(paddle_size.x / 2.0, paddle_size.y / 2.0, 0.0);
-    let paddle_max =
-        paddle_transform.translation + Vec3::new(paddle_size.x / 2.0, paddle_size.y / 2.0, 0.0);
+    let other_min = other_transform.translation.truncate() - other_collider.0 / 2.0;
+    let other_max = other_transform.translation.truncate() + other_collider.0 / 2.0;
 
-    ball_min.x <= paddle_max.x
-        && ball_max.x >= paddle_min.x
-        && ball_min.y <= paddle_max.y
-        && ball_max.y >= paddle_min.y
+    min.x <= other_max.x && max.x >= other_min.x && min.y <= other_max.y && max.y >= other_min.y
 }
 
-fn move_ball(
+#[allow(clippy::too_many_arguments)]
+pub fn move_ball(
     time: Res<Time>,
-    mut ball_query: Query<(&mut Transform, &mut Velocity), With<Ball>>,
-    paddle_query: Query<&Transform, (Or<(With<LeftPaddle>, With<RightPaddle>)>, Without<Ball>)>,
-    mut round_end_events: EventWriter<RoundEnd>,
+    rules: Res<MatchRules>,
+    mut ball_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Velocity,
+            &Collider,
+            &mut LastHitBy,
+        ),
+        (With<Ball>, Without<Caught>),
+    >,
+    paddle_query: Query<(Entity, &Transform, &Collider, &Paddle), Without<Ball>>,
+    obstacle_query: Query<(Entity, &Transform, &Collider), (With<Obstacle>, Without<Ball>)>,
+    court: Res<Court>,
+    mut goal_events: EventWriter<Goal>,
+    mut paddle_hit_events: EventWriter<BallHitPaddle>,
+    mut wall_hit_events: EventWriter<BallHitWall>,
 ) {
-    for (mut transform, mut velocity) in &mut ball_query {
+    for (ball, mut transform, mut velocity, collider, mut last_hit_by) in &mut ball_query {
         let movement_vec = velocity.direction * velocity.speed * time.delta_secs();
         transform.translation.x += movement_vec.x;
         transform.translation.y += movement_vec.y;
 
-        // Bounce off top and bottom walls
+        // Bounce off top and bottom walls, unless the ball is going into a goal there
         if transform.translation.y.abs() > COURT_HEIGHT {
-            transform.translation.y = transform.translation.y.signum() * COURT_HEIGHT;
-            velocity.direction.y *= -1.0;
+            let side = transform.translation.y.signum();
+            let wall = if side > 0. {
+                PaddleSide::Top
+            } else {
+                PaddleSide::Bottom
+            };
+            if court.is_goal_mouth(wall, transform.translation.truncate()) {
+                // Scored below, once the paddle of that side had its chance
+            } else if court.is_gap(transform.translation.x, transform.translation.y) {
+                // Through a gap in the wall the ball comes back in at the opposite wall
+                transform.translation.y = -side * COURT_HEIGHT;
+            } else {
+                transform.translation.y = side * COURT_HEIGHT;
+                velocity.direction.y *= -1.0;
+                wall_hit_events.send(BallHitWall {
+                    ball,
+                    position: transform.translation.truncate(),
+                    obstacle: None,
+                });
+            }
         }
 
-        // Check for paddle collisions
-        for paddle_transform in &paddle_query {
-            if check_paddle_collision(&transform, paddle_transform) {
-                // Move the ball out of the paddle
-                let paddle_x = paddle_transform.translation.x;
-                let ball_x = transform.translation.x;
-                let ball_size = 20.0;
-                let paddle_size = 20.0;
-
-                // Move ball to the edge of the paddle based on which side it hit
-                if ball_x < paddle_x {
-                    transform.translation.x = paddle_x - (paddle_size / 2.0 + ball_size / 2.0);
-                } else {
-                    transform.translation.x = paddle_x + (paddle_size / 2.0 + ball_size / 2.0);
-                }
+        // Bounce off obstacles
+        for (obstacle, obstacle_transform, obstacle_collider) in &obstacle_query {
+            if !check_collision(&transform, collider, obstacle_transform, obstacle_collider) {
+                continue;
+            }
+            // Push the ball out on the side it overlaps the least, and send it away from that side
+            let offset = (transform.translation - obstacle_transform.translation).truncate();
+            let overlap = (collider.0 + obstacle_collider.0) / 2.0 - offset.abs();
+            if overlap.x < overlap.y {
+                transform.translation.x += overlap.x * offset.x.signum();
+                velocity.direction.x = velocity.direction.x.abs() * offset.x.signum();
+            } else {
+                transform.translation.y += overlap.y * offset.y.signum();
+                velocity.direction.y = velocity.direction.y.abs() * offset.y.signum();
+            }
+            wall_hit_events.send(BallHitWall {
+                ball,
+                position: transform.translation.truncate(),
+                obstacle: Some(obstacle),
+            });
+        }
 
-                velocity.direction.x *= -1.0;
-                // Add a slight vertical angle based on where the ball hits the paddle
-                let relative_intersect_y =
-                    (transform.translation.y - paddle_transform.translation.y) / 50.0;
-                velocity.direction.y = relative_intersect_y.clamp(-0.8, 0.8);
-                velocity.direction = velocity.direction.normalize();
-                break;
+        // Check for paddle collisions
+        for (paddle_entity, paddle_transform, paddle_collider, paddle) in &paddle_query {
+            // Paddles only return balls heading for their own goal,
+            // so a ball coming from behind a paddle in mid-court passes through it
+            let facing = paddle.side.facing();
+            let axis = paddle.side.axis();
+            let hitbox = rules.handicaps[paddle.side.index()].hitbox(paddle.side, paddle_collider);
+            if velocity.direction.dot(facing) >= 0.
+                || !check_collision(&transform, collider, paddle_transform, &hitbox)
+            {
+                continue;
             }
+            // Move the ball out of the paddle, onto the side facing the court
+            let paddle_position = paddle_transform.translation.truncate();
+            let in_front = paddle_position
+                + facing * (paddle_collider.0.dot(facing.abs()) + collider.0.dot(facing.abs()))
+                    / 2.0;
+            let position =
+                axis * transform.translation.truncate().dot(axis) + facing * in_front.dot(facing);
+            transform.translation.x = position.x;
+            transform.translation.y = position.y;
+
+            // Add a slight angle based on where the ball hits the paddle
+            let relative_intersect = ((position - paddle_position).dot(axis)
+                / (paddle_collider.0.dot(axis) / 2.0))
+                .clamp(-1.0, 1.0);
+            velocity.direction = (facing * velocity.direction.dot(facing).abs()
+                + axis * relative_intersect.clamp(-0.8, 0.8))
+            .normalize();
+            last_hit_by.0 = Some(paddle_entity);
+            paddle_hit_events.send(BallHitPaddle {
+                ball,
+                paddle: paddle_entity,
+                side: paddle.side,
+                position,
+                offset: relative_intersect,
+                speed: velocity.speed,
+            });
+            break;
         }
 
         // Check if ball left the screen
-        if transform.translation.x > 600.0 {
-            round_end_events.send(RoundEnd {
-                winner: PaddleSide::Left,
-            });
-        } else if transform.translation.x < -600.0 {
-            round_end_events.send(RoundEnd {
-                winner: PaddleSide::Right,
+        let position = transform.translation.truncate();
+        let conceded = if position.y.abs() > COURT_HEIGHT {
+            Some(if position.y > 0. {
+                PaddleSide::Top
+            } else {
+                PaddleSide::Bottom
+            })
+        } else if position.x.abs() > COURT_HALF_WIDTH {
+            let side = if position.x > 0. {
+                PaddleSide::Right
+            } else {
+                PaddleSide::Left
+            };
+            if court.is_goal_mouth(side, position) {
+                Some(side)
+            } else {
+                // Outside the goal mouth the end of the court is a wall
+                transform.translation.x = position.x.signum() * COURT_HALF_WIDTH;
+                velocity.direction.x *= -1.0;
+                wall_hit_events.send(BallHitWall {
+                    ball,
+                    position: transform.translation.truncate(),
+                    obstacle: None,
+                });
+                None
+            }
+        } else {
+            None
+        };
+        if let Some(conceded) = conceded {
+            let scorer = match rules.mode {
+                GameMode::Classic
+                | GameMode::Doubles
+                | GameMode::TimeAttack
+                | GameMode::Survival => Some(conceded.opposite()),
+                GameMode::Practice | GameMode::Breakout => None,
+                // With more than two sides the point goes to whoever touched the ball last
+                GameMode::FourPlayer => last_hit_by
+                    .0
+                    .and_then(|paddle| paddle_query.get(paddle).ok())
+                    .map(|(_, _, _, paddle)| paddle.side)
+                    .filter(|side| *side != conceded),
+            };
+            goal_events.send(Goal {
+                ball,
+                conceded,
+                scorer,
             });
         }
     }
 }
 
-fn move_ai_paddle(
+// In multi-ball matches another ball is served every few paddle hits of a rally
+fn add_rally_balls(
+    mut commands: Commands,
+    rules: Res<MatchRules>,
+    mut rally: ResMut<Rally>,
+    mut rng: ResMut<GameRng>,
+    mut paddle_hit_events: EventReader<BallHitPaddle>,
+    ball_query: Query<(), With<Ball>>,
+) {
+    let multi_ball = &rules.multi_ball;
+    let mut balls = ball_query.iter().count();
+    for _ in paddle_hit_events.read() {
+        rally.hits += 1;
+        if multi_ball.enabled
+            && rally.hits.is_multiple_of(multi_ball.hits_per_ball)
+            && balls < multi_ball.max_balls
+        {
+            let towards = if rng.0.gen_bool(0.5) { 1.0 } else { -1.0 };
+            spawn_ball(
+                &mut commands,
+                Vec2::new(towards, rng.0.gen_range(-0.5..0.5)),
+            );
+            balls += 1;
+        }
+    }
+}
+
+fn curve_spinning_balls(time: Res<Time>, mut ball_query: Query<(&mut Velocity, &Spin)>) {
+    for (mut velocity, spin) in &mut ball_query {
+        let turn = Vec2::from_angle(spin.0.to_radians() * time.delta_secs());
+        velocity.direction = turn.rotate(velocity.direction).normalize();
+    }
+}
+
+// Hitting a ball takes the spin off it, unless the computer paddle hitting it puts spin on its returns
+fn spin_returns(
+    mut commands: Commands,
+    ai_skill: Res<AiSkill>,
+    mut rng: ResMut<GameRng>,
+    mut paddle_hit_events: EventReader<BallHitPaddle>,
+    paddle_query: Query<&Controller>,
+) {
+    for event in paddle_hit_events.read() {
+        let by_computer = paddle_query.get(event.paddle) == Ok(&Controller::Ai);
+        let spin = ai_skill.for_side(event.side).spin;
+        if by_computer && spin != 0. {
+            let curve = if rng.0.gen_bool(0.5) { spin } else { -spin };
+            commands.entity(event.ball).try_insert(Spin(curve));
+        } else {
+            commands.entity(event.ball).remove::<Spin>();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn move_ai_paddle(
     time: Res<Time>,
-    ball_query: Query<&Transform, (With<Ball>, Without<LeftPaddle>, Without<RightPaddle>)>,
+    rules: Res<MatchRules>,
+    actions: Res<Actions>,
+    ai_skill: Res<AiSkill>,
+    active_policy: Option<Res<ActivePolicy>>,
+    mut rng: ResMut<GameRng>,
+    ball_query: Query<(&Transform, &Velocity), (With<Ball>, Without<Paddle>)>,
     mut paddle_query: Query<
-        &mut Transform,
-        (With<RightPaddle>, Without<LeftPaddle>, Without<Ball>),
+        (
+            &mut Transform,
+            &Paddle,
+            &Controller,
+            &Collider,
+            Has<ReversedControls>,
+            Option<&mut AiAim>,
+        ),
+        Without<Ball>,
     >,
 ) {
-    if let Ok(ball_transform) = ball_query.get_single() {
-        let ball_y = ball_transform.translation.y;
-        let ai_speed = 250.0;
-
-        for mut paddle_transform in &mut paddle_query {
-            let target_y = ball_y;
-            let current_y = paddle_transform.translation.y;
-            let diff = target_y - current_y;
-
-            // Move towards the ball's y position
-            let movement = diff.signum() * ai_speed * time.delta_secs();
-            let new_y = (current_y + movement).clamp(
-                -COURT_HEIGHT + 50.0, // Half of paddle height (100/2)
-                COURT_HEIGHT - 50.0,  // Half of paddle height (100/2)
-            );
-            paddle_transform.translation.y = new_y;
+    for (mut paddle_transform, paddle, controller, collider, reversed, aim) in &mut paddle_query {
+        if *controller != Controller::Ai {
+            continue;
         }
+        let side = paddle.side;
+        // Sides steered by a policy are moved by the `PolicyPlugin` instead
+        if active_policy
+            .as_ref()
+            .is_some_and(|active_policy| active_policy.for_side(side).is_some())
+        {
+            continue;
+        }
+        let personality = ai_skill.for_side(side);
+        let speed_scale = rules.handicaps[side.index()].speed_scale();
+        let max_step = PADDLE_SPEED * speed_scale * time.delta_secs();
+        let paddle_position = paddle_transform.translation.truncate();
+        move_computer_paddle(
+            &mut paddle_transform,
+            paddle,
+            collider,
+            reversed,
+            max_step,
+            actions.opponent_target,
+            |current| {
+                let (ball, velocity) =
+                    most_threatening_ball(paddle_position, side.facing(), &ball_query)?;
+                let mut target = if personality.anticipate {
+                    predict_ball(ball, velocity, paddle_position, side)
+                } else {
+                    ball.dot(side.axis())
+                };
+                if let Some(mut aim) = aim {
+                    aim.timer -= time.delta_secs();
+                    if aim.timer <= 0. {
+                        aim.timer = personality.reaction.max(MIN_AIM_TIME);
+                        aim.offset = rng.0.gen_range(-1.0..=1.0) * personality.error;
+                        aim.target = target;
+                    }
+                    // Slow to react, the paddle keeps going where the ball was when it last looked
+                    if personality.reaction > 0. {
+                        target = aim.target;
+                    }
+                    target += aim.offset;
+                }
+                // Move towards the target along the side
+                let step = personality.speed * speed_scale * time.delta_secs();
+                Some((target - current).clamp(-step, step))
+            },
+        );
     }
 }
 
+// Moves a computer paddle along its side. `computer_movement` gets where the paddle is and gives how far the
+// computer moves it this frame, or none to leave it where it is. A second player dragging the right paddle
+// takes over from the computer, moving it by at most `max_step`. Reversed controls turn either movement around.
+pub fn move_computer_paddle(
+    transform: &mut Transform,
+    paddle: &Paddle,
+    collider: &Collider,
+    reversed: bool,
+    max_step: f32,
+    opponent_target: Option<f32>,
+    computer_movement: impl FnOnce(f32) -> Option<f32>,
+) {
+    let side = paddle.side;
+    let current = transform.translation.truncate().dot(side.axis());
+    let direction = if reversed { -1.0 } else { 1.0 };
+    let movement = match opponent_target {
+        Some(target_y) if side == PaddleSide::Right && !paddle.forward => {
+            (direction * target_y - current).clamp(-max_step, max_step)
+        }
+        _ => match computer_movement(current) {
+            Some(movement) => direction * movement,
+            None => return,
+        },
+    };
+    let position = clamp_to_court(current + movement, side, collider);
+    move_paddle_to(transform, side, position);
+}
+
+// Seconds a computer paddle sticks with how far off it is, even when it looks at the ball all the time
+const MIN_AIM_TIME: f32 = 0.5;
+
+// Where along the side a ball will reach the paddle, bouncing off the walls on the way
+fn predict_ball(ball: Vec2, velocity: Vec2, paddle_position: Vec2, side: PaddleSide) -> f32 {
+    let facing = side.facing();
+    let approach = -velocity.dot(facing);
+    let axis = side.axis();
+    if approach <= 0. {
+        return ball.dot(axis);
+    }
+    let time_to_reach = (ball - paddle_position).dot(facing) / approach;
+    let unbounded = (ball + velocity * time_to_reach).dot(axis);
+    // Fold the straight path back into the court at each wall it would bounce off
+    let wall = match side {
+        PaddleSide::Left | PaddleSide::Right => COURT_HEIGHT,
+        PaddleSide::Top | PaddleSide::Bottom => COURT_HALF_WIDTH,
+    };
+    let folded = (unbounded + wall).rem_euclid(wall * 4.0);
+    if folded <= wall * 2.0 {
+        folded - wall
+    } else {
+        wall * 3.0 - folded
+    }
+}
+
+// The ball a paddle has to deal with first: the one that reaches it soonest,
+// or the closest one if no ball is heading its way.
+// Returns its position and velocity.
+pub fn most_threatening_ball<'a>(
+    paddle_position: Vec2,
+    facing: Vec2,
+    balls: impl IntoIterator<Item = (&'a Transform, &'a Velocity)>,
+) -> Option<(Vec2, Vec2)> {
+    balls
+        .into_iter()
+        .map(|(transform, velocity)| {
+            let position = transform.translation.truncate();
+            let distance = (position - paddle_position).dot(facing);
+            let time_to_reach = distance / (-velocity.direction.dot(facing) * velocity.speed);
+            // Balls heading away sort after every incoming one
+            let velocity = velocity.direction * velocity.speed;
+            if time_to_reach > 0. {
+                (false, time_to_reach, position, velocity)
+            } else {
+                (true, distance.abs(), position, velocity)
+            }
+        })
+        .min_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)))
+        .map(|(_, _, position, velocity)| (position, velocity))
+}
+
 fn handle_escape_key(
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    match_exit: Res<MatchExit>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
     if keyboard_input.just_pressed(KeyCode::Escape) {
-        next_state.set(GameState::Menu);
+        next_state.set(match_exit.0.clone());
     }
 }
 
@@ -315,6 +975,7 @@ fn handle_pause_key(
         match current_state.get() {
             PlayingState::Paused => next_state.set(PlayingState::Playing),
             PlayingState::Playing => next_state.set(PlayingState::Paused),
+            PlayingState::Over => {}
         }
     }
 }
@@ -322,6 +983,8 @@ fn handle_pause_key(
 fn spawn_pause_text(mut commands: Commands) {
     commands.spawn((
         Text::new("Play/Pause (space)"),
+        TextColor(Color::WHITE),
+        ThemeRole::UiText,
         Node {
             position_type: PositionType::Absolute,
             bottom: Val::Px(20.0),
@@ -332,15 +995,105 @@ fn spawn_pause_text(mut commands: Commands) {
     ));
 }
 
-fn spawn_score_text(mut commands: Commands, score: Res<Score>) {
-    // Player score text
+fn spawn_touch_buttons(mut commands: Commands) {
+    for (button, label, left) in [
+        (TouchButton::Pause, "Play/Pause", Val::Px(20.0)),
+        (TouchButton::Back, "Back", Val::Px(200.0)),
+    ] {
+        commands
+            .spawn((
+                Button,
+                Node {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(20.0),
+                    left,
+                    width: Val::Px(160.0),
+                    height: Val::Px(60.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(Color::linear_rgba(0.15, 0.15, 0.15, 0.6)),
+                button,
+            ))
+            .with_child((
+                Text::new(label),
+                TextFont {
+                    font_size: 30.0,
+                    ..default()
+                },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            ));
+    }
+}
+
+fn handle_touch_buttons(
+    interaction_query: Query<(&Interaction, &TouchButton), Changed<Interaction>>,
+    current_playing_state: Res<State<PlayingState>>,
+    match_exit: Res<MatchExit>,
+    mut next_playing_state: ResMut<NextState<PlayingState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, button) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            TouchButton::Pause => match current_playing_state.get() {
+                PlayingState::Paused => next_playing_state.set(PlayingState::Playing),
+                PlayingState::Playing => next_playing_state.set(PlayingState::Paused),
+                PlayingState::Over => {}
+            },
+            TouchButton::Back => next_game_state.set(match_exit.0.clone()),
+        }
+    }
+}
+
+// Who the score shows for a side: the computer playing it by name, or else the player
+pub fn scorer_name(rules: &MatchRules, ai_skill: &AiSkill, side: PaddleSide) -> String {
+    match rules.controllers[side.index()] {
+        Controller::Ai => ai_skill.for_side(side).name.clone(),
+        _ => "Player".to_string(),
+    }
+}
+
+// What the score shows for a side during a match, its head start included
+fn score_label(
+    rules: &MatchRules,
+    ai_skill: &AiSkill,
+    score: &Score,
+    stats: &MatchStats,
+    side: PaddleSide,
+) -> String {
+    format!(
+        "{} - {}",
+        scorer_name(rules, ai_skill, side),
+        score.get(side) + stats.handicaps[side.index()].head_start
+    )
+}
+
+fn spawn_score_text(
+    mut commands: Commands,
+    score: Res<Score>,
+    stats: Res<MatchStats>,
+    rules: Res<MatchRules>,
+    ai_skill: Res<AiSkill>,
+) {
+    // Left score text
     commands.spawn((
-        Text::new(format!("Player - {}", score.player)),
+        Text::new(score_label(
+            &rules,
+            &ai_skill,
+            &score,
+            &stats,
+            PaddleSide::Left,
+        )),
         TextFont {
             font_size: 40.0,
             ..default()
         },
         TextColor(Color::WHITE),
+        ThemeRole::UiText,
         Node {
             position_type: PositionType::Absolute,
             top: Val::Px(COURT_HEIGHT + 20.0),
@@ -350,14 +1103,21 @@ fn spawn_score_text(mut commands: Commands, score: Res<Score>) {
         ScoreText,
     ));
 
-    // Computer score text
+    // Right score text
     commands.spawn((
-        Text::new(format!("Computer - {}", score.computer)),
+        Text::new(score_label(
+            &rules,
+            &ai_skill,
+            &score,
+            &stats,
+            PaddleSide::Right,
+        )),
         TextFont {
             font_size: 40.0,
             ..default()
         },
         TextColor(Color::WHITE),
+        ThemeRole::UiText,
         Node {
             position_type: PositionType::Absolute,
             top: Val::Px(COURT_HEIGHT + 20.0),
@@ -368,66 +1128,316 @@ fn spawn_score_text(mut commands: Commands, score: Res<Score>) {
     ));
 }
 
-fn handle_round_end(
-    mut round_end_events: EventReader<RoundEnd>,
-    mut dynamic_elements: Query<
-        (&mut Transform, &InitialTransform),
-        Or<(With<Ball>, With<LeftPaddle>, With<RightPaddle>)>,
-    >,
-    mut ball_query: Query<&mut Velocity, With<Ball>>,
+// Every ball that leaves the court scores on its own, and the round ends as the match rules say
+#[allow(clippy::too_many_arguments)]
+fn score_goals(
+    mut commands: Commands,
+    rules: Res<MatchRules>,
+    ai_skill: Res<AiSkill>,
+    rally: Res<Rally>,
+    stats: Res<MatchStats>,
+    mut goal_events: EventReader<Goal>,
+    mut point_events: EventWriter<PointScored>,
+    ball_query: Query<(), With<Ball>>,
     mut score: ResMut<Score>,
-    mut next_state: ResMut<NextState<PlayingState>>,
     mut score_text_query: Query<(&mut Text, &Node), With<ScoreText>>,
+    mut round_end_events: EventWriter<RoundEnd>,
 ) {
-    for event in round_end_events.read() {
+    if goal_events.is_empty() {
+        return;
+    }
+    let mut balls_left = ball_query.iter().count();
+    let mut round_over = false;
+    for goal in goal_events.read() {
         // Update score
-        match event.winner {
-            PaddleSide::Left => score.player += 1,
-            PaddleSide::Right => score.computer += 1,
+        if let Some(scorer) = goal.scorer {
+            score.add_point(scorer);
+            point_events.send(PointScored {
+                scorer,
+                conceded: goal.conceded,
+                rally: rally.hits,
+                score: PaddleSide::ALL.map(|side| score.get(side)),
+            });
         }
 
-        // Update score text
-        for (mut text, node) in &mut score_text_query {
-            if node.left == Val::Px(300.0) {
-                // Player score (left side)
-                text.0 = format!("Player - {}", score.player);
-            } else {
-                // Computer score (right side)
-                text.0 = format!("Computer - {}", score.computer);
-            }
+        commands.entity(goal.ball).despawn();
+        balls_left = balls_left.saturating_sub(1);
+        // Practice drills keep serving on their own instead
+        let ends_round = rules.mode != GameMode::Practice
+            && (balls_left == 0 || rules.multi_ball.round_end == RoundEndRule::EachGoal);
+        if ends_round && !round_over {
+            round_over = true;
+            round_end_events.send(RoundEnd);
         }
+    }
 
-        // Reset all dynamic elements to their initial positions
-        for (mut transform, initial) in &mut dynamic_elements {
-            transform.translation = initial.0;
-        }
+    // Update score text
+    for (mut text, node) in &mut score_text_query {
+        let side = if node.left == Val::Px(300.0) {
+            PaddleSide::Left
+        } else {
+            PaddleSide::Right
+        };
+        text.0 = score_label(&rules, &ai_skill, &score, &stats, side);
+    }
+}
 
-        // Reset ball velocity
-        if let Ok(mut velocity) = ball_query.get_single_mut() {
-            velocity.direction = Vec2::new(-1.0, 0.25).normalize();
-        }
+fn start_match_stats(rules: Res<MatchRules>, score: Res<Score>, mut stats: ResMut<MatchStats>) {
+    *stats = MatchStats {
+        start_score: PaddleSide::ALL.map(|side| score.get(side)),
+        ..default()
+    };
+    for side in rules.mode.sides() {
+        stats.handicaps[side.index()] = rules.handicaps[side.index()];
+    }
+}
+
+fn record_match_stats(time: Res<Time>, rally: Res<Rally>, mut stats: ResMut<MatchStats>) {
+    stats.duration += time.delta_secs();
+    stats.longest_rally = stats.longest_rally.max(rally.hits);
+}
 
-        // Pause game
-        next_state.set(PlayingState::Paused);
+pub fn handle_round_end(
+    mut commands: Commands,
+    mut round_end_events: EventReader<RoundEnd>,
+    mut serve_events: EventWriter<ServeStarted>,
+    mut paddle_query: Query<(&mut Transform, &InitialTransform), With<Paddle>>,
+    ball_query: Query<Entity, With<Ball>>,
+    mut rally: ResMut<Rally>,
+    mut next_state: ResMut<NextState<PlayingState>>,
+) {
+    if round_end_events.read().count() == 0 {
+        return;
+    }
+
+    // Reset the paddles to their initial positions
+    for (mut transform, initial) in &mut paddle_query {
+        transform.translation = initial.0;
+    }
+
+    // Clear the court and serve a single new ball
+    for ball in &ball_query {
+        commands.entity(ball).despawn();
+    }
+    serve(&mut commands, &mut serve_events);
+    rally.hits = 0;
+
+    // Pause game
+    next_state.set(PlayingState::Paused);
+}
+
+// Classic and doubles matches are played until they are left, and end then without a winner. So do matches of
+// the modes built on them, like the career, if they are left before they were decided.
+pub fn end_unfinished_match(
+    rules: Res<MatchRules>,
+    playing_state: Res<State<PlayingState>>,
+    score: Res<Score>,
+    stats: Res<MatchStats>,
+    mut match_ended_events: EventWriter<MatchEnded>,
+) {
+    if !matches!(rules.mode, GameMode::Classic | GameMode::Doubles)
+        || *playing_state.get() == PlayingState::Over
+    {
+        return;
     }
+    match_ended_events.send(MatchEnded {
+        winner: None,
+        points: stats.match_points(&score),
+    });
 }
 
 fn cleanup_game(
     mut commands: Commands,
+    mut rally: ResMut<Rally>,
+    mut ai_skill: ResMut<AiSkill>,
+    mut next_state: ResMut<NextState<PlayingState>>,
     game_entities: Query<
         Entity,
         Or<(
             With<Ball>,
-            With<LeftPaddle>,
-            With<RightPaddle>,
+            With<Paddle>,
             With<Player>,
             With<CourtLine>,
             With<PauseText>,
             With<ScoreText>,
+            With<TouchButton>,
         )>,
     >,
 ) {
     for entity in game_entities.iter() {
         commands.entity(entity).despawn_recursive();
     }
+    rally.hits = 0;
+    *ai_skill = AiSkill::default();
+    // A decided match must not carry over into the next one
+    next_state.set(PlayingState::Paused);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::{headless_app, play_headless_frame};
+    use crate::rules::MultiBallRules;
+
+    #[test]
+    fn match_points_add_head_starts_to_points_since_the_start() {
+        let mut score = Score::default();
+        for _ in 0..3 {
+            score.add_point(PaddleSide::Left);
+        }
+        score.add_point(PaddleSide::Right);
+        let mut stats = MatchStats {
+            start_score: [2, 1, 0, 0],
+            ..default()
+        };
+        stats.handicaps[PaddleSide::Right.index()].head_start = 2;
+        assert_eq!(stats.match_points(&score), [1, 2, 0, 0]);
+        // The running score itself is left alone
+        assert_eq!(score.get(PaddleSide::Right), 1);
+    }
+
+    #[test]
+    fn decided_winner_needs_the_points_and_a_lead() {
+        let sides = [PaddleSide::Left, PaddleSide::Right];
+        assert_eq!(decided_winner(&[4, 3, 0, 0], &sides, 5), None);
+        assert_eq!(
+            decided_winner(&[4, 5, 0, 0], &sides, 5),
+            Some(PaddleSide::Right)
+        );
+        // Multi-ball can take both sides past the target at once
+        assert_eq!(
+            decided_winner(&[6, 5, 0, 0], &sides, 5),
+            Some(PaddleSide::Left)
+        );
+        assert_eq!(decided_winner(&[5, 5, 0, 0], &sides, 5), None);
+        // Only the sides playing count
+        assert_eq!(
+            decided_winner(&[5, 3, 9, 0], &sides, 5),
+            Some(PaddleSide::Left)
+        );
+        assert_eq!(decided_winner(&[1, 2, 3, 3], &PaddleSide::ALL, 3), None);
+    }
+
+    #[test]
+    fn most_threatening_ball_is_the_first_to_arrive() {
+        let paddle = PaddleSide::Left.home();
+        let ball = |x: f32, direction: Vec2, speed: f32| {
+            (
+                Transform::from_xyz(x, 0., 0.),
+                Velocity { direction, speed },
+            )
+        };
+        // A fast ball from further away arrives before a slow close one, and one heading away comes last
+        let balls = [
+            ball(-400., Vec2::NEG_X, 50.),
+            ball(0., Vec2::NEG_X, 500.),
+            ball(-550., Vec2::X, 300.),
+        ];
+        let threat = most_threatening_ball(paddle, Vec2::X, balls.iter().map(|(t, v)| (t, v)));
+        assert_eq!(threat, Some((Vec2::ZERO, Vec2::NEG_X * 500.)));
+
+        // With every ball heading away, the closest one
+        let balls = [ball(200., Vec2::X, 300.), ball(-300., Vec2::X, 300.)];
+        let threat = most_threatening_ball(paddle, Vec2::X, balls.iter().map(|(t, v)| (t, v)));
+        assert_eq!(threat, Some((Vec2::new(-300., 0.), Vec2::X * 300.)));
+        assert_eq!(most_threatening_ball(paddle, Vec2::X, []), None);
+    }
+
+    #[test]
+    fn rallies_add_balls_up_to_the_limit() {
+        let rules = MatchRules {
+            controllers: [Controller::Ai; 4],
+            power_ups: false,
+            multi_ball: MultiBallRules {
+                enabled: true,
+                hits_per_ball: 2,
+                max_balls: 3,
+                ..default()
+            },
+            ..default()
+        };
+        let mut app = headless_app(rules, default(), 7);
+        let mut most_balls = 0;
+        for _ in 0..3000 {
+            play_headless_frame(&mut app);
+            let world = app.world_mut();
+            let balls = world.query_filtered::<(), With<Ball>>().iter(world).count();
+            most_balls = most_balls.max(balls);
+        }
+        assert_eq!(most_balls, 3);
+    }
+
+    #[test]
+    fn doubles_puts_a_forward_paddle_between_each_goal_and_the_net() {
+        let rules = MatchRules {
+            mode: GameMode::Doubles,
+            ..default()
+        };
+        let mut app = headless_app(rules, default(), 0);
+        play_headless_frame(&mut app);
+        let world = app.world_mut();
+        let mut paddles: Vec<_> = world
+            .query::<(&Paddle, &Transform)>()
+            .iter(world)
+            .map(|(paddle, transform)| (paddle.side, paddle.forward, transform.translation.x))
+            .collect();
+        paddles.sort_by(|a, b| a.2.total_cmp(&b.2));
+        assert_eq!(
+            paddles,
+            [
+                (PaddleSide::Left, false, -COURT_HALF_WIDTH),
+                (PaddleSide::Left, true, -FORWARD_PADDLE_DISTANCE),
+                (PaddleSide::Right, true, FORWARD_PADDLE_DISTANCE),
+                (PaddleSide::Right, false, COURT_HALF_WIDTH),
+            ]
+        );
+    }
+
+    #[test]
+    fn moving_a_paddle_keeps_its_distance_from_the_goal() {
+        let mut transform = Transform::from_xyz(-FORWARD_PADDLE_DISTANCE, 10., 1.);
+        move_paddle_to(&mut transform, PaddleSide::Left, -50.);
+        assert_eq!(
+            transform.translation,
+            Vec3::new(-FORWARD_PADDLE_DISTANCE, -50., 1.)
+        );
+        let mut transform = Transform::from_xyz(40., COURT_HEIGHT, 1.);
+        move_paddle_to(&mut transform, PaddleSide::Top, 100.);
+        assert_eq!(transform.translation, Vec3::new(100., COURT_HEIGHT, 1.));
+    }
+
+    #[test]
+    fn predict_ball_follows_bounces_off_the_walls() {
+        let paddle = PaddleSide::Left.home();
+        // Straight on, and with a bounce off the top wall on the way
+        assert_eq!(
+            predict_ball(
+                Vec2::new(0., 20.),
+                Vec2::new(-300., 0.),
+                paddle,
+                PaddleSide::Left
+            ),
+            20.
+        );
+        let bounced = predict_ball(
+            Vec2::new(0., 200.),
+            Vec2::new(-300., 300.),
+            paddle,
+            PaddleSide::Left,
+        );
+        assert!(
+            (bounced - (COURT_HEIGHT * 2. - 800.)).abs() < 0.01,
+            "{bounced}"
+        );
+        // A ball heading away is followed where it is
+        assert_eq!(
+            predict_ball(
+                Vec2::new(0., 50.),
+                Vec2::new(300., 100.),
+                paddle,
+                PaddleSide::Left
+            ),
+            50.
+        );
+    }
 }
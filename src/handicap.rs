@@ -0,0 +1,301 @@
+use crate::game::{Collider, PaddleSide, PADDLE_SPEED};
+use crate::rules::MatchRules;
+use crate::theme::ThemeRole;
+use crate::ui::{next_value, on_off, spawn_button, ChangeState};
+use crate::{GameSet, GameState};
+use bevy::prelude::*;
+
+pub struct HandicapPlugin;
+
+/// This plugin draws the setup screen shown before every match, where each side playing is given its
+/// [`Handicap`]. The handicaps are kept in the [`MatchRules`], so they carry over to the next match.
+impl Plugin for HandicapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Setup), setup_match_setup)
+            .add_systems(
+                Update,
+                (handle_handicap_click, handle_setup_escape_key)
+                    .in_set(GameSet::Input)
+                    .run_if(in_state(GameState::Setup)),
+            )
+            .add_systems(OnExit(GameState::Setup), cleanup_match_setup);
+    }
+}
+
+// Modifiers evening out a match between players of different strength
+#[derive(Clone, Copy, PartialEq)]
+pub struct Handicap {
+    // Length of the paddle, in percent of the normal length
+    pub paddle_size: u32,
+    // Speed of the paddle, in percent of the normal speed
+    pub paddle_speed: u32,
+    // Points the side starts every match with, counted towards winning it but kept out of the running score
+    pub head_start: u32,
+    // The paddle returns balls that only just miss it
+    pub aim_assist: bool,
+}
+
+impl Default for Handicap {
+    fn default() -> Self {
+        Handicap {
+            paddle_size: 100,
+            paddle_speed: 100,
+            head_start: 0,
+            aim_assist: false,
+        }
+    }
+}
+
+// How far past each end of the paddle aim assist still returns a ball
+const AIM_ASSIST_REACH: f32 = 15.0;
+
+impl Handicap {
+    pub fn paddle_size(&self, side: PaddleSide) -> Vec2 {
+        let scale = self.paddle_size as f32 / 100.0;
+        side.paddle_size() * (side.axis() * (scale - 1.0) + Vec2::ONE)
+    }
+
+    // Scales the speed of any paddle, including the computer's
+    pub fn speed_scale(&self) -> f32 {
+        self.paddle_speed as f32 / 100.0
+    }
+
+    pub fn paddle_speed(&self) -> f32 {
+        PADDLE_SPEED * self.speed_scale()
+    }
+
+    // The box the ball is checked against for a paddle of the side, which aim assist stretches along the side
+    pub fn hitbox(&self, side: PaddleSide, collider: &Collider) -> Collider {
+        if self.aim_assist {
+            Collider(collider.0 + side.axis() * AIM_ASSIST_REACH * 2.0)
+        } else {
+            *collider
+        }
+    }
+}
+
+const PADDLE_SIZES: [u32; 5] = [60, 80, 100, 120, 150];
+const PADDLE_SPEEDS: [u32; 5] = [60, 80, 100, 120, 150];
+const HEAD_STARTS: [u32; 5] = [0, 1, 2, 3, 5];
+
+// Each handicap button cycles one modifier of a side through its possible values
+#[derive(Component, Clone, Copy)]
+struct HandicapButton {
+    side: PaddleSide,
+    setting: HandicapSetting,
+}
+
+#[derive(Clone, Copy)]
+enum HandicapSetting {
+    PaddleSize,
+    PaddleSpeed,
+    HeadStart,
+    AimAssist,
+}
+
+impl HandicapSetting {
+    const ALL: [HandicapSetting; 4] = [
+        HandicapSetting::PaddleSize,
+        HandicapSetting::PaddleSpeed,
+        HandicapSetting::HeadStart,
+        HandicapSetting::AimAssist,
+    ];
+
+    fn label(&self, handicap: &Handicap) -> String {
+        match self {
+            HandicapSetting::PaddleSize => format!("Paddle size: {}%", handicap.paddle_size),
+            HandicapSetting::PaddleSpeed => format!("Paddle speed: {}%", handicap.paddle_speed),
+            HandicapSetting::HeadStart => format!("Head start: {}", handicap.head_start),
+            HandicapSetting::AimAssist => format!("Aim assist: {}", on_off(handicap.aim_assist)),
+        }
+    }
+
+    fn cycle(&self, handicap: &mut Handicap) {
+        match self {
+            HandicapSetting::PaddleSize => {
+                handicap.paddle_size = next_value(&PADDLE_SIZES, handicap.paddle_size)
+            }
+            HandicapSetting::PaddleSpeed => {
+                handicap.paddle_speed = next_value(&PADDLE_SPEEDS, handicap.paddle_speed)
+            }
+            HandicapSetting::HeadStart => {
+                handicap.head_start = next_value(&HEAD_STARTS, handicap.head_start)
+            }
+            HandicapSetting::AimAssist => handicap.aim_assist = !handicap.aim_assist,
+        }
+    }
+}
+
+#[derive(Component)]
+struct SetupMenu;
+
+fn setup_match_setup(mut commands: Commands, rules: Res<MatchRules>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            SetupMenu,
+        ))
+        .with_children(|children| {
+            children.spawn((
+                Text::new(format!("Match setup: {}", rules.mode.name())),
+                TextFont {
+                    font_size: 60.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                ThemeRole::UiText,
+                Node {
+                    margin: UiRect::bottom(Val::Px(40.0)),
+                    ..default()
+                },
+            ));
+
+            children
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(40.0),
+                    margin: UiRect::bottom(Val::Px(20.0)),
+                    ..default()
+                })
+                .with_children(|columns| {
+                    for side in rules.mode.sides() {
+                        let handicap = &rules.handicaps[side.index()];
+                        columns
+                            .spawn(Node {
+                                flex_direction: FlexDirection::Column,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            })
+                            .with_children(|column| {
+                                column.spawn((
+                                    Text::new(side_title(&rules, *side)),
+                                    TextFont {
+                                        font_size: 30.0,
+                                        ..default()
+                                    },
+                                    TextColor(Color::WHITE),
+                                    ThemeRole::UiText,
+                                    Node {
+                                        margin: UiRect::bottom(Val::Px(20.0)),
+                                        ..default()
+                                    },
+                                ));
+                                for setting in HandicapSetting::ALL {
+                                    spawn_button(
+                                        column,
+                                        setting.label(handicap),
+                                        25.0,
+                                        HandicapButton {
+                                            side: *side,
+                                            setting,
+                                        },
+                                    );
+                                }
+                            });
+                    }
+                });
+
+            spawn_button(children, "Start", 40.0, ChangeState(GameState::Playing));
+            spawn_button(children, "Back", 30.0, ChangeState(GameState::Menu));
+        });
+}
+
+fn side_title(rules: &MatchRules, side: PaddleSide) -> String {
+    let controller = rules.controllers[side.index()];
+    format!("{} ({})", side.name(), controller.label(side))
+}
+
+fn handle_handicap_click(
+    mut rules: ResMut<MatchRules>,
+    interaction_query: Query<(&Interaction, &HandicapButton, &Children), Changed<Interaction>>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, button, children) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let handicap = &mut rules.handicaps[button.side.index()];
+        button.setting.cycle(handicap);
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.0 = button.setting.label(handicap);
+        }
+    }
+}
+
+fn handle_setup_escape_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Menu);
+    }
+}
+
+fn cleanup_match_setup(mut commands: Commands, menu: Query<Entity, With<SetupMenu>>) {
+    for entity in menu.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paddle_size_only_stretches_the_length() {
+        let handicap = Handicap {
+            paddle_size: 150,
+            ..default()
+        };
+        let left = PaddleSide::Left.paddle_size();
+        assert_eq!(
+            handicap.paddle_size(PaddleSide::Left),
+            Vec2::new(left.x, left.y * 1.5)
+        );
+        let top = PaddleSide::Top.paddle_size();
+        assert_eq!(
+            handicap.paddle_size(PaddleSide::Top),
+            Vec2::new(top.x * 1.5, top.y)
+        );
+        assert_eq!(Handicap::default().paddle_size(PaddleSide::Left), left);
+    }
+
+    #[test]
+    fn aim_assist_reaches_past_both_ends() {
+        let collider = Collider(PaddleSide::Left.paddle_size());
+        assert_eq!(
+            Handicap::default().hitbox(PaddleSide::Left, &collider).0,
+            collider.0
+        );
+        let handicap = Handicap {
+            aim_assist: true,
+            ..default()
+        };
+        assert_eq!(
+            handicap.hitbox(PaddleSide::Left, &collider).0,
+            collider.0 + Vec2::new(0., AIM_ASSIST_REACH * 2.)
+        );
+        assert_eq!(
+            handicap.hitbox(PaddleSide::Bottom, &collider).0,
+            collider.0 + Vec2::new(AIM_ASSIST_REACH * 2., 0.)
+        );
+    }
+
+    #[test]
+    fn paddle_speed_scales_the_normal_speed() {
+        let handicap = Handicap {
+            paddle_speed: 80,
+            ..default()
+        };
+        assert_eq!(handicap.paddle_speed(), PADDLE_SPEED * 0.8);
+        assert_eq!(Handicap::default().paddle_speed(), PADDLE_SPEED);
+    }
+}
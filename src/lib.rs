@@ -1,50 +1,309 @@
+//! Pong, as a set of Bevy plugins.
+//!
+//! [`PongPlugin`] adds the whole game to an app. By default it is a complete game with its own loading screen,
+//! menu, camera, audio and input, which is what the binaries use. To embed it as a minigame in a larger app,
+//! parts of it can be left out and replaced by the host, and it can be tied to a state of the host app:
+//!
+//! ```no_run
+//! # use bevy::prelude::*;
+//! # use bevy_pong::PongPlugin;
+//! #[derive(States, Default, Clone, Eq, PartialEq, Debug, Hash)]
+//! enum AppState {
+//!     #[default]
+//!     Hub,
+//!     Minigame,
+//! }
+//!
+//! App::new()
+//!     .add_plugins(DefaultPlugins)
+//!     .init_state::<AppState>()
+//!     .add_plugins(
+//!         PongPlugin::default()
+//!             .without_camera()
+//!             .run_in_state(AppState::Minigame, AppState::Hub),
+//!     )
+//!     .run();
+//! ```
+//!
+//! The screens of the game are the variants of [`GameState`], and a match is played in [`GameState::Playing`].
+//! What happens during a match is sent as [`events`], and the components and resources it runs on
+//! are in [`game`], with the rules of the next match in [`rules`].
+
 #![allow(clippy::type_complexity)]
 
-mod actions;
+mod achievements;
+pub mod actions;
+mod arena;
 mod audio;
-mod camera;
-mod game;
-mod loading;
+mod breakout;
+pub mod camera;
+mod career;
+mod crt;
+mod data;
+pub mod demo;
+mod effects;
+pub mod env;
+pub mod eval;
+pub mod events;
+pub mod game;
+pub mod handicap;
+mod lifecycle;
+pub mod loading;
 mod menu;
+mod party;
+pub mod policy;
+mod powerups;
+mod practice;
+pub mod rules;
+pub mod settings;
+mod storage;
+mod survival;
+mod theme;
+mod time_attack;
+mod tournament;
+mod ui;
 
-use crate::actions::ActionsPlugin;
+use crate::achievements::AchievementsPlugin;
+use crate::actions::{Actions, ActionsPlugin};
+use crate::arena::ArenaPlugin;
 use crate::audio::InternalAudioPlugin;
+use crate::breakout::BreakoutPlugin;
 use crate::camera::CameraPlugin;
+use crate::career::CareerPlugin;
+use crate::crt::CrtPlugin;
+use crate::demo::{DemoPlugin, Exhibition};
+use crate::effects::EffectsPlugin;
 use crate::game::PongGamePlugin;
+use crate::handicap::HandicapPlugin;
+use crate::lifecycle::LifecyclePlugin;
 use crate::loading::LoadingPlugin;
 use crate::menu::MenuPlugin;
+use crate::party::PartyPlugin;
+use crate::policy::PolicyPlugin;
+use crate::powerups::PowerUpsPlugin;
+use crate::practice::PracticePlugin;
+use crate::rules::RulesPlugin;
+use crate::settings::SettingsPlugin;
+use crate::survival::SurvivalPlugin;
+use crate::theme::ThemePlugin;
+use crate::time_attack::TimeAttackPlugin;
+use crate::tournament::TournamentPlugin;
+use crate::ui::UiPlugin;
 
 use bevy::app::App;
 #[cfg(debug_assertions)]
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::prelude::*;
+use bevy::state::state::FreelyMutableState;
 
 // This example game uses States to separate logic
 // See https://bevy-cheatbook.github.io/programming/states.html
 // Or https://github.com/bevyengine/bevy/blob/main/examples/ecs/state.rs
+/// The screen the game is on
 #[derive(States, Default, Clone, Eq, PartialEq, Debug, Hash)]
-enum GameState {
-    // During the loading State the LoadingPlugin will load our assets
+pub enum GameState {
+    /// During the loading State the LoadingPlugin will load our assets
     #[default]
     Loading,
-    // During this State the actual game logic is executed
+    /// During this State the actual game logic is executed
     Playing,
-    // Here the menu is drawn and waiting for player interaction
+    /// Here the menu is drawn and waiting for player interaction
     Menu,
+    /// Here the settings screen is drawn and the player can change the Settings resource
+    Settings,
+    /// Here the match rules screen is drawn and the player can change the MatchRules resource
+    Rules,
+    /// Here the pre-match setup screen is drawn and the player can change the handicaps of each side
+    Setup,
+    /// Here the tournament screen is drawn, between the matches of a tournament
+    Tournament,
+    /// Here the career ladder is drawn, between the matches of the career
+    Career,
+    /// Here the achievements are listed, with the ones unlocked so far
+    Achievements,
+    /// Nothing of the game is shown, while the host app is in a state of its own.
+    /// Only used when the game runs in a state of the host, see [`PongPlugin::run_in_state`].
+    Inactive,
+}
+
+/// The steps every frame of the game goes through in `Update`, in this order.
+/// Host apps can order their own systems around them, for example to steer a paddle after the input is read.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameSet {
+    /// Reading the player's input, moving the player's paddles and handling the buttons of the screens
+    Input,
+    /// Moving the computer's paddles
+    Ai,
+    /// Moving the balls, and everything they can run into
+    Physics,
+    /// Goals, points and the rules of the mode being played, up to the end of the round or match
+    Scoring,
+    /// What the player sees and hears of all that: the text on screen, effects, audio and theme
+    Presentation,
+}
+
+/// Send this to leave the game. The menu sends it when the player quits.
+/// A standalone game closes the app, an embedded one returns to the exit state of the host app.
+#[derive(Event)]
+pub struct ExitGame;
+
+/// Adds the game to an app, see the [crate docs](crate) for how to embed it
+pub struct PongPlugin {
+    loading: bool,
+    menu: bool,
+    camera: bool,
+    audio: bool,
+    input: bool,
+    start: GameState,
+    host: Option<Box<dyn Fn(&mut App) + Send + Sync>>,
+    exhibition: Option<Exhibition>,
 }
 
-pub struct GamePlugin;
+impl Default for PongPlugin {
+    fn default() -> Self {
+        PongPlugin {
+            loading: true,
+            menu: true,
+            camera: true,
+            audio: true,
+            input: true,
+            start: GameState::Menu,
+            host: None,
+            exhibition: None,
+        }
+    }
+}
+
+impl PongPlugin {
+    /// Leaves out the loading screen. The host app has to insert the asset collections of [`loading`]
+    /// before the game starts, for example with [`loading::load_collections`] in its own loading state.
+    pub fn without_loading(mut self) -> Self {
+        self.loading = false;
+        self
+    }
+
+    /// Leaves out the menu. [`GameState::Menu`] stays empty for the host app to draw a menu of its own,
+    /// which the other screens of the game go back to.
+    pub fn without_menu(mut self) -> Self {
+        self.menu = false;
+        self
+    }
+
+    /// Leaves out the camera. The host app has to spawn a 2d camera with the [`camera::MainCamera`] component.
+    pub fn without_camera(mut self) -> Self {
+        self.camera = false;
+        self
+    }
 
-impl Plugin for GamePlugin {
+    /// Leaves out the sounds and the audio plugin playing them
+    pub fn without_audio(mut self) -> Self {
+        self.audio = false;
+        self
+    }
+
+    /// Leaves out reading the keyboard, mouse and touch screen for the player's paddle.
+    /// The host app moves it by setting the [`actions::Actions`] resource instead.
+    pub fn without_input(mut self) -> Self {
+        self.input = false;
+        self
+    }
+
+    /// The screen the game starts on once its assets are loaded, the menu by default
+    pub fn start_in(mut self, state: GameState) -> Self {
+        self.start = state;
+        self
+    }
+
+    /// Plays the matches of an [`Exhibition`] between computer opponents instead of showing the menu,
+    /// printing how they went, and closes the game once they are over
+    pub fn exhibition(mut self, exhibition: Exhibition) -> Self {
+        self.exhibition = Some(exhibition);
+        self
+    }
+
+    /// Runs the game only while the host app is in the `active` state, starting it every time that state is
+    /// entered and leaving it [`GameState::Inactive`] otherwise. Sending [`ExitGame`] takes the host app
+    /// to the `exit` state instead of closing it.
+    pub fn run_in_state<S: FreelyMutableState>(mut self, active: S, exit: S) -> Self {
+        self.host = Some(Box::new(move |app: &mut App| {
+            app.insert_resource(HostStates { exit: exit.clone() })
+                .add_systems(OnEnter(active.clone()), start_game)
+                .add_systems(OnExit(active.clone()), stop_game)
+                .add_systems(Update, exit_to_host::<S>.run_if(on_event::<ExitGame>));
+        }));
+        self
+    }
+}
+
+impl Plugin for PongPlugin {
     fn build(&self, app: &mut App) {
-        app.init_state::<GameState>().add_plugins((
-            LoadingPlugin,
-            CameraPlugin,
-            MenuPlugin,
-            ActionsPlugin,
-            InternalAudioPlugin,
-            PongGamePlugin,
-        ));
+        // Without the loading screen, the assets are already there when the game starts
+        let first_state = if self.loading {
+            GameState::Loading
+        } else {
+            self.start.clone()
+        };
+        match &self.host {
+            Some(add_host_systems) => {
+                app.insert_state(GameState::Inactive)
+                    .insert_resource(FirstState(first_state));
+                add_host_systems(app);
+            }
+            None => {
+                app.insert_state(first_state)
+                    .add_systems(Update, exit_app.run_if(on_event::<ExitGame>));
+            }
+        }
+        app.add_event::<ExitGame>()
+            // Set by the input plugin, or by the host app without it
+            .init_resource::<Actions>()
+            .add_plugins((
+                SettingsPlugin,
+                RulesPlugin,
+                HandicapPlugin,
+                ThemePlugin,
+                UiPlugin,
+                // Plugins tuples are limited in size, so the ones running a match are grouped
+                (
+                    PongGamePlugin,
+                    ArenaPlugin,
+                    PowerUpsPlugin,
+                    PartyPlugin,
+                    PolicyPlugin,
+                    PracticePlugin,
+                    BreakoutPlugin,
+                    TimeAttackPlugin,
+                    SurvivalPlugin,
+                    TournamentPlugin,
+                    CareerPlugin,
+                    AchievementsPlugin,
+                ),
+                EffectsPlugin,
+                CrtPlugin,
+                LifecyclePlugin,
+                DemoPlugin { attract: self.menu },
+            ));
+        if let Some(exhibition) = &self.exhibition {
+            app.insert_resource(exhibition.clone());
+        }
+        if self.loading {
+            app.add_plugins(LoadingPlugin {
+                continue_to: self.start.clone(),
+                audio: self.audio,
+            });
+        }
+        if self.menu {
+            app.add_plugins(MenuPlugin);
+        }
+        if self.camera {
+            app.add_plugins(CameraPlugin);
+        }
+        if self.audio {
+            app.add_plugins(InternalAudioPlugin);
+        }
+        if self.input {
+            app.add_plugins(ActionsPlugin);
+        }
 
         #[cfg(debug_assertions)]
         {
@@ -52,3 +311,125 @@ impl Plugin for GamePlugin {
         }
     }
 }
+
+// The state the game goes to when the host app enters its active state
+#[derive(Resource)]
+struct FirstState(GameState);
+
+// The state of the host app to go to when the game is left
+#[derive(Resource)]
+struct HostStates<S: FreelyMutableState> {
+    exit: S,
+}
+
+fn start_game(first_state: Res<FirstState>, mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(first_state.0.clone());
+}
+
+fn stop_game(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Inactive);
+}
+
+fn exit_to_host<S: FreelyMutableState>(
+    host_states: Res<HostStates<S>>,
+    mut next_state: ResMut<NextState<S>>,
+) {
+    next_state.set(host_states.exit.clone());
+}
+
+fn exit_app(mut app_exit_events: EventWriter<AppExit>) {
+    app_exit_events.send(AppExit::Success);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::asset::AssetPlugin;
+    use bevy::input::InputPlugin;
+    use bevy::state::app::StatesPlugin;
+    use bevy::window::WindowPlugin;
+
+    #[derive(States, Default, Clone, Eq, PartialEq, Debug, Hash)]
+    enum HostState {
+        #[default]
+        Hub,
+        Minigame,
+    }
+
+    fn game_state(app: &App) -> GameState {
+        app.world().resource::<State<GameState>>().get().clone()
+    }
+
+    #[test]
+    fn game_runs_only_in_the_state_of_the_host() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            StatesPlugin,
+            AssetPlugin::default(),
+            InputPlugin,
+            WindowPlugin {
+                primary_window: None,
+                ..default()
+            },
+        ))
+        .init_state::<HostState>()
+        .add_plugins(
+            PongPlugin::default()
+                .without_loading()
+                .without_menu()
+                .without_camera()
+                .without_audio()
+                .without_input()
+                .run_in_state(HostState::Minigame, HostState::Hub),
+        );
+        app.update();
+        assert_eq!(game_state(&app), GameState::Inactive);
+
+        app.world_mut()
+            .resource_mut::<NextState<HostState>>()
+            .set(HostState::Minigame);
+        app.update();
+        app.update();
+        assert_eq!(game_state(&app), GameState::Menu);
+
+        app.world_mut().send_event(ExitGame);
+        app.update();
+        app.update();
+        assert_eq!(
+            *app.world().resource::<State<HostState>>().get(),
+            HostState::Hub
+        );
+        // Leaving the state of the host stops the game on the frame after
+        app.update();
+        assert_eq!(game_state(&app), GameState::Inactive);
+    }
+
+    #[derive(Resource, Default)]
+    struct Steps(Vec<GameSet>);
+
+    #[test]
+    fn game_sets_run_in_order() {
+        let mut app = crate::env::headless_app(default(), default(), 0);
+        app.init_resource::<Steps>();
+        let sets = [
+            GameSet::Presentation,
+            GameSet::Scoring,
+            GameSet::Physics,
+            GameSet::Ai,
+            GameSet::Input,
+        ];
+        // Added in reverse, so only the sets put them in order
+        for set in sets.clone() {
+            let step = set.clone();
+            app.add_systems(
+                Update,
+                (move |mut steps: ResMut<Steps>| steps.0.push(step.clone())).in_set(set),
+            );
+        }
+        app.update();
+        let mut expected = sets.to_vec();
+        expected.reverse();
+        assert_eq!(app.world().resource::<Steps>().0, expected);
+    }
+}
@@ -0,0 +1,222 @@
+use crate::demo::Exhibition;
+use crate::game::PlayingState;
+use crate::{GameSet, GameState};
+use bevy::core::FrameCount;
+use bevy::prelude::*;
+use bevy::window::{AppLifecycle, WindowFocused, WindowOccluded};
+use std::time::Duration;
+
+pub struct LifecyclePlugin;
+
+/// This plugin notices when the game goes into the background (app suspended on mobile, tab hidden on web or
+/// window unfocused on desktop) and sends [`LifecycleEvent`]s so other plugins can react.
+/// The game itself is paused, and virtual time stands still until the player comes back.
+/// An [`Exhibition`] has nobody to come back, so its matches play on in the background.
+impl Plugin for LifecyclePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AppActivity>()
+            .add_event::<LifecycleEvent>()
+            .add_systems(PreUpdate, detect_lifecycle_changes)
+            .add_systems(
+                Update,
+                (
+                    (
+                        pause_game_on_suspend.run_if(in_state(GameState::Playing)),
+                        pause_virtual_time,
+                    )
+                        .run_if(not(resource_exists::<Exhibition>)),
+                    restore_max_delta.run_if(resource_exists::<ClampedDelta>),
+                )
+                    .in_set(GameSet::Input),
+            );
+    }
+}
+
+#[derive(Event, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LifecycleEvent {
+    Suspended,
+    Resumed,
+}
+
+// The game counts as suspended as soon as any one of these is false
+#[derive(Resource)]
+struct AppActivity {
+    focused: bool,
+    visible: bool,
+    running: bool,
+}
+
+impl Default for AppActivity {
+    fn default() -> Self {
+        AppActivity {
+            focused: true,
+            visible: true,
+            running: true,
+        }
+    }
+}
+
+impl AppActivity {
+    fn is_suspended(&self) -> bool {
+        !(self.focused && self.visible && self.running)
+    }
+}
+
+// The longest frame the simulation may see right after resuming.
+// Anything longer could let the ball tunnel straight through a paddle.
+const MAX_RESUME_DELTA: Duration = Duration::from_millis(1000 / 30);
+
+// Holds the usual max delta of virtual time while the first frame after resuming is clamped
+#[derive(Resource)]
+struct ClampedDelta {
+    previous_max_delta: Duration,
+    since_frame: u32,
+}
+
+fn detect_lifecycle_changes(
+    mut activity: ResMut<AppActivity>,
+    mut focus_events: EventReader<WindowFocused>,
+    mut occluded_events: EventReader<WindowOccluded>,
+    mut app_lifecycle_events: EventReader<AppLifecycle>,
+    mut lifecycle_events: EventWriter<LifecycleEvent>,
+) {
+    let was_suspended = activity.is_suspended();
+    for event in focus_events.read() {
+        activity.focused = event.focused;
+    }
+    for event in occluded_events.read() {
+        activity.visible = !event.occluded;
+    }
+    for event in app_lifecycle_events.read() {
+        match event {
+            AppLifecycle::WillSuspend | AppLifecycle::Suspended => activity.running = false,
+            AppLifecycle::WillResume | AppLifecycle::Running => activity.running = true,
+            AppLifecycle::Idle => {}
+        }
+    }
+
+    match (was_suspended, activity.is_suspended()) {
+        (false, true) => {
+            lifecycle_events.send(LifecycleEvent::Suspended);
+        }
+        (true, false) => {
+            lifecycle_events.send(LifecycleEvent::Resumed);
+        }
+        _ => {}
+    }
+}
+
+fn pause_game_on_suspend(
+    mut lifecycle_events: EventReader<LifecycleEvent>,
+    current_state: Res<State<PlayingState>>,
+    mut next_state: ResMut<NextState<PlayingState>>,
+) {
+    // Resuming is left to the player, so they are not thrown straight back into a rally.
+    // A match that is already over stays over.
+    if lifecycle_events
+        .read()
+        .any(|event| *event == LifecycleEvent::Suspended)
+        && *current_state.get() != PlayingState::Over
+    {
+        next_state.set(PlayingState::Paused);
+    }
+}
+
+fn pause_virtual_time(
+    mut commands: Commands,
+    mut lifecycle_events: EventReader<LifecycleEvent>,
+    mut time: ResMut<Time<Virtual>>,
+    frame_count: Res<FrameCount>,
+    clamped_delta: Option<Res<ClampedDelta>>,
+) {
+    for event in lifecycle_events.read() {
+        match event {
+            LifecycleEvent::Suspended => time.pause(),
+            LifecycleEvent::Resumed => {
+                time.unpause();
+                // The first frame after resuming can span the whole time spent in the background
+                let previous_max_delta = clamped_delta
+                    .as_ref()
+                    .map_or(time.max_delta(), |clamped| clamped.previous_max_delta);
+                commands.insert_resource(ClampedDelta {
+                    previous_max_delta,
+                    since_frame: frame_count.0,
+                });
+                time.set_max_delta(MAX_RESUME_DELTA);
+            }
+        }
+    }
+}
+
+fn restore_max_delta(
+    mut commands: Commands,
+    clamped_delta: Res<ClampedDelta>,
+    mut time: ResMut<Time<Virtual>>,
+    frame_count: Res<FrameCount>,
+) {
+    if frame_count.0 > clamped_delta.since_frame {
+        time.set_max_delta(clamped_delta.previous_max_delta);
+        commands.remove_resource::<ClampedDelta>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::{headless_app, play_headless_frame};
+
+    fn lifecycle_app() -> App {
+        let mut app = headless_app(default(), default(), 0);
+        app.add_event::<WindowFocused>()
+            .add_event::<WindowOccluded>()
+            .add_event::<AppLifecycle>();
+        // The headless app is already finished, so the plugin can't be added the usual way
+        LifecyclePlugin.build(&mut app);
+        play_headless_frame(&mut app);
+        app
+    }
+
+    fn focus(app: &mut App, focused: bool) {
+        app.world_mut().send_event(WindowFocused {
+            window: Entity::PLACEHOLDER,
+            focused,
+        });
+        app.update();
+    }
+
+    fn playing_state(app: &App) -> PlayingState {
+        app.world().resource::<State<PlayingState>>().get().clone()
+    }
+
+    #[test]
+    fn losing_focus_pauses_the_match_and_time() {
+        let mut app = lifecycle_app();
+        let max_delta = app.world().resource::<Time<Virtual>>().max_delta();
+        focus(&mut app, false);
+        app.update();
+        assert!(app.world().resource::<Time<Virtual>>().is_paused());
+        assert_eq!(playing_state(&app), PlayingState::Paused);
+
+        focus(&mut app, true);
+        let time = app.world().resource::<Time<Virtual>>();
+        assert!(!time.is_paused());
+        assert_eq!(time.max_delta(), MAX_RESUME_DELTA);
+        // Resuming the match is left to the player
+        assert_eq!(playing_state(&app), PlayingState::Paused);
+        app.update();
+        assert_eq!(
+            app.world().resource::<Time<Virtual>>().max_delta(),
+            max_delta
+        );
+    }
+
+    #[test]
+    fn exhibitions_play_on_without_focus() {
+        let mut app = lifecycle_app();
+        app.insert_resource(Exhibition::default());
+        focus(&mut app, false);
+        app.update();
+        assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+        assert_eq!(playing_state(&app), PlayingState::Playing);
+    }
+}
@@ -1,24 +1,56 @@
+use crate::achievements::Achievement;
+use crate::arena::Arena;
+use crate::breakout::Level;
+use crate::career::Personality;
+use crate::practice::Drill;
+use crate::theme::Theme;
 use crate::GameState;
 use bevy::prelude::*;
+use bevy::state::state::FreelyMutableState;
 use bevy_asset_loader::prelude::*;
 use bevy_kira_audio::AudioSource;
 
-pub struct LoadingPlugin;
+pub struct LoadingPlugin {
+    // State the game continues to once everything is loaded
+    pub continue_to: GameState,
+    // Whether to load the sounds, which need the audio plugin
+    pub audio: bool,
+}
 
 /// This plugin loads all assets using [`AssetLoader`] from a third party bevy plugin
 /// Alternatively you can write the logic to load assets yourself
 /// If interested, take a look at <https://bevy-cheatbook.github.io/features/assets.html>
 impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_loading_state(
-            LoadingState::new(GameState::Loading)
-                .continue_to_state(GameState::Menu)
-                .load_collection::<AudioAssets>()
-                .load_collection::<TextureAssets>(),
-        );
+        app.add_loading_state(load_collections(
+            LoadingState::new(GameState::Loading).continue_to_state(self.continue_to.clone()),
+            self.audio,
+        ));
     }
 }
 
+/// Adds all of the game's asset collections to a loading state.
+/// Host apps with a loading screen of their own can use this to load the game's assets in it,
+/// and leave out the sounds if the game's audio is turned off.
+pub fn load_collections<S: FreelyMutableState>(
+    loading_state: LoadingState<S>,
+    audio: bool,
+) -> LoadingState<S> {
+    let loading_state = if audio {
+        loading_state.load_collection::<AudioAssets>()
+    } else {
+        loading_state
+    };
+    loading_state
+        .load_collection::<TextureAssets>()
+        .load_collection::<ThemeAssets>()
+        .load_collection::<ArenaAssets>()
+        .load_collection::<DrillAssets>()
+        .load_collection::<LevelAssets>()
+        .load_collection::<OpponentAssets>()
+        .load_collection::<AchievementAssets>()
+}
+
 // the following asset collections will be loaded during the State `GameState::Loading`
 // when done loading, they will be inserted as resources (see <https://github.com/NiklasEi/bevy_asset_loader>)
 
@@ -35,3 +67,94 @@ pub struct TextureAssets {
     #[asset(path = "textures/github.png")]
     pub github: Handle<Image>,
 }
+
+#[derive(AssetCollection, Resource)]
+pub struct ThemeAssets {
+    #[asset(
+        paths(
+            "themes/classic.theme.ron",
+            "themes/neon.theme.ron",
+            "themes/high_contrast.theme.ron",
+            "themes/colorblind.theme.ron"
+        ),
+        collection(typed)
+    )]
+    pub themes: Vec<Handle<Theme>>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct ArenaAssets {
+    #[asset(
+        paths(
+            "arenas/classic.arena.ron",
+            "arenas/pillars.arena.ron",
+            "arenas/sliders.arena.ron",
+            "arenas/portals.arena.ron",
+            "arenas/fortress.arena.ron"
+        ),
+        collection(typed)
+    )]
+    pub arenas: Vec<Handle<Arena>>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct DrillAssets {
+    #[asset(
+        paths(
+            "drills/wall.drill.ron",
+            "drills/steady_feed.drill.ron",
+            "drills/corners.drill.ron",
+            "drills/spin.drill.ron"
+        ),
+        collection(typed)
+    )]
+    pub drills: Vec<Handle<Drill>>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct LevelAssets {
+    #[asset(
+        paths(
+            "levels/first_wall.level.ron",
+            "levels/checkers.level.ron",
+            "levels/fortress.level.ron"
+        ),
+        collection(typed)
+    )]
+    pub levels: Vec<Handle<Level>>,
+}
+
+// The career ladder, from the first opponent to the last
+#[derive(AssetCollection, Resource)]
+pub struct OpponentAssets {
+    #[asset(
+        paths(
+            "opponents/rookie.opponent.ron",
+            "opponents/net_hugger.opponent.ron",
+            "opponents/tracker.opponent.ron",
+            "opponents/erratic.opponent.ron",
+            "opponents/boss.opponent.ron"
+        ),
+        collection(typed)
+    )]
+    pub opponents: Vec<Handle<Personality>>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct AchievementAssets {
+    #[asset(
+        paths(
+            "achievements/first_win.achievement.ron",
+            "achievements/veteran.achievement.ron",
+            "achievements/sharpshooter.achievement.ron",
+            "achievements/flawless.achievement.ron",
+            "achievements/perfect_eleven.achievement.ron",
+            "achievements/long_rally.achievement.ron",
+            "achievements/marathon.achievement.ron",
+            "achievements/speed_demon.achievement.ron",
+            "achievements/top_of_the_ladder.achievement.ron"
+        ),
+        collection(typed)
+    )]
+    pub achievements: Vec<Handle<Achievement>>,
+}
@@ -6,13 +6,27 @@ use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 use bevy::winit::WinitWindows;
 use bevy::DefaultPlugins;
-use bevy_pong::GamePlugin;
+use bevy_pong::demo::{Exhibition, ExhibitionFinished, ExhibitionMatchPlayed};
+use bevy_pong::PongPlugin;
+use std::env;
 use std::io::Cursor;
 use winit::window::Icon;
 
+const USAGE: &str =
+    "Usage: bevy_pong [--ai-vs-ai LEFT RIGHT [--matches N] [--points N] [--time-scale X]]";
+
 fn main() {
+    let mut pong = PongPlugin::default();
+    match parse_exhibition(env::args().skip(1)) {
+        Ok(Some(exhibition)) => pong = pong.exhibition(exhibition),
+        Ok(None) => {}
+        Err(error) => {
+            eprintln!("{error}\n{USAGE}");
+            std::process::exit(2);
+        }
+    }
+
     App::new()
-        .insert_resource(ClearColor(Color::linear_rgb(0.05, 0.05, 0.05)))
         .add_plugins(
             DefaultPlugins
                 .set(WindowPlugin {
@@ -32,8 +46,9 @@ fn main() {
                     ..default()
                 }),
         )
-        .add_plugins(GamePlugin)
+        .add_plugins(pong)
         .add_systems(Startup, set_window_icon)
+        .add_systems(Last, print_exhibition_results)
         .run();
 }
 
@@ -57,3 +72,95 @@ fn set_window_icon(
         primary.set_window_icon(Some(icon));
     };
 }
+
+// Runs last, after the game has sent the results of the frame
+fn print_exhibition_results(
+    exhibition: Option<Res<Exhibition>>,
+    mut played_events: EventReader<ExhibitionMatchPlayed>,
+    mut finished_events: EventReader<ExhibitionFinished>,
+) {
+    let Some(exhibition) = exhibition else {
+        return;
+    };
+    for played in played_events.read() {
+        println!(
+            "Match {}: {} {} - {} {} in {:.0} seconds, longest rally {}",
+            played.number,
+            exhibition.left,
+            played.points[0],
+            played.points[1],
+            exhibition.right,
+            played.duration,
+            played.longest_rally
+        );
+    }
+    for finished in finished_events.read() {
+        println!(
+            "{} won {} and {} won {} of {} matches, {} - {} in points",
+            exhibition.left,
+            finished.wins[0],
+            exhibition.right,
+            finished.wins[1],
+            finished.played,
+            finished.points[0],
+            finished.points[1]
+        );
+    }
+}
+
+// `--ai-vs-ai LEFT RIGHT` plays the computer opponents named LEFT and RIGHT against each other instead of
+// showing the menu, printing the results
+fn parse_exhibition(mut args: impl Iterator<Item = String>) -> Result<Option<Exhibition>, String> {
+    let Some(flag) = args.next() else {
+        return Ok(None);
+    };
+    if flag != "--ai-vs-ai" {
+        return Err(format!("Unknown argument {flag}"));
+    }
+    let mut exhibition = Exhibition {
+        left: args.next().ok_or("Missing the left opponent")?,
+        right: args.next().ok_or("Missing the right opponent")?,
+        ..Exhibition::default()
+    };
+    while let Some(option) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing the value of {option}"))?;
+        let invalid = || format!("Invalid value {value} for {option}");
+        match option.as_str() {
+            "--matches" => exhibition.matches = value.parse().map_err(|_| invalid())?,
+            "--points" => exhibition.points_to_win = value.parse().map_err(|_| invalid())?,
+            "--time-scale" => exhibition.time_scale = value.parse().map_err(|_| invalid())?,
+            _ => return Err(format!("Unknown argument {option}")),
+        }
+    }
+    Ok(Some(exhibition))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Option<Exhibition>, String> {
+        parse_exhibition(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn exhibition_options_are_parsed() {
+        assert!(parse("").unwrap().is_none());
+        let exhibition = parse("--ai-vs-ai Ace Computer --matches 3 --points 7")
+            .unwrap()
+            .unwrap();
+        assert_eq!(exhibition.left, "Ace");
+        assert_eq!(exhibition.right, "Computer");
+        assert_eq!(exhibition.matches, 3);
+        assert_eq!(exhibition.points_to_win, 7);
+        assert_eq!(exhibition.time_scale, Exhibition::default().time_scale);
+
+        assert!(parse("--fullscreen").is_err());
+        assert!(parse("--ai-vs-ai Ace").is_err());
+        assert!(parse("--ai-vs-ai Ace Computer --matches").is_err());
+        assert!(parse("--ai-vs-ai Ace Computer --matches many").is_err());
+        assert!(parse("--ai-vs-ai Ace Computer --speed 2").is_err());
+    }
+}
@@ -1,7 +1,11 @@
-use crate::game::Score;
+use crate::arena::Arenas;
+use crate::demo::{attract_running, DemoMatch};
+use crate::game::{scorer_name, AiSkill, PaddleSide, Score};
 use crate::loading::TextureAssets;
-use crate::GameState;
-use bevy::app::AppExit;
+use crate::rules::{GameMode, MatchRules};
+use crate::theme::ThemeRole;
+use crate::ui::{next_value, spawn_button, ButtonColors, ChangeState};
+use crate::{ExitGame, GameSet, GameState};
 use bevy::prelude::*;
 
 pub struct MenuPlugin;
@@ -13,26 +17,23 @@ impl Plugin for MenuPlugin {
         app.add_systems(OnEnter(GameState::Menu), setup_menu)
             .add_systems(
                 Update,
-                (handle_button_click, handle_menu_escape_key)
+                (
+                    handle_button_click,
+                    handle_mode_click,
+                    handle_arena_click,
+                    handle_menu_escape_key,
+                )
                     .chain()
+                    .in_set(GameSet::Input)
                     .run_if(in_state(GameState::Menu)),
             )
-            .add_systems(OnExit(GameState::Menu), cleanup_menu);
-    }
-}
-
-#[derive(Component, Clone)]
-struct ButtonColors {
-    normal: Color,
-    hovered: Color,
-}
-
-impl Default for ButtonColors {
-    fn default() -> Self {
-        ButtonColors {
-            normal: Color::linear_rgb(0.15, 0.15, 0.15),
-            hovered: Color::linear_rgb(0.25, 0.25, 0.25),
-        }
+            .add_systems(OnExit(GameState::Menu), cleanup_menu)
+            // Attract mode matches play behind the menu, which only shows there
+            .add_systems(
+                OnEnter(GameState::Playing),
+                setup_menu.run_if(attract_running),
+            )
+            .add_systems(OnExit(GameState::Playing), cleanup_menu);
     }
 }
 
@@ -42,7 +43,23 @@ struct Menu;
 #[derive(Component)]
 struct ScoreText;
 
-fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, score: Res<Score>) {
+fn setup_menu(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    score: Res<Score>,
+    rules: Res<MatchRules>,
+    arenas: Arenas,
+    demo: Option<Res<DemoMatch>>,
+) {
+    // An attract mode match has a score of its own, while the menu keeps showing the player's.
+    // The match is dimmed so the menu stays readable over it.
+    let score = demo.as_ref().map_or(&*score, |demo| &demo.saved_score);
+    let rules = demo.as_ref().map_or(&*rules, |demo| &demo.saved_rules);
+    let background = if demo.is_some() {
+        Color::srgba(0., 0., 0., 0.6)
+    } else {
+        Color::NONE
+    };
     commands
         .spawn((
             Node {
@@ -53,6 +70,7 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, score: Res<S
                 justify_content: JustifyContent::Center,
                 ..default()
             },
+            BackgroundColor(background),
             Menu,
         ))
         .with_children(|children| {
@@ -63,6 +81,7 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, score: Res<S
                     ..default()
                 },
                 TextColor(Color::WHITE),
+                ThemeRole::UiText,
                 Node {
                     margin: UiRect::bottom(Val::Px(50.0)),
                     ..default()
@@ -82,21 +101,23 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, score: Res<S
                 },))
                 .with_children(|score_children| {
                     score_children.spawn((
-                        Text::new(format!("Player - {}", score.player)),
+                        Text::new(score_label(rules, score, PaddleSide::Left)),
                         TextFont {
                             font_size: 30.0,
                             ..default()
                         },
                         TextColor(Color::WHITE),
+                        ThemeRole::UiText,
                         ScoreText,
                     ));
                     score_children.spawn((
-                        Text::new(format!("Computer - {}", score.computer)),
+                        Text::new(score_label(rules, score, PaddleSide::Right)),
                         TextFont {
                             font_size: 30.0,
                             ..default()
                         },
                         TextColor(Color::WHITE),
+                        ThemeRole::UiText,
                         ScoreText,
                     ));
                 });
@@ -115,7 +136,7 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, score: Res<S
                     },
                     BackgroundColor(button_colors.normal),
                     ButtonColors::default(),
-                    ChangeState(GameState::Playing),
+                    ChangeState(GameState::Setup),
                 ))
                 .with_child((
                     Text::new("Play"),
@@ -126,8 +147,35 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, score: Res<S
                     TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
                 ));
 
+            spawn_button(children, mode_label(rules), 30.0, ModeButton);
+            spawn_button(children, arena_label(rules, &arenas), 30.0, ArenaButton);
+            // The other screens sit side by side, so the menu still fits on small windows
+            for row in [
+                &[
+                    ("Career", GameState::Career),
+                    ("Tournament", GameState::Tournament),
+                    ("Achievements", GameState::Achievements),
+                ][..],
+                &[
+                    ("Match rules", GameState::Rules),
+                    ("Settings", GameState::Settings),
+                ],
+            ] {
+                children
+                    .spawn(Node {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(20.0),
+                        ..default()
+                    })
+                    .with_children(|buttons| {
+                        for (label, state) in row {
+                            spawn_button(buttons, *label, 30.0, ChangeState(state.clone()));
+                        }
+                    });
+            }
+
             // Reset Score button (only shown if there are points to reset)
-            if score.player > 0 || score.computer > 0 {
+            if !score.is_empty() {
                 children
                     .spawn((
                         Button,
@@ -267,9 +315,6 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>, score: Res<S
         });
 }
 
-#[derive(Component)]
-struct ChangeState(GameState);
-
 #[derive(Component)]
 struct OpenLink(&'static str);
 
@@ -279,26 +324,47 @@ struct QuitGame;
 #[derive(Component)]
 struct ResetScore;
 
+// Cycles through the game modes, from pong to breakout
+#[derive(Component)]
+struct ModeButton;
+
+fn mode_label(rules: &MatchRules) -> String {
+    format!("Mode: {}", rules.mode.name())
+}
+
+// Cycles through the arenas for the next match
+#[derive(Component)]
+struct ArenaButton;
+
+// The rules can name an arena that is not there, in which case the first one is played
+fn active_arena_name(rules: &MatchRules, arenas: &Arenas) -> String {
+    arenas
+        .active(rules)
+        .map_or_else(|| rules.arena.clone(), |arena| arena.name.clone())
+}
+
+fn arena_label(rules: &MatchRules, arenas: &Arenas) -> String {
+    format!("Arena: {}", active_arena_name(rules, arenas))
+}
+
 fn handle_menu_escape_key(
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut app_exit_events: EventWriter<AppExit>,
+    mut exit_events: EventWriter<ExitGame>,
 ) {
     if keyboard_input.just_pressed(KeyCode::Escape) {
-        app_exit_events.send(AppExit::Success);
+        exit_events.send(ExitGame);
     }
 }
 
 fn handle_button_click(
-    mut next_state: ResMut<NextState<GameState>>,
-    mut app_exit_events: EventWriter<AppExit>,
+    mut exit_events: EventWriter<ExitGame>,
+    rules: Res<MatchRules>,
     mut score: ResMut<Score>,
+    mut demo: Option<ResMut<DemoMatch>>,
     mut score_text_query: Query<&mut Text, With<ScoreText>>,
-    mut interaction_query: Query<
+    interaction_query: Query<
         (
             &Interaction,
-            &mut BackgroundColor,
-            &ButtonColors,
-            Option<&ChangeState>,
             Option<&OpenLink>,
             Option<&QuitGame>,
             Option<&ResetScore>,
@@ -306,42 +372,82 @@ fn handle_button_click(
         (Changed<Interaction>, With<Button>),
     >,
 ) {
-    for (interaction, mut color, button_colors, change_state, open_link, quit_game, reset_score) in
-        &mut interaction_query
-    {
-        match *interaction {
-            Interaction::Pressed => {
-                if let Some(state) = change_state {
-                    next_state.set(state.0.clone());
-                } else if let Some(link) = open_link {
-                    if let Err(error) = webbrowser::open(link.0) {
-                        warn!("Failed to open link {error:?}");
-                    }
-                } else if quit_game.is_some() {
-                    app_exit_events.send(AppExit::Success);
-                } else if reset_score.is_some() {
-                    score.player = 0;
-                    score.computer = 0;
-                    // Update score text directly
-                    for (i, mut text) in score_text_query.iter_mut().enumerate() {
-                        if i == 0 {
-                            text.0 = format!("Player - {}", score.player);
-                        } else {
-                            text.0 = format!("Computer - {}", score.computer);
-                        }
-                    }
-                }
-            }
-            Interaction::Hovered => {
-                *color = button_colors.hovered.into();
+    for (interaction, open_link, quit_game, reset_score) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(link) = open_link {
+            if let Err(error) = webbrowser::open(link.0) {
+                warn!("Failed to open link {error:?}");
             }
-            Interaction::None => {
-                *color = button_colors.normal.into();
+        } else if quit_game.is_some() {
+            exit_events.send(ExitGame);
+        } else if reset_score.is_some() {
+            // Behind an attract mode match, the menu shows the score it gives back when it ends
+            let (rules, score) = match demo.as_deref_mut() {
+                Some(demo) => (&demo.saved_rules, &mut demo.saved_score),
+                None => (&*rules, &mut *score),
+            };
+            *score = Score::default();
+            // Update score text directly
+            for (i, mut text) in score_text_query.iter_mut().enumerate() {
+                let side = if i == 0 {
+                    PaddleSide::Left
+                } else {
+                    PaddleSide::Right
+                };
+                text.0 = score_label(rules, score, side);
             }
         }
     }
 }
 
+// Computer opponents are only picked for a match, so outside of one the score names the default computer
+fn score_label(rules: &MatchRules, score: &Score, side: PaddleSide) -> String {
+    format!(
+        "{} - {}",
+        scorer_name(rules, &AiSkill::default(), side),
+        score.get(side)
+    )
+}
+
+fn handle_mode_click(
+    mut rules: ResMut<MatchRules>,
+    interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<ModeButton>)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, children) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        rules.mode = next_value(&GameMode::ALL, rules.mode);
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.0 = mode_label(&rules);
+        }
+    }
+}
+
+fn handle_arena_click(
+    mut rules: ResMut<MatchRules>,
+    arenas: Arenas,
+    interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<ArenaButton>)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, children) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(arena) = arenas.next_name(&active_arena_name(&rules, &arenas)) {
+            rules.arena = arena;
+        }
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.0 = arena_label(&rules, &arenas);
+        }
+    }
+}
+
 fn cleanup_menu(mut commands: Commands, menu: Query<Entity, With<Menu>>) {
     for entity in menu.iter() {
         commands.entity(entity).despawn_recursive();
@@ -0,0 +1,231 @@
+use crate::arena::Court;
+use crate::events::{Goal, MatchEnded};
+use crate::game::{handle_round_end, Paddle, PaddleSide, PlayingState};
+use crate::rules::{GameMode, MatchRules};
+use crate::theme::ThemeRole;
+use crate::ui::spawn_end_screen;
+use crate::{GameSet, GameState};
+use bevy::prelude::*;
+
+pub struct PartyPlugin;
+
+/// This plugin runs the four-player party mode: every side starts with the lives set in the [`MatchRules`],
+/// loses one for each goal it concedes and is out of the match once it has none left.
+/// Its goal is walled off, and the last side standing wins.
+impl Plugin for PartyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Lives>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (reset_lives, spawn_lives_text)
+                    .chain()
+                    .run_if(is_party_match),
+            )
+            .add_systems(
+                Update,
+                lose_lives
+                    .after(handle_round_end)
+                    .in_set(GameSet::Scoring)
+                    .run_if(is_party_match)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PlayingState::Playing)),
+            )
+            .add_systems(
+                Update,
+                update_lives_text
+                    .in_set(GameSet::Presentation)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(resource_changed::<Lives>),
+            )
+            .add_systems(OnExit(GameState::Playing), cleanup_party);
+    }
+}
+
+// Lives left for each side, indexed by `PaddleSide::index`
+#[derive(Resource, Default)]
+pub struct Lives {
+    remaining: [u32; 4],
+}
+
+impl Lives {
+    pub fn get(&self, side: PaddleSide) -> u32 {
+        self.remaining[side.index()]
+    }
+
+    fn sides_left(&self) -> Vec<PaddleSide> {
+        PaddleSide::ALL
+            .into_iter()
+            .filter(|side| self.get(*side) > 0)
+            .collect()
+    }
+}
+
+#[derive(Component)]
+struct LivesText;
+
+#[derive(Component)]
+struct PartyText;
+
+fn is_party_match(rules: Res<MatchRules>) -> bool {
+    rules.mode == GameMode::FourPlayer
+}
+
+fn reset_lives(mut lives: ResMut<Lives>, rules: Res<MatchRules>) {
+    *lives = Lives::default();
+    for side in rules.mode.sides() {
+        lives.remaining[side.index()] = rules.lives;
+    }
+}
+
+fn lives_label(lives: &Lives) -> String {
+    let sides: Vec<String> = PaddleSide::ALL
+        .iter()
+        .map(|side| match lives.get(*side) {
+            0 => format!("{} out", side.name()),
+            remaining => format!("{} {}", side.name(), remaining),
+        })
+        .collect();
+    format!("Lives: {}", sides.join("  "))
+}
+
+fn spawn_lives_text(mut commands: Commands, lives: Res<Lives>) {
+    commands.spawn((
+        Text::new(lives_label(&lives)),
+        TextFont {
+            font_size: 30.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        ThemeRole::UiText,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            left: Val::Px(20.0),
+            ..default()
+        },
+        LivesText,
+        PartyText,
+    ));
+}
+
+fn update_lives_text(lives: Res<Lives>, mut text_query: Query<&mut Text, With<LivesText>>) {
+    for mut text in &mut text_query {
+        text.0 = lives_label(&lives);
+    }
+}
+
+// Takes a life from every side that concedes, and ends the match once one side or none is left
+fn lose_lives(
+    mut commands: Commands,
+    mut goal_events: EventReader<Goal>,
+    mut lives: ResMut<Lives>,
+    mut court: ResMut<Court>,
+    paddle_query: Query<(Entity, &Paddle)>,
+    mut match_ended_events: EventWriter<MatchEnded>,
+    mut next_state: ResMut<NextState<PlayingState>>,
+) {
+    if goal_events.is_empty() {
+        return;
+    }
+    for goal in goal_events.read() {
+        let remaining = &mut lives.remaining[goal.conceded.index()];
+        if *remaining == 0 {
+            continue;
+        }
+        *remaining -= 1;
+        if *remaining > 0 {
+            continue;
+        }
+        for (entity, paddle) in &paddle_query {
+            if paddle.side == goal.conceded {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+        court.close_goal(&mut commands, goal.conceded);
+    }
+
+    let sides_left = lives.sides_left();
+    if sides_left.len() > 1 {
+        return;
+    }
+    next_state.set(PlayingState::Over);
+    // Lives are lost rather than points scored
+    match_ended_events.send(MatchEnded {
+        winner: sides_left.first().copied(),
+        points: [0; 4],
+    });
+    let result = match sides_left.first() {
+        Some(winner) => format!("{} wins!", winner.name()),
+        None => "Nobody wins!".to_string(),
+    };
+    spawn_end_screen(&mut commands, result, Vec::new(), PartyText);
+}
+
+fn cleanup_party(mut commands: Commands, text_query: Query<Entity, With<PartyText>>) {
+    for entity in text_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn concede(app: &mut App, sides: &[PaddleSide]) {
+        for side in sides {
+            app.world_mut().send_event(Goal {
+                ball: Entity::PLACEHOLDER,
+                conceded: *side,
+                scorer: None,
+            });
+        }
+        app.update();
+    }
+
+    #[test]
+    fn the_last_side_with_lives_wins() {
+        let mut app = App::new();
+        app.add_event::<Goal>()
+            .add_event::<MatchEnded>()
+            .init_resource::<NextState<PlayingState>>()
+            .insert_resource(Court {
+                goals: PaddleSide::ALL.to_vec(),
+                ..default()
+            })
+            .insert_resource(Lives {
+                remaining: [1, 2, 1, 1],
+            })
+            .add_systems(Update, lose_lives);
+        let left = app
+            .world_mut()
+            .spawn(Paddle {
+                side: PaddleSide::Left,
+                forward: false,
+            })
+            .id();
+
+        concede(&mut app, &[PaddleSide::Left, PaddleSide::Right]);
+        assert!(app.world().get_entity(left).is_err());
+        assert!(!app
+            .world()
+            .resource::<Court>()
+            .goals
+            .contains(&PaddleSide::Left));
+        assert_eq!(
+            lives_label(app.world().resource::<Lives>()),
+            "Lives: Left out  Right 1  Top 1  Bottom 1"
+        );
+        assert!(app.world().resource::<Events<MatchEnded>>().is_empty());
+
+        // A side that is already out can't lose more lives, and the others going out at once ends the match
+        concede(
+            &mut app,
+            &[PaddleSide::Left, PaddleSide::Top, PaddleSide::Bottom],
+        );
+        let events = app.world().resource::<Events<MatchEnded>>();
+        let mut cursor = events.get_cursor();
+        let ended: Vec<_> = cursor.read(events).collect();
+        assert_eq!(ended.len(), 1);
+        assert_eq!(ended[0].winner, Some(PaddleSide::Right));
+    }
+}
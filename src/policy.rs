@@ -0,0 +1,517 @@
+//! Computer opponents steered by policy files, such as agents trained in the [`env`](crate::env).
+//!
+//! Policies are read from `policies/*.policy.json`, relative to where the game was started, every time the
+//! menu is shown, so new ones can be dropped in while the game runs. The one picked in the menu plays in
+//! place of the built-in computer, looking at the court the way the agent in the environment does and moving
+//! no faster than the computer it replaces.
+//!
+//! A policy file holds a name, the [`ObservationField`]s it looks at and a model turning that observation
+//! into a movement from -1 (full speed down, or left for the top and bottom paddles) to 1:
+//!
+//! ```json
+//! {
+//!     "name": "Follower",
+//!     "observation": ["ball_position", "paddle_position"],
+//!     "model": {
+//!         "mlp": {
+//!             "layers": [
+//!                 { "weights": [[0, 4, -4]], "biases": [0], "activation": "tanh" }
+//!             ]
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! An `mlp` is a list of fully connected layers, each with a row of weights per output, one weight per
+//! input, a bias per output and an activation of `linear` (the default), `relu` or `tanh`. The last layer has
+//! a single output, the movement.
+//!
+//! A `table` is a list of rules, of which the first one whose conditions all hold gives the movement, or the
+//! default if none does. A condition takes the sum of the observation values times its weights and checks it
+//! is `above` and/or `below` the given values:
+//!
+//! ```json
+//! "model": {
+//!     "table": {
+//!         "rules": [
+//!             { "when": [{ "weights": [0, 1, -1], "above": 0.05 }], "action": 1 },
+//!             { "when": [{ "weights": [0, 1, -1], "below": -0.05 }], "action": -1 }
+//!         ],
+//!         "default": 0
+//!     }
+//! }
+//! ```
+
+use crate::actions::{Actions, Controller};
+use crate::env::{observation_for, Observation, ObservationField};
+use crate::game::{
+    most_threatening_ball, move_computer_paddle, AiSkill, Ball, Collider, Paddle, PaddleSide,
+    PlayingState, Velocity, PADDLE_SPEED,
+};
+use crate::powerups::ReversedControls;
+use crate::rules::MatchRules;
+use crate::{GameSet, GameState};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+pub struct PolicyPlugin;
+
+/// This plugin finds the policy files and lets the one picked in the [`MatchRules`] steer the computer paddles
+impl Plugin for PolicyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Policies>()
+            .add_systems(OnEnter(GameState::Menu), find_policies)
+            .add_systems(OnEnter(GameState::Playing), pick_policy)
+            .add_systems(
+                Update,
+                move_policy_paddles
+                    .in_set(GameSet::Ai)
+                    .run_if(resource_exists::<ActivePolicy>)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PlayingState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), drop_policy);
+    }
+}
+
+const POLICY_DIR: &str = "policies";
+const POLICY_EXTENSION: &str = ".policy.json";
+
+/// A policy file
+#[derive(Deserialize, Clone)]
+pub struct Policy {
+    pub name: String,
+    pub observation: Vec<ObservationField>,
+    pub model: Model,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Model {
+    Mlp {
+        layers: Vec<Layer>,
+    },
+    Table {
+        rules: Vec<Rule>,
+        #[serde(default)]
+        default: f32,
+    },
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Layer {
+    // A row per output, with a weight per input
+    pub weights: Vec<Vec<f32>>,
+    pub biases: Vec<f32>,
+    #[serde(default)]
+    pub activation: Activation,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Activation {
+    #[default]
+    Linear,
+    Relu,
+    Tanh,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Rule {
+    pub when: Vec<Condition>,
+    pub action: f32,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Condition {
+    pub weights: Vec<f32>,
+    pub above: Option<f32>,
+    pub below: Option<f32>,
+}
+
+impl Policy {
+    /// Reads a policy file, checking the model fits the observation
+    pub fn load(path: &Path) -> Result<Policy, String> {
+        let json = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let policy: Policy = serde_json::from_str(&json).map_err(|error| error.to_string())?;
+        policy.check()?;
+        Ok(policy)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let inputs: usize = self.observation.iter().map(ObservationField::size).sum();
+        match &self.model {
+            Model::Mlp { layers } => {
+                let mut size = inputs;
+                for (index, layer) in layers.iter().enumerate() {
+                    if layer.biases.len() != layer.weights.len()
+                        || layer.weights.iter().any(|row| row.len() != size)
+                    {
+                        return Err(format!(
+                            "layer {} should have a bias and {size} weights per output",
+                            index + 1
+                        ));
+                    }
+                    size = layer.biases.len();
+                }
+                if size != 1 {
+                    return Err("the last layer should have a single output".to_string());
+                }
+            }
+            Model::Table { rules, .. } => {
+                let conditions = rules.iter().flat_map(|rule| &rule.when);
+                if conditions
+                    .into_iter()
+                    .any(|condition| condition.weights.len() != inputs)
+                {
+                    return Err(format!("every condition should have {inputs} weights"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The movement for an observation, from -1 to 1
+    pub fn act(&self, observation: &[f32]) -> f32 {
+        let action = match &self.model {
+            Model::Mlp { layers } => layers
+                .iter()
+                .fold(observation.to_vec(), |values, layer| layer.apply(&values))
+                .first()
+                .copied()
+                .unwrap_or(0.),
+            Model::Table { rules, default } => rules
+                .iter()
+                .find(|rule| {
+                    rule.when
+                        .iter()
+                        .all(|condition| condition.holds(observation))
+                })
+                .map_or(*default, |rule| rule.action),
+        };
+        // A broken model stands still instead of sending the paddle off
+        if action.is_nan() {
+            0.
+        } else {
+            action.clamp(-1.0, 1.0)
+        }
+    }
+}
+
+impl Layer {
+    fn apply(&self, inputs: &[f32]) -> Vec<f32> {
+        self.weights
+            .iter()
+            .zip(&self.biases)
+            .map(|(row, bias)| {
+                let sum = weighted_sum(row, inputs) + bias;
+                match self.activation {
+                    Activation::Linear => sum,
+                    Activation::Relu => sum.max(0.),
+                    Activation::Tanh => sum.tanh(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Condition {
+    fn holds(&self, observation: &[f32]) -> bool {
+        let value = weighted_sum(&self.weights, observation);
+        self.above.is_none_or(|above| value > above) && self.below.is_none_or(|below| value < below)
+    }
+}
+
+fn weighted_sum(weights: &[f32], values: &[f32]) -> f32 {
+    weights
+        .iter()
+        .zip(values)
+        .map(|(weight, value)| weight * value)
+        .sum()
+}
+
+/// The policies found in the policy folder, by name
+#[derive(Resource, Default)]
+pub struct Policies(pub Vec<Policy>);
+
+impl Policies {
+    pub fn get(&self, name: &str) -> Option<&Policy> {
+        self.0.iter().find(|policy| policy.name == name)
+    }
+
+    // The name of the policy after `current`, with the built-in computer before the first and after the last
+    pub fn next_name(&self, current: Option<&str>) -> Option<String> {
+        let next =
+            match current.and_then(|name| self.0.iter().position(|policy| policy.name == name)) {
+                Some(index) => index + 1,
+                None => 0,
+            };
+        self.0.get(next).map(|policy| policy.name.clone())
+    }
+}
+
+/// The policies steering computer paddles in the current match, in place of their personality
+#[derive(Resource, Default)]
+pub struct ActivePolicy {
+    // Steers every computer paddle without a policy of its own
+    pub policy: Option<Policy>,
+    // Sides playing with a policy of their own instead, indexed by `PaddleSide::index`
+    pub sides: [Option<Policy>; 4],
+}
+
+impl ActivePolicy {
+    pub fn for_side(&self, side: PaddleSide) -> Option<&Policy> {
+        self.sides[side.index()].as_ref().or(self.policy.as_ref())
+    }
+}
+
+pub fn find_policies(mut policies: ResMut<Policies>) {
+    policies.0.clear();
+    // Web builds have no file system, and without the folder there is nothing to find
+    let Ok(entries) = fs::read_dir(POLICY_DIR) else {
+        return;
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(POLICY_EXTENSION))
+        })
+        .collect();
+    paths.sort();
+    for path in paths {
+        match Policy::load(&path) {
+            Ok(policy) => policies.0.push(policy),
+            Err(error) => warn!("Failed to load policy {}: {error}", path.display()),
+        }
+    }
+}
+
+fn pick_policy(mut commands: Commands, rules: Res<MatchRules>, policies: Res<Policies>) {
+    if let Some(policy) = rules.policy.as_deref().and_then(|name| policies.get(name)) {
+        commands.insert_resource(ActivePolicy {
+            policy: Some(policy.clone()),
+            ..default()
+        });
+    }
+}
+
+fn drop_policy(mut commands: Commands) {
+    commands.remove_resource::<ActivePolicy>();
+}
+
+// Takes the place of `move_ai_paddle` for the sides with a policy, moving as fast as the computer would
+fn move_policy_paddles(
+    time: Res<Time>,
+    rules: Res<MatchRules>,
+    actions: Res<Actions>,
+    ai_skill: Res<AiSkill>,
+    active_policy: Res<ActivePolicy>,
+    ball_query: Query<(&Transform, &Velocity), (With<Ball>, Without<Paddle>)>,
+    mut paddle_query: Query<
+        (
+            &mut Transform,
+            &Paddle,
+            &Controller,
+            &Collider,
+            Has<ReversedControls>,
+        ),
+        Without<Ball>,
+    >,
+) {
+    // Where the paddle guarding each goal is, which is what the opponent position looks at
+    let mut goal_paddles = [None; 4];
+    for (transform, paddle, ..) in &paddle_query {
+        if !paddle.forward {
+            goal_paddles[paddle.side.index()] = Some(transform.translation.truncate());
+        }
+    }
+
+    for (mut transform, paddle, controller, collider, reversed) in &mut paddle_query {
+        if *controller != Controller::Ai {
+            continue;
+        }
+        let side = paddle.side;
+        let Some(policy) = active_policy.for_side(side) else {
+            continue;
+        };
+        let speed_scale = rules.handicaps[side.index()].speed_scale();
+        let max_step = PADDLE_SPEED * speed_scale * time.delta_secs();
+        let position = transform.translation.truncate();
+        move_computer_paddle(
+            &mut transform,
+            paddle,
+            collider,
+            reversed,
+            max_step,
+            actions.opponent_target,
+            |_| {
+                let ball = most_threatening_ball(position, side.facing(), &ball_query);
+                let observation: Observation = observation_for(
+                    &policy.observation,
+                    side,
+                    ball,
+                    position,
+                    goal_paddles[side.opposite().index()],
+                );
+                let step = ai_skill.for_side(side).speed * speed_scale * time.delta_secs();
+                Some(policy.act(&observation) * step)
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(weights: Vec<Vec<f32>>, biases: Vec<f32>, activation: Activation) -> Layer {
+        Layer {
+            weights,
+            biases,
+            activation,
+        }
+    }
+
+    // A policy looking at the paddle position alone, a single input
+    fn policy(model: Model) -> Policy {
+        Policy {
+            name: "Test".to_string(),
+            observation: vec![ObservationField::PaddlePosition],
+            model,
+        }
+    }
+
+    fn condition(above: Option<f32>, below: Option<f32>) -> Condition {
+        Condition {
+            weights: vec![1.],
+            above,
+            below,
+        }
+    }
+
+    #[test]
+    fn check_accepts_mlp_matching_the_observation() {
+        let model = Model::Mlp {
+            layers: vec![
+                layer(vec![vec![1.], vec![-1.]], vec![0., 0.], Activation::Relu),
+                layer(vec![vec![1., 1.]], vec![0.], Activation::Tanh),
+            ],
+        };
+        assert!(policy(model).check().is_ok());
+    }
+
+    #[test]
+    fn check_rejects_mlp_of_the_wrong_shape() {
+        let models = [
+            // Two weights for a single input
+            vec![layer(vec![vec![1., 1.]], vec![0.], Activation::Linear)],
+            // A bias missing
+            vec![layer(
+                vec![vec![1.], vec![1.]],
+                vec![0.],
+                Activation::Linear,
+            )],
+            // Two outputs out of the last layer
+            vec![layer(
+                vec![vec![1.], vec![1.]],
+                vec![0., 0.],
+                Activation::Linear,
+            )],
+            // The second layer does not take the two outputs of the first
+            vec![
+                layer(vec![vec![1.], vec![1.]], vec![0., 0.], Activation::Linear),
+                layer(vec![vec![1.]], vec![0.], Activation::Linear),
+            ],
+        ];
+        for layers in models {
+            assert!(policy(Model::Mlp { layers }).check().is_err());
+        }
+        // Without any layer the output is the whole observation, which is more than one value
+        let mut policy = policy(Model::Mlp { layers: vec![] });
+        policy.observation = ObservationField::ALL.to_vec();
+        assert!(policy.check().is_err());
+    }
+
+    #[test]
+    fn check_rejects_conditions_of_the_wrong_size() {
+        let rule = Rule {
+            when: vec![Condition {
+                weights: vec![1., 1.],
+                above: Some(0.),
+                below: None,
+            }],
+            action: 1.,
+        };
+        let model = Model::Table {
+            rules: vec![rule],
+            default: 0.,
+        };
+        assert!(policy(model).check().is_err());
+    }
+
+    #[test]
+    fn mlp_applies_layers_in_order() {
+        let model = Model::Mlp {
+            layers: vec![
+                layer(vec![vec![2.], vec![-2.]], vec![0.1, 0.1], Activation::Relu),
+                layer(vec![vec![1., -1.]], vec![0.], Activation::Tanh),
+            ],
+        };
+        let policy = policy(model);
+        // relu(0.2 + 0.1) = 0.3 and relu(-0.2 + 0.1) = 0, then tanh(0.3)
+        assert_eq!(policy.act(&[0.1]), 0.3f32.tanh());
+        // relu(-0.3) = 0 and relu(0.5) = 0.5, then tanh(-0.5)
+        assert_eq!(policy.act(&[-0.2]), (-0.5f32).tanh());
+    }
+
+    #[test]
+    fn table_takes_the_first_matching_rule() {
+        let model = Model::Table {
+            rules: vec![
+                Rule {
+                    when: vec![condition(Some(0.5), None)],
+                    action: 1.,
+                },
+                Rule {
+                    when: vec![condition(Some(0.), Some(0.8))],
+                    action: 0.5,
+                },
+                Rule {
+                    when: vec![condition(None, Some(-0.5))],
+                    action: -1.,
+                },
+            ],
+            default: -0.25,
+        };
+        let policy = policy(model);
+        assert_eq!(policy.act(&[0.9]), 1.);
+        // Both of the first two rules hold, and the first one wins
+        assert_eq!(policy.act(&[0.6]), 1.);
+        assert_eq!(policy.act(&[0.3]), 0.5);
+        assert_eq!(policy.act(&[-0.9]), -1.);
+        // Bounds are exclusive
+        assert_eq!(policy.act(&[0.]), -0.25);
+        assert_eq!(policy.act(&[-0.5]), -0.25);
+    }
+
+    #[test]
+    fn act_stays_within_full_speed() {
+        let linear = |bias| {
+            policy(Model::Mlp {
+                layers: vec![layer(vec![vec![1.]], vec![bias], Activation::Linear)],
+            })
+        };
+        assert_eq!(linear(5.).act(&[0.]), 1.);
+        assert_eq!(linear(-5.).act(&[0.]), -1.);
+        assert_eq!(linear(0.).act(&[f32::NAN]), 0.);
+        let table = policy(Model::Table {
+            rules: vec![],
+            default: f32::NAN,
+        });
+        assert_eq!(table.act(&[0.]), 0.);
+    }
+}
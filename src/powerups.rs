@@ -0,0 +1,571 @@
+use crate::actions::Controller;
+use crate::events::{BallHitPaddle, RoundEnd};
+use crate::game::{
+    check_collision, clamp_to_court, move_ball, move_paddle_to, Ball, Collider, LastHitBy, Paddle,
+    PlayingState, Velocity, COURT_HEIGHT,
+};
+use crate::rules::MatchRules;
+use crate::theme::ThemeRole;
+use crate::{GameSet, GameState};
+use bevy::ecs::component::ComponentId;
+use bevy::ecs::world::DeferredWorld;
+use bevy::prelude::*;
+use rand::Rng;
+
+pub struct PowerUpsPlugin;
+
+/// This plugin scatters power-up pickups over the middle of the court when the [`MatchRules`] allow it.
+/// A ball touching a pickup gives its effect to the paddle that last hit that ball.
+/// Every effect is a component with its own timer that undoes its change when it is removed,
+/// whether it ran out, the round ended or the match was left.
+impl Plugin for PowerUpsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_power_ups.run_if(|rules: Res<MatchRules>| rules.power_ups),
+                collect_power_ups.after(move_ball),
+                catch_balls.after(move_ball),
+                hold_caught_balls.after(move_ball),
+                expire_effect::<Resized>,
+                expire_effect::<SpeedBurst>,
+                expire_effect::<Sticky>,
+                expire_effect::<ReversedControls>,
+                expire_extra_paddles,
+            )
+                .in_set(GameSet::Physics)
+                .run_if(in_state(GameState::Playing))
+                .run_if(in_state(PlayingState::Playing)),
+        )
+        .add_systems(
+            Update,
+            clear_effects_on_round_end
+                .in_set(GameSet::Scoring)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), cleanup_power_ups);
+    }
+}
+
+const SPAWN_INTERVAL: f32 = 6.0;
+const MAX_PICKUPS: usize = 2;
+const PICKUP_SIZE: Vec2 = Vec2::new(30.0, 30.0);
+// Pickups appear between these x coordinates, well away from the paddles
+const SPAWN_WIDTH: f32 = 300.0;
+const EFFECT_DURATION: f32 = 10.0;
+const SPEED_BURST_DURATION: f32 = 2.0;
+const REVERSE_DURATION: f32 = 5.0;
+const GROW_SCALE: f32 = 1.5;
+const SHRINK_SCALE: f32 = 0.6;
+const SPEED_BURST_SCALE: f32 = 1.6;
+const CATCH_DURATION: f32 = 0.75;
+// Paddle travel while holding a caught ball that aims the release at the steepest angle
+const AIM_RANGE: f32 = 100.0;
+
+#[derive(Component, Clone, Copy)]
+pub enum PowerUp {
+    // The collecting paddle grows
+    Grow,
+    // The opposing paddles shrink
+    Shrink,
+    // The ball that picked it up speeds up for a moment
+    SpeedBurst,
+    // A second paddle joins the collector's side in mid-court
+    ExtraPaddle,
+    // The collecting paddle catches balls and releases them aimed by its movement
+    Sticky,
+    // Movement is swapped for the opposing paddles
+    ReverseControls,
+}
+
+impl PowerUp {
+    const ALL: [PowerUp; 6] = [
+        PowerUp::Grow,
+        PowerUp::Shrink,
+        PowerUp::SpeedBurst,
+        PowerUp::ExtraPaddle,
+        PowerUp::Sticky,
+        PowerUp::ReverseControls,
+    ];
+
+    fn color(&self) -> Color {
+        match self {
+            PowerUp::Grow => Color::linear_rgb(0.2, 0.8, 0.2),
+            PowerUp::Shrink => Color::linear_rgb(0.8, 0.2, 0.2),
+            PowerUp::SpeedBurst => Color::linear_rgb(1.0, 0.6, 0.1),
+            PowerUp::ExtraPaddle => Color::linear_rgb(0.2, 0.5, 1.0),
+            PowerUp::Sticky => Color::linear_rgb(0.8, 0.3, 0.8),
+            PowerUp::ReverseControls => Color::linear_rgb(0.9, 0.9, 0.2),
+        }
+    }
+
+    fn letter(&self) -> &'static str {
+        match self {
+            PowerUp::Grow => "G",
+            PowerUp::Shrink => "S",
+            PowerUp::SpeedBurst => "B",
+            PowerUp::ExtraPaddle => "P",
+            PowerUp::Sticky => "C",
+            PowerUp::ReverseControls => "R",
+        }
+    }
+}
+
+// A paddle whose size was changed by a power-up, going back to `base_size` when removed
+#[derive(Component)]
+#[component(on_remove = restore_paddle_size)]
+pub struct Resized {
+    timer: Timer,
+    base_size: Vec2,
+}
+
+// A ball sped up by a power-up, going back to `base_speed` when removed
+#[derive(Component)]
+#[component(on_remove = restore_ball_speed)]
+pub struct SpeedBurst {
+    timer: Timer,
+    base_speed: f32,
+}
+
+#[derive(Component)]
+pub struct Sticky {
+    timer: Timer,
+}
+
+#[derive(Component)]
+pub struct ReversedControls {
+    timer: Timer,
+}
+
+// A temporary paddle, despawned when its time is up
+#[derive(Component)]
+pub struct ExtraPaddle {
+    timer: Timer,
+}
+
+// A ball held by a sticky paddle, which stops moving on its own until released
+#[derive(Component)]
+pub struct Caught {
+    paddle: Entity,
+    // Position along the paddle, which the ball keeps while it is held
+    offset: f32,
+    timer: Timer,
+    last_paddle_position: f32,
+    aim: f32,
+}
+
+trait TimedEffect: Component {
+    fn timer_mut(&mut self) -> &mut Timer;
+}
+
+impl TimedEffect for Resized {
+    fn timer_mut(&mut self) -> &mut Timer {
+        &mut self.timer
+    }
+}
+
+impl TimedEffect for SpeedBurst {
+    fn timer_mut(&mut self) -> &mut Timer {
+        &mut self.timer
+    }
+}
+
+impl TimedEffect for Sticky {
+    fn timer_mut(&mut self) -> &mut Timer {
+        &mut self.timer
+    }
+}
+
+impl TimedEffect for ReversedControls {
+    fn timer_mut(&mut self) -> &mut Timer {
+        &mut self.timer
+    }
+}
+
+fn restore_paddle_size(mut world: DeferredWorld, entity: Entity, _component: ComponentId) {
+    let Some(base_size) = world
+        .get::<Resized>(entity)
+        .map(|resized| resized.base_size)
+    else {
+        return;
+    };
+    if let Some(mut collider) = world.get_mut::<Collider>(entity) {
+        collider.0 = base_size;
+    }
+    if let Some(mut sprite) = world.get_mut::<Sprite>(entity) {
+        sprite.custom_size = Some(base_size);
+    }
+}
+
+fn restore_ball_speed(mut world: DeferredWorld, entity: Entity, _component: ComponentId) {
+    let Some(base_speed) = world
+        .get::<SpeedBurst>(entity)
+        .map(|burst| burst.base_speed)
+    else {
+        return;
+    };
+    if let Some(mut velocity) = world.get_mut::<Velocity>(entity) {
+        velocity.speed = base_speed;
+    }
+}
+
+fn spawn_power_ups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut since_last: Local<f32>,
+    pickup_query: Query<(), With<PowerUp>>,
+) {
+    *since_last += time.delta_secs();
+    if *since_last < SPAWN_INTERVAL {
+        return;
+    }
+    *since_last = 0.;
+    if pickup_query.iter().count() >= MAX_PICKUPS {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    let power_up = PowerUp::ALL[rng.gen_range(0..PowerUp::ALL.len())];
+    let position = Vec3::new(
+        rng.gen_range(-SPAWN_WIDTH..SPAWN_WIDTH),
+        rng.gen_range(-COURT_HEIGHT + PICKUP_SIZE.y..COURT_HEIGHT - PICKUP_SIZE.y),
+        0.5,
+    );
+    commands
+        .spawn((
+            Sprite {
+                color: power_up.color(),
+                custom_size: Some(PICKUP_SIZE),
+                ..default()
+            },
+            Transform::from_translation(position),
+            Collider(PICKUP_SIZE),
+            power_up,
+        ))
+        .with_child((
+            Text2d::new(power_up.letter()),
+            TextFont {
+                font_size: 20.0,
+                ..default()
+            },
+            TextColor(Color::BLACK),
+            Transform::from_xyz(0., 0., 0.1),
+        ));
+}
+
+fn collect_power_ups(
+    mut commands: Commands,
+    mut ball_query: Query<
+        (
+            Entity,
+            &Transform,
+            &Collider,
+            &LastHitBy,
+            &mut Velocity,
+            Option<&SpeedBurst>,
+        ),
+        With<Ball>,
+    >,
+    pickup_query: Query<(Entity, &Transform, &Collider, &PowerUp), Without<Ball>>,
+    mut paddle_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Collider,
+            &mut Sprite,
+            &Paddle,
+            &Controller,
+            Option<&Resized>,
+        ),
+        (Without<Ball>, Without<PowerUp>),
+    >,
+) {
+    // Two balls can reach the same pickup in one frame, only the first gets it
+    let mut collected = Vec::new();
+    for (ball, ball_transform, ball_collider, last_hit_by, mut velocity, speed_burst) in
+        &mut ball_query
+    {
+        // Balls nobody has hit yet pass over the pickups
+        let Some(collector) = last_hit_by.0 else {
+            continue;
+        };
+        let Ok((_, collector_transform, _, _, collector_paddle, collector_controller, _)) =
+            paddle_query.get(collector)
+        else {
+            continue;
+        };
+        let collector_paddle = *collector_paddle;
+        let collector_side = collector_paddle.side;
+        let collector_controller = *collector_controller;
+        let collector_position = collector_transform.translation.truncate();
+        for (pickup, pickup_transform, pickup_collider, power_up) in &pickup_query {
+            if collected.contains(&pickup)
+                || !check_collision(
+                    ball_transform,
+                    ball_collider,
+                    pickup_transform,
+                    pickup_collider,
+                )
+            {
+                continue;
+            }
+            collected.push(pickup);
+            commands.entity(pickup).despawn_recursive();
+
+            match power_up {
+                PowerUp::Grow | PowerUp::Shrink => {
+                    let (scale, grow) = match power_up {
+                        PowerUp::Grow => (GROW_SCALE, true),
+                        _ => (SHRINK_SCALE, false),
+                    };
+                    for (
+                        paddle,
+                        mut transform,
+                        mut collider,
+                        mut sprite,
+                        paddle_side,
+                        _,
+                        resized,
+                    ) in &mut paddle_query
+                    {
+                        // Grow the collector's side, shrink the other ones
+                        let side = paddle_side.side;
+                        if (side == collector_side) != grow {
+                            continue;
+                        }
+                        // A paddle resized again keeps the size it had before the first one,
+                        // and only its length along the side changes
+                        let base_size = resized.map_or(collider.0, |resized| resized.base_size);
+                        let axis = side.axis();
+                        collider.0 = base_size * (axis * (scale - 1.0) + Vec2::ONE);
+                        sprite.custom_size = Some(collider.0);
+                        let position = clamp_to_court(
+                            transform.translation.truncate().dot(axis),
+                            side,
+                            &collider,
+                        );
+                        move_paddle_to(&mut transform, side, position);
+                        commands.entity(paddle).insert(Resized {
+                            timer: Timer::from_seconds(EFFECT_DURATION, TimerMode::Once),
+                            base_size,
+                        });
+                    }
+                }
+                PowerUp::SpeedBurst => {
+                    let base_speed = speed_burst.map_or(velocity.speed, |burst| burst.base_speed);
+                    velocity.speed = base_speed * SPEED_BURST_SCALE;
+                    commands.entity(ball).insert(SpeedBurst {
+                        timer: Timer::from_seconds(SPEED_BURST_DURATION, TimerMode::Once),
+                        base_speed,
+                    });
+                }
+                PowerUp::ExtraPaddle => {
+                    // Extra paddles guard the middle of their owner's half,
+                    // moved by the same controller as the collector
+                    let axis = collector_side.axis();
+                    let position =
+                        collector_side.home() / 2.0 + axis * collector_position.dot(axis);
+                    let size = collector_side.paddle_size();
+                    // Not tagged `Player`, so pointer input keeps steering the collector's paddle,
+                    // which this one moves along with
+                    commands.spawn((
+                        Sprite {
+                            color: Color::WHITE,
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        Transform::from_translation(position.extend(1.)),
+                        Collider(size),
+                        // Copied so it listens to the same keys as the collector
+                        collector_paddle,
+                        collector_controller,
+                        ExtraPaddle {
+                            timer: Timer::from_seconds(EFFECT_DURATION, TimerMode::Once),
+                        },
+                        ThemeRole::Paddle,
+                    ));
+                }
+                PowerUp::Sticky => {
+                    commands.entity(collector).insert(Sticky {
+                        timer: Timer::from_seconds(EFFECT_DURATION, TimerMode::Once),
+                    });
+                }
+                PowerUp::ReverseControls => {
+                    for (paddle, _, _, _, paddle_side, _, _) in &paddle_query {
+                        if paddle_side.side != collector_side {
+                            commands.entity(paddle).insert(ReversedControls {
+                                timer: Timer::from_seconds(REVERSE_DURATION, TimerMode::Once),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Sticky paddles hold on to the balls they hit instead of returning them straight away
+fn catch_balls(
+    mut commands: Commands,
+    mut paddle_hit_events: EventReader<BallHitPaddle>,
+    paddle_query: Query<(&Transform, &Paddle), With<Sticky>>,
+) {
+    for event in paddle_hit_events.read() {
+        let Ok((paddle_transform, paddle)) = paddle_query.get(event.paddle) else {
+            continue;
+        };
+        let axis = paddle.side.axis();
+        let paddle_position = paddle_transform.translation.truncate().dot(axis);
+        commands.entity(event.ball).insert(Caught {
+            paddle: event.paddle,
+            offset: event.position.dot(axis) - paddle_position,
+            timer: Timer::from_seconds(CATCH_DURATION, TimerMode::Once),
+            last_paddle_position: paddle_position,
+            aim: 0.,
+        });
+    }
+}
+
+fn hold_caught_balls(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut ball_query: Query<(Entity, &mut Transform, &mut Velocity, &mut Caught), With<Ball>>,
+    paddle_query: Query<(&Transform, &Paddle), Without<Ball>>,
+) {
+    for (ball, mut transform, mut velocity, mut caught) in &mut ball_query {
+        let Ok((paddle_transform, paddle)) = paddle_query.get(caught.paddle) else {
+            commands.entity(ball).remove::<Caught>();
+            continue;
+        };
+        // The ball rides along with the paddle, and moving the paddle aims the release
+        let facing = paddle.side.facing();
+        let axis = paddle.side.axis();
+        let paddle_position = paddle_transform.translation.truncate().dot(axis);
+        caught.aim += paddle_position - caught.last_paddle_position;
+        caught.last_paddle_position = paddle_position;
+        let position = facing * transform.translation.truncate().dot(facing)
+            + axis * (paddle_position + caught.offset);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+
+        if caught.timer.tick(time.delta()).finished() {
+            velocity.direction = (facing * velocity.direction.dot(facing).abs()
+                + axis * (caught.aim / AIM_RANGE).clamp(-0.8, 0.8))
+            .normalize();
+            commands.entity(ball).remove::<Caught>();
+        }
+    }
+}
+
+fn expire_effect<T: TimedEffect>(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut effect_query: Query<(Entity, &mut T)>,
+) {
+    for (entity, mut effect) in &mut effect_query {
+        if effect.timer_mut().tick(time.delta()).finished() {
+            commands.entity(entity).remove::<T>();
+        }
+    }
+}
+
+fn expire_extra_paddles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut paddle_query: Query<(Entity, &mut ExtraPaddle)>,
+) {
+    for (entity, mut extra_paddle) in &mut paddle_query {
+        if extra_paddle.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+// Every round starts without the effects of the last one, but the pickups stay on the court
+fn clear_effects_on_round_end(
+    mut commands: Commands,
+    mut round_end_events: EventReader<RoundEnd>,
+    effect_query: Query<Entity, Or<(With<Resized>, With<Sticky>, With<ReversedControls>)>>,
+    extra_paddle_query: Query<Entity, With<ExtraPaddle>>,
+) {
+    if round_end_events.read().count() == 0 {
+        return;
+    }
+    for entity in &effect_query {
+        commands
+            .entity(entity)
+            .remove::<(Resized, Sticky, ReversedControls)>();
+    }
+    for entity in &extra_paddle_query {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn cleanup_power_ups(mut commands: Commands, pickup_query: Query<Entity, With<PowerUp>>) {
+    for entity in pickup_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removing_an_effect_undoes_it() {
+        let mut world = World::new();
+        let base_size = Vec2::new(20., 100.);
+        let paddle = world
+            .spawn((
+                Collider(base_size * GROW_SCALE),
+                Sprite::from_color(Color::WHITE, base_size * GROW_SCALE),
+                Resized {
+                    timer: Timer::from_seconds(EFFECT_DURATION, TimerMode::Once),
+                    base_size,
+                },
+            ))
+            .id();
+        let ball = world
+            .spawn((
+                Velocity {
+                    direction: Vec2::X,
+                    speed: 400. * SPEED_BURST_SCALE,
+                },
+                SpeedBurst {
+                    timer: Timer::from_seconds(SPEED_BURST_DURATION, TimerMode::Once),
+                    base_speed: 400.,
+                },
+            ))
+            .id();
+
+        world.entity_mut(paddle).remove::<Resized>();
+        world.entity_mut(ball).remove::<SpeedBurst>();
+        assert_eq!(world.get::<Collider>(paddle).unwrap().0, base_size);
+        assert_eq!(
+            world.get::<Sprite>(paddle).unwrap().custom_size,
+            Some(base_size)
+        );
+        assert_eq!(world.get::<Velocity>(ball).unwrap().speed, 400.);
+    }
+
+    #[test]
+    fn effects_run_out_after_their_time() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(
+                std::time::Duration::from_secs_f32(1.0),
+            ))
+            .add_systems(Update, expire_effect::<ReversedControls>);
+        let paddle = app
+            .world_mut()
+            .spawn(ReversedControls {
+                timer: Timer::from_seconds(REVERSE_DURATION, TimerMode::Once),
+            })
+            .id();
+        let mut elapsed = 0.;
+        while app.world().get::<ReversedControls>(paddle).is_some() && elapsed < 60. {
+            app.update();
+            elapsed = app.world().resource::<Time>().elapsed_secs();
+        }
+        assert_eq!(elapsed, REVERSE_DURATION);
+    }
+}
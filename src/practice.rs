@@ -0,0 +1,452 @@
+use crate::data::RonAssetAppExt;
+use crate::events::{BallHitPaddle, Goal, MatchEnded, ServeStarted};
+use crate::game::{
+    check_collision, move_ball, spawn_ball_at, Ball, Collider, PaddleSide, PlayingState, Spin,
+    Velocity, BALL_SIZE, COURT_HALF_WIDTH,
+};
+use crate::loading::DrillAssets;
+use crate::rules::{GameMode, MatchRules};
+use crate::theme::ThemeRole;
+use crate::ui::spawn_end_screen;
+use crate::{GameSet, GameState};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+pub struct PracticePlugin;
+
+/// This plugin runs practice sessions: the left paddle plays alone against the [`Drill`] picked in the
+/// [`MatchRules`], either a solid wall or a ball machine serving a pattern.
+/// Returns made and missed, and how many of them landed in the drill's target zones,
+/// are counted until the session runs out of time and a summary is shown.
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_ron_asset::<Drill>(&["drill.ron"])
+            .init_resource::<PracticeSession>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                start_session.run_if(is_practice),
+            )
+            .add_systems(
+                Update,
+                (
+                    (serve_drill_balls, track_returns.after(move_ball)).in_set(GameSet::Physics),
+                    end_session.in_set(GameSet::Scoring),
+                    update_practice_text.in_set(GameSet::Presentation),
+                )
+                    .run_if(is_practice)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PlayingState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), cleanup_practice);
+    }
+}
+
+/// A practice drill, loaded from `assets/drills/*.drill.ron`
+#[derive(Asset, TypePath, Deserialize)]
+pub struct Drill {
+    pub name: String,
+    // Seconds a session lasts
+    pub duration: f32,
+    pub opponent: DrillOpponent,
+    // Zones on the far half of the court that returns should go through
+    #[serde(default)]
+    pub targets: Vec<TargetZone>,
+}
+
+#[derive(Deserialize, Clone)]
+pub enum DrillOpponent {
+    // A solid wall returning every ball, which serves again once a ball is missed
+    Wall {
+        serve: DrillServe,
+    },
+    // Serves the pattern over and over, keeping the balls that are returned to it
+    BallMachine {
+        interval: f32,
+        pattern: Vec<DrillServe>,
+    },
+}
+
+#[derive(Deserialize, Clone)]
+pub struct DrillServe {
+    pub speed: f32,
+    // Degrees above the straight line towards the player's goal, negative for below
+    #[serde(default)]
+    pub angle: f32,
+    // Degrees per second the ball curves by until it is hit, positive curving anticlockwise
+    #[serde(default)]
+    pub spin: f32,
+    // Where the ball leaves the far end
+    #[serde(default)]
+    pub height: f32,
+}
+
+#[derive(Deserialize)]
+pub struct TargetZone {
+    // Center of the zone
+    pub position: (f32, f32),
+    pub size: (f32, f32),
+}
+
+// The session running in the current match
+#[derive(Resource, Default)]
+pub struct PracticeSession {
+    opponent: Option<DrillOpponent>,
+    duration: f32,
+    elapsed: f32,
+    since_serve: f32,
+    next_serve: usize,
+    has_targets: bool,
+    made: u32,
+    missed: u32,
+    on_target: u32,
+}
+
+// Seconds the wall waits before serving a new ball
+const WALL_SERVE_DELAY: f32 = 1.0;
+// Distance from the far end that balls are served from and counted as returned at
+const FAR_END_MARGIN: f32 = 40.0;
+
+// A ball on its way back from the player
+#[derive(Component)]
+struct Returned {
+    on_target: bool,
+}
+
+#[derive(Component)]
+struct Target;
+
+#[derive(Component)]
+struct PracticeText;
+
+// Everything spawned for a session, removed when the match is left
+#[derive(Component)]
+struct PracticeEntity;
+
+/// All loaded drills, in the order they are offered on the rules screen
+#[derive(SystemParam)]
+pub struct Drills<'w> {
+    assets: Res<'w, Assets<Drill>>,
+    collection: Res<'w, DrillAssets>,
+}
+
+impl Drills<'_> {
+    fn iter(&self) -> impl Iterator<Item = &Drill> {
+        self.collection
+            .drills
+            .iter()
+            .filter_map(|handle| self.assets.get(handle))
+    }
+
+    // The drill picked in the match rules, falling back to the first one if it is unknown
+    pub fn active(&self, rules: &MatchRules) -> Option<&Drill> {
+        self.iter()
+            .find(|drill| drill.name == rules.drill)
+            .or_else(|| self.iter().next())
+    }
+
+    // The name of the drill after `current`, wrapping around to the first
+    pub fn next_name(&self, current: &str) -> Option<String> {
+        let names: Vec<&str> = self.iter().map(|drill| drill.name.as_str()).collect();
+        let next = names
+            .iter()
+            .position(|name| *name == current)
+            .map_or(0, |index| (index + 1) % names.len());
+        names.get(next).map(|name| name.to_string())
+    }
+}
+
+fn is_practice(rules: Res<MatchRules>) -> bool {
+    rules.mode == GameMode::Practice
+}
+
+fn start_session(
+    mut commands: Commands,
+    rules: Res<MatchRules>,
+    drills: Drills,
+    mut session: ResMut<PracticeSession>,
+) {
+    *session = PracticeSession::default();
+    let Some(drill) = drills.active(&rules) else {
+        return;
+    };
+    session.opponent = Some(drill.opponent.clone());
+    session.duration = drill.duration;
+    session.has_targets = !drill.targets.is_empty();
+
+    for target in &drill.targets {
+        let size = Vec2::new(target.size.0, target.size.1);
+        commands.spawn((
+            Sprite {
+                color: Color::linear_rgba(0.2, 0.8, 0.2, 0.25),
+                custom_size: Some(size),
+                ..default()
+            },
+            Transform::from_xyz(target.position.0, target.position.1, 0.2),
+            Collider(size),
+            Target,
+            PracticeEntity,
+        ));
+    }
+
+    if let DrillOpponent::BallMachine { .. } = drill.opponent {
+        commands.spawn((
+            Sprite {
+                color: Color::WHITE,
+                custom_size: Some(Vec2::new(20.0, 60.0)),
+                ..default()
+            },
+            Transform::from_xyz(COURT_HALF_WIDTH - 10.0, 0., 0.5),
+            ThemeRole::CourtLine,
+            PracticeEntity,
+        ));
+    }
+
+    commands.spawn((
+        Text::new(practice_label(&session)),
+        TextFont {
+            font_size: 30.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        ThemeRole::UiText,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            left: Val::Px(20.0),
+            ..default()
+        },
+        PracticeText,
+        PracticeEntity,
+    ));
+}
+
+fn serve_drill_balls(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut session: ResMut<PracticeSession>,
+    ball_query: Query<(), With<Ball>>,
+    mut serve_events: EventWriter<ServeStarted>,
+) {
+    session.since_serve += time.delta_secs();
+    let serve = match &session.opponent {
+        Some(DrillOpponent::Wall { serve }) => {
+            if !ball_query.is_empty() {
+                session.since_serve = 0.;
+                return;
+            }
+            if session.since_serve < WALL_SERVE_DELAY {
+                return;
+            }
+            serve.clone()
+        }
+        Some(DrillOpponent::BallMachine { interval, pattern }) => {
+            if session.since_serve < *interval || pattern.is_empty() {
+                return;
+            }
+            let serve = pattern[session.next_serve % pattern.len()].clone();
+            session.next_serve += 1;
+            serve
+        }
+        None => return,
+    };
+    session.since_serve = 0.;
+
+    let angle = serve.angle.to_radians();
+    let position = Vec2::new(COURT_HALF_WIDTH - FAR_END_MARGIN, serve.height);
+    let direction = Vec2::new(-angle.cos(), angle.sin());
+    let mut ball = spawn_ball_at(
+        &mut commands,
+        position,
+        Velocity {
+            direction,
+            speed: serve.speed,
+        },
+    );
+    if serve.spin != 0. {
+        ball.insert(Spin(serve.spin));
+    }
+    serve_events.send(ServeStarted {
+        ball: ball.id(),
+        position,
+        velocity: direction * serve.speed,
+    });
+}
+
+fn track_returns(
+    mut commands: Commands,
+    mut session: ResMut<PracticeSession>,
+    mut paddle_hit_events: EventReader<BallHitPaddle>,
+    mut goal_events: EventReader<Goal>,
+    mut ball_query: Query<(Entity, &Transform, &Collider, &mut Returned), With<Ball>>,
+    target_query: Query<(&Transform, &Collider), (With<Target>, Without<Ball>)>,
+) {
+    for event in paddle_hit_events.read() {
+        if event.side == PaddleSide::Left {
+            commands
+                .entity(event.ball)
+                .try_insert(Returned { on_target: false });
+        }
+    }
+
+    for goal in goal_events.read() {
+        if goal.conceded == PaddleSide::Left {
+            session.missed += 1;
+        }
+    }
+
+    let machine = matches!(session.opponent, Some(DrillOpponent::BallMachine { .. }));
+    for (ball, transform, collider, mut returned) in &mut ball_query {
+        if target_query
+            .iter()
+            .any(|(target_transform, target_collider)| {
+                check_collision(transform, collider, target_transform, target_collider)
+            })
+        {
+            returned.on_target = true;
+        }
+        if transform.translation.x < COURT_HALF_WIDTH - FAR_END_MARGIN + BALL_SIZE.x {
+            continue;
+        }
+        session.made += 1;
+        if returned.on_target {
+            session.on_target += 1;
+        }
+        // The wall sends the ball back, the machine keeps it
+        if machine {
+            commands.entity(ball).despawn();
+        } else {
+            commands.entity(ball).remove::<Returned>();
+        }
+    }
+}
+
+fn practice_label(session: &PracticeSession) -> String {
+    let mut label = format!(
+        "Time {:.0}  Made {}  Missed {}",
+        (session.duration - session.elapsed).max(0.),
+        session.made,
+        session.missed
+    );
+    if session.has_targets {
+        label.push_str(&format!("  On target {}", session.on_target));
+    }
+    label
+}
+
+fn update_practice_text(
+    session: Res<PracticeSession>,
+    mut text_query: Query<&mut Text, With<PracticeText>>,
+) {
+    for mut text in &mut text_query {
+        text.0 = practice_label(&session);
+    }
+}
+
+fn end_session(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut session: ResMut<PracticeSession>,
+    mut match_ended_events: EventWriter<MatchEnded>,
+    mut next_state: ResMut<NextState<PlayingState>>,
+) {
+    session.elapsed += time.delta_secs();
+    if session.opponent.is_none() || session.elapsed < session.duration {
+        return;
+    }
+    next_state.set(PlayingState::Over);
+    match_ended_events.send(MatchEnded {
+        winner: None,
+        points: [0; 4],
+    });
+
+    let attempts = session.made + session.missed;
+    let mut details = vec![format!("Returns made: {} of {}", session.made, attempts)];
+    if session.has_targets {
+        let accuracy = (session.on_target * 100)
+            .checked_div(session.made)
+            .unwrap_or(0);
+        details.push(format!(
+            "On target: {} ({}% of returns)",
+            session.on_target, accuracy
+        ));
+    }
+    spawn_end_screen(&mut commands, "Session over", details, PracticeEntity);
+}
+
+fn cleanup_practice(mut commands: Commands, practice_query: Query<Entity, With<PracticeEntity>>) {
+    for entity in practice_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::read_ron_assets;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    #[test]
+    fn every_drill_loads() {
+        let drills = read_ron_assets::<Drill>("drills", "drill.ron").unwrap();
+        assert!(!drills.is_empty());
+    }
+
+    fn serve(speed: f32) -> DrillServe {
+        DrillServe {
+            speed,
+            angle: 0.,
+            spin: 0.,
+            height: 0.,
+        }
+    }
+
+    #[test]
+    fn ball_machine_serves_its_pattern_in_turn() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                0.25,
+            )))
+            .add_event::<ServeStarted>()
+            .insert_resource(PracticeSession {
+                opponent: Some(DrillOpponent::BallMachine {
+                    interval: 0.5,
+                    pattern: vec![serve(300.), serve(400.)],
+                }),
+                ..default()
+            })
+            .add_systems(Update, serve_drill_balls);
+        let mut speeds = Vec::new();
+        for _ in 0..8 {
+            app.update();
+            let events = app.world().resource::<Events<ServeStarted>>();
+            let mut cursor = events.get_cursor();
+            speeds.extend(cursor.read(events).map(|serve| serve.velocity.length()));
+            app.world_mut()
+                .resource_mut::<Events<ServeStarted>>()
+                .clear();
+        }
+        // The first update doesn't move time on
+        assert_eq!(speeds, [300., 400., 300.]);
+    }
+
+    #[test]
+    fn label_shows_targets_only_when_the_drill_has_them() {
+        let mut session = PracticeSession {
+            duration: 60.,
+            elapsed: 15.2,
+            made: 4,
+            missed: 1,
+            on_target: 2,
+            ..default()
+        };
+        assert_eq!(practice_label(&session), "Time 45  Made 4  Missed 1");
+        session.has_targets = true;
+        assert_eq!(
+            practice_label(&session),
+            "Time 45  Made 4  Missed 1  On target 2"
+        );
+    }
+}
@@ -0,0 +1,370 @@
+use crate::actions::{partner_keys, Controller};
+use crate::game::PaddleSide;
+use crate::handicap::Handicap;
+use crate::policy::Policies;
+use crate::practice::Drills;
+use crate::theme::ThemeRole;
+use crate::ui::{next_value, on_off, spawn_button, ChangeState};
+use crate::{GameSet, GameState};
+use bevy::prelude::*;
+
+pub struct RulesPlugin;
+
+/// This plugin holds the [`MatchRules`] for the next match and the screen to change them
+/// The screen is only reachable from the menu, so the rules never change in the middle of a match
+impl Plugin for RulesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchRules>()
+            .add_systems(OnEnter(GameState::Rules), setup_rules_menu)
+            .add_systems(
+                Update,
+                (handle_rule_click, handle_rules_escape_key)
+                    .in_set(GameSet::Input)
+                    .run_if(in_state(GameState::Rules)),
+            )
+            .add_systems(OnExit(GameState::Rules), cleanup_rules_menu);
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct MatchRules {
+    pub multi_ball: MultiBallRules,
+    // Pickups with timed effects appear in mid-court, off unless switched on for the match
+    pub power_ups: bool,
+    // Name of the `Arena` to play in
+    pub arena: String,
+    pub mode: GameMode,
+    // Who moves the paddle of each side, indexed by `PaddleSide::index`
+    pub controllers: [Controller; 4],
+    // Who moves the forward paddles of the left and right sides in doubles
+    pub partners: [Controller; 2],
+    // Goals a side can concede in party mode before it is out, and balls the player can lose in breakout
+    pub lives: u32,
+    // Name of the `Drill` to practice
+    pub drill: String,
+    // Minutes a time attack match lasts
+    pub time_limit: u32,
+    // Modifiers for each side, indexed by `PaddleSide::index` and picked on the setup screen before a match
+    pub handicaps: [Handicap; 4],
+    // Name of the `Policy` steering the computer paddles instead of the built-in computer, if any
+    pub policy: Option<String>,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        MatchRules {
+            multi_ball: MultiBallRules::default(),
+            power_ups: false,
+            arena: "Classic".to_string(),
+            mode: GameMode::Classic,
+            controllers: [
+                Controller::Player,
+                Controller::Ai,
+                Controller::Ai,
+                Controller::Ai,
+            ],
+            partners: [Controller::Ai, Controller::Ai],
+            lives: 3,
+            drill: "Wall".to_string(),
+            time_limit: 2,
+            handicaps: [Handicap::default(); 4],
+            policy: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MultiBallRules {
+    pub enabled: bool,
+    // Paddle hits in a rally after which another ball joins
+    pub hits_per_ball: u32,
+    pub max_balls: usize,
+    pub round_end: RoundEndRule,
+}
+
+impl Default for MultiBallRules {
+    fn default() -> Self {
+        MultiBallRules {
+            enabled: false,
+            hits_per_ball: 5,
+            max_balls: 3,
+            round_end: RoundEndRule::LastBall,
+        }
+    }
+}
+
+// Every ball that leaves the court scores, but only this decides when the court is reset for a new serve
+#[derive(Clone, Copy, PartialEq)]
+pub enum RoundEndRule {
+    // Play on until no ball is left on the court
+    LastBall,
+    // Every goal ends the round
+    EachGoal,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameMode {
+    // Left against right, scoring against each other
+    Classic,
+    // Like classic, with a second paddle near the net on each side
+    Doubles,
+    // A paddle and a goal on every side, and the last side with lives left wins
+    FourPlayer,
+    // The left paddle alone against a wall or ball machine
+    Practice,
+    // The left paddle alone, breaking the bricks that fill the far half of the court
+    Breakout,
+    // Most points before the time runs out, with sudden death on a tie
+    TimeAttack,
+    // One life against a computer that keeps getting better, with the ball getting faster
+    Survival,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 7] = [
+        GameMode::Classic,
+        GameMode::Doubles,
+        GameMode::FourPlayer,
+        GameMode::Practice,
+        GameMode::Breakout,
+        GameMode::TimeAttack,
+        GameMode::Survival,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "classic",
+            GameMode::Doubles => "doubles",
+            GameMode::FourPlayer => "four-player party",
+            GameMode::Practice => "practice",
+            GameMode::Breakout => "breakout",
+            GameMode::TimeAttack => "time attack",
+            GameMode::Survival => "survival",
+        }
+    }
+
+    pub fn sides(&self) -> &'static [PaddleSide] {
+        match self {
+            GameMode::Classic | GameMode::Doubles | GameMode::TimeAttack | GameMode::Survival => {
+                &PaddleSide::ALL[..2]
+            }
+            GameMode::FourPlayer => &PaddleSide::ALL,
+            GameMode::Practice | GameMode::Breakout => &PaddleSide::ALL[..1],
+        }
+    }
+}
+
+const HITS_PER_BALL: [u32; 4] = [3, 5, 10, 20];
+const MAX_BALLS: [usize; 4] = [2, 3, 5, 8];
+const LIVES: [u32; 4] = [1, 3, 5, 10];
+const TIME_LIMITS: [u32; 4] = [1, 2, 3, 5];
+
+// Each rules button cycles one rule through its possible values
+#[derive(Component, Clone, Copy)]
+enum RuleButton {
+    MultiBall,
+    HitsPerBall,
+    MaxBalls,
+    RoundEnd,
+    PowerUps,
+    Mode,
+    Controller(PaddleSide),
+    Partner(PaddleSide),
+    Lives,
+    Drill,
+    TimeLimit,
+    Policy,
+}
+
+impl RuleButton {
+    // The mode and the settings only some modes use
+    const MODE: [RuleButton; 4] = [
+        RuleButton::Mode,
+        RuleButton::Drill,
+        RuleButton::TimeLimit,
+        RuleButton::Lives,
+    ];
+
+    const ALL: [RuleButton; 5] = [
+        RuleButton::MultiBall,
+        RuleButton::HitsPerBall,
+        RuleButton::MaxBalls,
+        RuleButton::RoundEnd,
+        RuleButton::PowerUps,
+    ];
+
+    // Who plays, shown in a column of their own
+    const PLAYERS: [RuleButton; 7] = [
+        RuleButton::Controller(PaddleSide::Left),
+        RuleButton::Partner(PaddleSide::Left),
+        RuleButton::Controller(PaddleSide::Right),
+        RuleButton::Partner(PaddleSide::Right),
+        RuleButton::Controller(PaddleSide::Top),
+        RuleButton::Controller(PaddleSide::Bottom),
+        RuleButton::Policy,
+    ];
+
+    fn label(&self, rules: &MatchRules) -> String {
+        let multi_ball = &rules.multi_ball;
+        match self {
+            RuleButton::MultiBall => format!("Multi-ball: {}", on_off(multi_ball.enabled)),
+            RuleButton::HitsPerBall => {
+                format!("Extra ball every {} hits", multi_ball.hits_per_ball)
+            }
+            RuleButton::MaxBalls => format!("Max balls: {}", multi_ball.max_balls),
+            RuleButton::RoundEnd => match multi_ball.round_end {
+                RoundEndRule::LastBall => "Round ends: after last ball".to_string(),
+                RoundEndRule::EachGoal => "Round ends: on every goal".to_string(),
+            },
+            RuleButton::PowerUps => format!("Power-ups: {}", on_off(rules.power_ups)),
+            RuleButton::Mode => format!("Mode: {}", rules.mode.name()),
+            RuleButton::Controller(side) => format!(
+                "{}: {}",
+                side.name(),
+                rules.controllers[side.index()].label(*side)
+            ),
+            RuleButton::Partner(side) => format!(
+                "{} forward: {}",
+                side.name(),
+                rules.partners[side.index()].label(partner_keys(*side))
+            ),
+            RuleButton::Lives => format!("Lives: {}", rules.lives),
+            RuleButton::Drill => format!("Practice drill: {}", rules.drill),
+            RuleButton::TimeLimit => format!("Time attack: {} min", rules.time_limit),
+            RuleButton::Policy => format!(
+                "Computer: {}",
+                rules.policy.as_deref().unwrap_or("built-in")
+            ),
+        }
+    }
+
+    fn cycle(&self, rules: &mut MatchRules, drills: &Drills, policies: &Policies) {
+        let multi_ball = &mut rules.multi_ball;
+        match self {
+            RuleButton::MultiBall => multi_ball.enabled = !multi_ball.enabled,
+            RuleButton::HitsPerBall => {
+                multi_ball.hits_per_ball = next_value(&HITS_PER_BALL, multi_ball.hits_per_ball)
+            }
+            RuleButton::MaxBalls => {
+                multi_ball.max_balls = next_value(&MAX_BALLS, multi_ball.max_balls)
+            }
+            RuleButton::RoundEnd => {
+                multi_ball.round_end = match multi_ball.round_end {
+                    RoundEndRule::LastBall => RoundEndRule::EachGoal,
+                    RoundEndRule::EachGoal => RoundEndRule::LastBall,
+                }
+            }
+            RuleButton::PowerUps => rules.power_ups = !rules.power_ups,
+            RuleButton::Mode => rules.mode = next_value(&GameMode::ALL, rules.mode),
+            RuleButton::Controller(side) => {
+                let controller = &mut rules.controllers[side.index()];
+                *controller = next_value(&Controller::CHOICES, *controller)
+            }
+            RuleButton::Partner(side) => {
+                let controller = &mut rules.partners[side.index()];
+                *controller = next_value(&Controller::CHOICES, *controller)
+            }
+            RuleButton::Lives => rules.lives = next_value(&LIVES, rules.lives),
+            RuleButton::Drill => {
+                if let Some(drill) = drills.next_name(&rules.drill) {
+                    rules.drill = drill;
+                }
+            }
+            RuleButton::TimeLimit => rules.time_limit = next_value(&TIME_LIMITS, rules.time_limit),
+            RuleButton::Policy => rules.policy = policies.next_name(rules.policy.as_deref()),
+        }
+    }
+}
+
+#[derive(Component)]
+struct RulesMenu;
+
+fn setup_rules_menu(mut commands: Commands, rules: Res<MatchRules>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            RulesMenu,
+        ))
+        .with_children(|children| {
+            children.spawn((
+                Text::new("Match rules"),
+                TextFont {
+                    font_size: 60.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                ThemeRole::UiText,
+                Node {
+                    margin: UiRect::bottom(Val::Px(40.0)),
+                    ..default()
+                },
+            ));
+            children
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(40.0),
+                    ..default()
+                })
+                .with_children(|columns| {
+                    for buttons in [
+                        &RuleButton::MODE[..],
+                        &RuleButton::ALL[..],
+                        &RuleButton::PLAYERS[..],
+                    ] {
+                        columns
+                            .spawn(Node {
+                                flex_direction: FlexDirection::Column,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            })
+                            .with_children(|column| {
+                                for rule in buttons {
+                                    spawn_button(column, rule.label(&rules), 25.0, *rule);
+                                }
+                            });
+                    }
+                });
+            spawn_button(children, "Back", 30.0, ChangeState(GameState::Menu));
+        });
+}
+
+fn handle_rule_click(
+    mut rules: ResMut<MatchRules>,
+    drills: Drills,
+    policies: Res<Policies>,
+    interaction_query: Query<(&Interaction, &RuleButton, &Children), Changed<Interaction>>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, rule, children) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        rule.cycle(&mut rules, &drills, &policies);
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.0 = rule.label(&rules);
+        }
+    }
+}
+
+fn handle_rules_escape_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Menu);
+    }
+}
+
+fn cleanup_rules_menu(mut commands: Commands, menu: Query<Entity, With<RulesMenu>>) {
+    for entity in menu.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,383 @@
+use crate::crt::{CrtSettings, BLOOM_SUPPORTED};
+use crate::effects::EffectSettings;
+use crate::theme::{ThemeRole, Themes};
+use crate::ui::{next_value, on_off, spawn_button, ChangeState};
+use crate::{GameSet, GameState};
+use bevy::prelude::*;
+
+pub struct SettingsPlugin;
+
+/// This plugin holds the player facing settings and the screen to change them
+/// Other plugins read the [`Settings`] resource to adjust how they behave
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>()
+            .add_systems(OnEnter(GameState::Settings), setup_settings_menu)
+            .add_systems(
+                Update,
+                (handle_setting_click, handle_settings_escape_key)
+                    .in_set(GameSet::Input)
+                    .run_if(in_state(GameState::Settings)),
+            )
+            .add_systems(OnExit(GameState::Settings), cleanup_settings_menu);
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Settings {
+    pub touch: TouchSettings,
+    pub mouse: MouseSettings,
+    pub effects: EffectSettings,
+    pub display: DisplaySettings,
+    pub crt: CrtSettings,
+}
+
+pub struct TouchSettings {
+    // Distance (in world units) between a touch and its paddle within which the paddle holds still
+    pub deadzone: f32,
+    // Each half of the screen controls the paddle on that side, so two players can share a tablet
+    pub split_screen: bool,
+}
+
+impl Default for TouchSettings {
+    fn default() -> Self {
+        TouchSettings {
+            deadzone: 8.0,
+            split_screen: false,
+        }
+    }
+}
+
+pub struct MouseSettings {
+    // Steer the player paddle with the height of the mouse pointer
+    pub enabled: bool,
+    // Hide and lock the pointer while playing, moving the paddle by relative mouse motion instead
+    pub pointer_lock: bool,
+    pub sensitivity: f32,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        MouseSettings {
+            enabled: false,
+            pointer_lock: false,
+            sensitivity: 1.0,
+        }
+    }
+}
+
+pub struct DisplaySettings {
+    // Name of the active `Theme`
+    pub theme: String,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings {
+            theme: "Classic".to_string(),
+        }
+    }
+}
+
+const TOUCH_DEADZONES: [f32; 4] = [4.0, 8.0, 16.0, 32.0];
+const MOUSE_SENSITIVITIES: [f32; 5] = [0.5, 0.75, 1.0, 1.5, 2.0];
+const EFFECT_STRENGTHS: [f32; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+
+// The settings screen shows one column per section
+#[derive(Clone, Copy, PartialEq)]
+enum SettingSection {
+    Controls,
+    Effects,
+    Display,
+}
+
+impl SettingSection {
+    const ALL: [SettingSection; 3] = [
+        SettingSection::Controls,
+        SettingSection::Effects,
+        SettingSection::Display,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            SettingSection::Controls => "Controls",
+            SettingSection::Effects => "Effects",
+            SettingSection::Display => "Display",
+        }
+    }
+}
+
+// Each settings button cycles one setting through its possible values
+#[derive(Component, Clone, Copy)]
+enum SettingButton {
+    MouseControl,
+    PointerLock,
+    MouseSensitivity,
+    TouchSplitScreen,
+    TouchDeadzone,
+    Trail,
+    Particles,
+    ScreenShake,
+    PaddleFlash,
+    ReducedMotion,
+    Theme,
+    Crt,
+    Scanlines,
+    Curvature,
+    ChromaticAberration,
+    Bloom,
+}
+
+impl SettingButton {
+    const ALL: [SettingButton; 16] = [
+        SettingButton::MouseControl,
+        SettingButton::PointerLock,
+        SettingButton::MouseSensitivity,
+        SettingButton::TouchSplitScreen,
+        SettingButton::TouchDeadzone,
+        SettingButton::Trail,
+        SettingButton::Particles,
+        SettingButton::ScreenShake,
+        SettingButton::PaddleFlash,
+        SettingButton::ReducedMotion,
+        SettingButton::Theme,
+        SettingButton::Crt,
+        SettingButton::Scanlines,
+        SettingButton::Curvature,
+        SettingButton::ChromaticAberration,
+        SettingButton::Bloom,
+    ];
+
+    fn section(&self) -> SettingSection {
+        match self {
+            SettingButton::MouseControl
+            | SettingButton::PointerLock
+            | SettingButton::MouseSensitivity
+            | SettingButton::TouchSplitScreen
+            | SettingButton::TouchDeadzone => SettingSection::Controls,
+            SettingButton::Trail
+            | SettingButton::Particles
+            | SettingButton::ScreenShake
+            | SettingButton::PaddleFlash
+            | SettingButton::ReducedMotion => SettingSection::Effects,
+            SettingButton::Theme
+            | SettingButton::Crt
+            | SettingButton::Scanlines
+            | SettingButton::Curvature
+            | SettingButton::ChromaticAberration
+            | SettingButton::Bloom => SettingSection::Display,
+        }
+    }
+
+    // Settings for stages the platform cannot render are left off the screen
+    fn is_supported(&self) -> bool {
+        match self {
+            SettingButton::Bloom => BLOOM_SUPPORTED,
+            _ => true,
+        }
+    }
+
+    fn label(&self, settings: &Settings) -> String {
+        match self {
+            SettingButton::MouseControl => {
+                format!("Mouse control: {}", on_off(settings.mouse.enabled))
+            }
+            SettingButton::PointerLock => {
+                format!("Pointer lock: {}", on_off(settings.mouse.pointer_lock))
+            }
+            SettingButton::MouseSensitivity => {
+                format!("Mouse sensitivity: {}x", settings.mouse.sensitivity)
+            }
+            SettingButton::TouchSplitScreen => {
+                format!(
+                    "Touch split screen: {}",
+                    on_off(settings.touch.split_screen)
+                )
+            }
+            SettingButton::TouchDeadzone => format!("Touch deadzone: {}", settings.touch.deadzone),
+            SettingButton::Trail => format!("Ball trail: {}", on_off(settings.effects.trail)),
+            SettingButton::Particles => {
+                format!("Particles: {}", on_off(settings.effects.particles))
+            }
+            SettingButton::ScreenShake => {
+                format!("Screen shake: {}", on_off(settings.effects.screen_shake))
+            }
+            SettingButton::PaddleFlash => {
+                format!("Paddle flash: {}", on_off(settings.effects.paddle_flash))
+            }
+            SettingButton::ReducedMotion => {
+                format!(
+                    "Reduced motion: {}",
+                    on_off(settings.effects.reduced_motion)
+                )
+            }
+            SettingButton::Theme => format!("Theme: {}", settings.display.theme),
+            SettingButton::Crt => format!("CRT effect: {}", on_off(settings.crt.enabled)),
+            SettingButton::Scanlines => format!("Scanlines: {}", percent(settings.crt.scanlines)),
+            SettingButton::Curvature => format!("Curvature: {}", percent(settings.crt.curvature)),
+            SettingButton::ChromaticAberration => format!(
+                "Chromatic aberration: {}",
+                percent(settings.crt.chromatic_aberration)
+            ),
+            SettingButton::Bloom => format!("Bloom: {}", percent(settings.crt.bloom)),
+        }
+    }
+
+    fn cycle(&self, settings: &mut Settings, themes: &Themes) {
+        match self {
+            SettingButton::MouseControl => settings.mouse.enabled = !settings.mouse.enabled,
+            SettingButton::PointerLock => {
+                settings.mouse.pointer_lock = !settings.mouse.pointer_lock
+            }
+            SettingButton::MouseSensitivity => {
+                settings.mouse.sensitivity =
+                    next_value(&MOUSE_SENSITIVITIES, settings.mouse.sensitivity)
+            }
+            SettingButton::TouchSplitScreen => {
+                settings.touch.split_screen = !settings.touch.split_screen
+            }
+            SettingButton::TouchDeadzone => {
+                settings.touch.deadzone = next_value(&TOUCH_DEADZONES, settings.touch.deadzone)
+            }
+            SettingButton::Trail => settings.effects.trail = !settings.effects.trail,
+            SettingButton::Particles => settings.effects.particles = !settings.effects.particles,
+            SettingButton::ScreenShake => {
+                settings.effects.screen_shake = !settings.effects.screen_shake
+            }
+            SettingButton::PaddleFlash => {
+                settings.effects.paddle_flash = !settings.effects.paddle_flash
+            }
+            SettingButton::ReducedMotion => {
+                settings.effects.reduced_motion = !settings.effects.reduced_motion
+            }
+            SettingButton::Theme => {
+                if let Some(theme) = themes.next_name(&settings.display.theme) {
+                    settings.display.theme = theme;
+                }
+            }
+            SettingButton::Crt => settings.crt.enabled = !settings.crt.enabled,
+            SettingButton::Scanlines => {
+                settings.crt.scanlines = next_value(&EFFECT_STRENGTHS, settings.crt.scanlines)
+            }
+            SettingButton::Curvature => {
+                settings.crt.curvature = next_value(&EFFECT_STRENGTHS, settings.crt.curvature)
+            }
+            SettingButton::ChromaticAberration => {
+                settings.crt.chromatic_aberration =
+                    next_value(&EFFECT_STRENGTHS, settings.crt.chromatic_aberration)
+            }
+            SettingButton::Bloom => {
+                settings.crt.bloom = next_value(&EFFECT_STRENGTHS, settings.crt.bloom)
+            }
+        }
+    }
+}
+
+fn percent(value: f32) -> String {
+    format!("{}%", (value * 100.).round())
+}
+
+#[derive(Component)]
+struct SettingsMenu;
+
+fn setup_settings_menu(mut commands: Commands, settings: Res<Settings>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            SettingsMenu,
+        ))
+        .with_children(|children| {
+            children.spawn((
+                Text::new("Settings"),
+                TextFont {
+                    font_size: 60.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                ThemeRole::UiText,
+                Node {
+                    margin: UiRect::bottom(Val::Px(40.0)),
+                    ..default()
+                },
+            ));
+
+            children
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(40.0),
+                    margin: UiRect::bottom(Val::Px(20.0)),
+                    ..default()
+                })
+                .with_children(|columns| {
+                    for section in SettingSection::ALL {
+                        columns
+                            .spawn(Node {
+                                flex_direction: FlexDirection::Column,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            })
+                            .with_children(|column| {
+                                column.spawn((
+                                    Text::new(section.title()),
+                                    TextFont {
+                                        font_size: 30.0,
+                                        ..default()
+                                    },
+                                    TextColor(Color::WHITE),
+                                    Node {
+                                        margin: UiRect::bottom(Val::Px(20.0)),
+                                        ..default()
+                                    },
+                                ));
+                                for setting in SettingButton::ALL.into_iter().filter(|setting| {
+                                    setting.section() == section && setting.is_supported()
+                                }) {
+                                    spawn_button(column, setting.label(&settings), 25.0, setting);
+                                }
+                            });
+                    }
+                });
+
+            spawn_button(children, "Back", 30.0, ChangeState(GameState::Menu));
+        });
+}
+
+fn handle_setting_click(
+    mut settings: ResMut<Settings>,
+    themes: Themes,
+    interaction_query: Query<(&Interaction, &SettingButton, &Children), Changed<Interaction>>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, setting, children) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        setting.cycle(&mut settings, &themes);
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.0 = setting.label(&settings);
+        }
+    }
+}
+
+fn handle_settings_escape_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Menu);
+    }
+}
+
+fn cleanup_settings_menu(mut commands: Commands, menu: Query<Entity, With<SettingsMenu>>) {
+    for entity in menu.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+// Everything the game keeps between runs is written to this folder, relative to where the game was started.
+// Web builds have no file system, so there nothing is kept.
+const SAVE_DIR: &str = "saves";
+
+fn save_path(file_name: &str) -> PathBuf {
+    PathBuf::from(SAVE_DIR).join(file_name)
+}
+
+/// Writes `contents` to the file `file_name` in the save folder, returning where it was written
+pub fn write_file(file_name: &str, contents: &str) -> std::io::Result<PathBuf> {
+    let path = save_path(file_name);
+    fs::create_dir_all(SAVE_DIR)?;
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// Keeps `value` as `<name>.json` in the save folder, logging instead of failing if that is not possible
+pub fn save_json<T: Serialize>(name: &str, value: &T) {
+    let result = serde_json::to_string_pretty(value)
+        .map_err(std::io::Error::from)
+        .and_then(|json| write_file(&format!("{name}.json"), &json));
+    if let Err(error) = result {
+        warn!("Failed to save {name}: {error}");
+    }
+}
+
+/// Reads back what [`save_json`] kept under `name`, if it exists and can still be read
+pub fn load_json<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let json = fs::read_to_string(save_path(&format!("{name}.json"))).ok()?;
+    serde_json::from_str(&json)
+        .inspect_err(|error| warn!("Failed to load {name}: {error}"))
+        .ok()
+}
+
+/// Removes what [`save_json`] kept under `name`
+pub fn delete_json(name: &str) {
+    // Nothing saved is nothing to remove
+    let _ = fs::remove_file(save_path(&format!("{name}.json")));
+}
@@ -0,0 +1,202 @@
+use crate::events::{BallHitPaddle, Goal, MatchEnded};
+use crate::game::{handle_round_end, move_ball, AiSkill, Ball, PaddleSide, PlayingState, Velocity};
+use crate::rules::{GameMode, MatchRules};
+use crate::theme::ThemeRole;
+use crate::ui::{format_clock, spawn_end_screen};
+use crate::{GameSet, GameState};
+use bevy::prelude::*;
+
+pub struct SurvivalPlugin;
+
+/// This plugin runs survival matches: the player has a single life against a computer that keeps getting
+/// faster, while every ball speeds up the longer the match goes on.
+/// How long the player lasted and how many balls they returned is shown once they concede.
+impl Plugin for SurvivalPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SurvivalRun>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                start_survival.run_if(is_survival),
+            )
+            .add_systems(
+                Update,
+                (
+                    speed_up_balls.before(move_ball).in_set(GameSet::Physics),
+                    run_survival
+                        .after(handle_round_end)
+                        .in_set(GameSet::Scoring),
+                    update_survival_text.in_set(GameSet::Presentation),
+                )
+                    .run_if(is_survival)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PlayingState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), cleanup_survival);
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SurvivalRun {
+    elapsed: f32,
+    returns: u32,
+}
+
+// Speed the computer paddle gains every second, up to the speed of the player's paddle
+const AI_IMPROVEMENT: f32 = 3.0;
+const MAX_AI_SPEED: f32 = 500.0;
+// Speed every ball gains every second, which balls served later start with
+const BALL_ACCELERATION: f32 = 4.0;
+
+#[derive(Component)]
+struct SurvivalText;
+
+// Everything spawned for a survival match, removed when the match is left
+#[derive(Component)]
+struct SurvivalEntity;
+
+fn is_survival(rules: Res<MatchRules>) -> bool {
+    rules.mode == GameMode::Survival
+}
+
+fn start_survival(mut commands: Commands, mut run: ResMut<SurvivalRun>) {
+    *run = SurvivalRun::default();
+    commands.spawn((
+        Text::new(survival_label(&run)),
+        TextFont {
+            font_size: 40.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        ThemeRole::UiText,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            left: Val::Px(20.0),
+            ..default()
+        },
+        SurvivalText,
+        SurvivalEntity,
+    ));
+}
+
+fn speed_up_balls(
+    time: Res<Time>,
+    run: Res<SurvivalRun>,
+    mut ball_query: Query<(&mut Velocity, Ref<Ball>)>,
+) {
+    for (mut velocity, ball) in &mut ball_query {
+        velocity.speed += if ball.is_added() {
+            BALL_ACCELERATION * run.elapsed
+        } else {
+            BALL_ACCELERATION * time.delta_secs()
+        };
+    }
+}
+
+// Runs after the round was reset, so a lost match ends up over instead of paused
+#[allow(clippy::too_many_arguments)]
+fn run_survival(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut run: ResMut<SurvivalRun>,
+    mut ai_skill: ResMut<AiSkill>,
+    mut paddle_hit_events: EventReader<BallHitPaddle>,
+    mut goal_events: EventReader<Goal>,
+    mut match_ended_events: EventWriter<MatchEnded>,
+    mut next_state: ResMut<NextState<PlayingState>>,
+) {
+    run.elapsed += time.delta_secs();
+    let base_speed = AiSkill::default().personality.speed;
+    ai_skill.personality.speed = (base_speed + AI_IMPROVEMENT * run.elapsed).min(MAX_AI_SPEED);
+
+    for event in paddle_hit_events.read() {
+        if event.side == PaddleSide::Left {
+            run.returns += 1;
+        }
+    }
+
+    if !goal_events
+        .read()
+        .any(|goal| goal.conceded == PaddleSide::Left)
+    {
+        return;
+    }
+    next_state.set(PlayingState::Over);
+    // Survival is played for time, nobody wins it
+    match_ended_events.send(MatchEnded {
+        winner: None,
+        points: [0; 4],
+    });
+    spawn_end_screen(
+        &mut commands,
+        format!("Survived {}", format_clock(run.elapsed)),
+        vec![format!("Returns: {}", run.returns)],
+        SurvivalEntity,
+    );
+}
+
+fn survival_label(run: &SurvivalRun) -> String {
+    format!(
+        "Time {}  Returns {}",
+        format_clock(run.elapsed),
+        run.returns
+    )
+}
+
+fn update_survival_text(
+    run: Res<SurvivalRun>,
+    mut text_query: Query<&mut Text, With<SurvivalText>>,
+) {
+    for mut text in &mut text_query {
+        text.0 = survival_label(&run);
+    }
+}
+
+fn cleanup_survival(mut commands: Commands, survival_query: Query<Entity, With<SurvivalEntity>>) {
+    for entity in survival_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    #[test]
+    fn balls_and_the_computer_speed_up_over_time() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                0.25,
+            )))
+            .add_event::<BallHitPaddle>()
+            .add_event::<Goal>()
+            .add_event::<MatchEnded>()
+            .init_resource::<NextState<PlayingState>>()
+            .init_resource::<AiSkill>()
+            .insert_resource(SurvivalRun {
+                elapsed: 200.,
+                returns: 0,
+            })
+            .add_systems(Update, (speed_up_balls, run_survival).chain());
+        let velocity = Velocity {
+            direction: Vec2::X,
+            speed: 100.,
+        };
+        // A ball served late in the match starts as fast as the ones already in play
+        let ball = app.world_mut().spawn((Ball, velocity)).id();
+        app.update();
+        let speed = app.world().get::<Velocity>(ball).unwrap().speed;
+        assert_eq!(speed, 100. + BALL_ACCELERATION * 200.);
+        app.update();
+        let speed = app.world().get::<Velocity>(ball).unwrap().speed - speed;
+        assert_eq!(speed, BALL_ACCELERATION * 0.25);
+        // The computer has long reached the speed of the player
+        assert_eq!(
+            app.world().resource::<AiSkill>().personality.speed,
+            MAX_AI_SPEED
+        );
+    }
+}
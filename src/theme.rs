@@ -0,0 +1,179 @@
+use crate::data::{hex_color, RonAssetAppExt};
+use crate::loading::ThemeAssets;
+use crate::settings::Settings;
+use crate::GameSet;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+pub struct ThemePlugin;
+
+/// This plugin colors the game according to the [`Theme`] picked in the [`Settings`]
+/// Entities opt in with a [`ThemeRole`], and are recolored live whenever the settings change
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_ron_asset::<Theme>(&["theme.ron"])
+            // Shown while the themes are still loading
+            .insert_resource(ClearColor(Color::linear_rgb(0.05, 0.05, 0.05)))
+            .add_systems(
+                Update,
+                apply_theme
+                    .in_set(GameSet::Presentation)
+                    .run_if(resource_exists::<ThemeAssets>),
+            );
+    }
+}
+
+/// A color palette for the whole game, loaded from `assets/themes/*.theme.ron`
+#[derive(Asset, TypePath, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    #[serde(deserialize_with = "hex_color")]
+    pub background: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub court_line: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub paddle: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub ball: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub ui_text: Color,
+    // Optional textures (relative to `assets`), tinted with the colors above
+    #[serde(default)]
+    pub ball_texture: Option<String>,
+    #[serde(default)]
+    pub paddle_texture: Option<String>,
+}
+
+impl Theme {
+    fn color(&self, role: ThemeRole) -> Color {
+        match role {
+            ThemeRole::CourtLine => self.court_line,
+            ThemeRole::Paddle => self.paddle,
+            ThemeRole::Ball => self.ball,
+            ThemeRole::UiText => self.ui_text,
+        }
+    }
+
+    fn texture(&self, role: ThemeRole) -> Option<&str> {
+        match role {
+            ThemeRole::Paddle => self.paddle_texture.as_deref(),
+            ThemeRole::Ball => self.ball_texture.as_deref(),
+            ThemeRole::CourtLine | ThemeRole::UiText => None,
+        }
+    }
+}
+
+// Which of the theme colors an entity's `Sprite` or `TextColor` takes
+#[derive(Component, Clone, Copy)]
+pub enum ThemeRole {
+    CourtLine,
+    Paddle,
+    Ball,
+    UiText,
+}
+
+/// All loaded themes, in the order they are offered in the settings
+#[derive(SystemParam)]
+pub struct Themes<'w> {
+    assets: Res<'w, Assets<Theme>>,
+    collection: Res<'w, ThemeAssets>,
+}
+
+impl Themes<'_> {
+    fn iter(&self) -> impl Iterator<Item = &Theme> {
+        self.collection
+            .themes
+            .iter()
+            .filter_map(|handle| self.assets.get(handle))
+    }
+
+    // The theme picked in the settings, falling back to the first one if it is unknown
+    pub fn active(&self, settings: &Settings) -> Option<&Theme> {
+        self.iter()
+            .find(|theme| theme.name == settings.display.theme)
+            .or_else(|| self.iter().next())
+    }
+
+    // The name of the theme after `current`, wrapping around to the first
+    pub fn next_name(&self, current: &str) -> Option<String> {
+        let names: Vec<&str> = self.iter().map(|theme| theme.name.as_str()).collect();
+        let next = names
+            .iter()
+            .position(|name| *name == current)
+            .map_or(0, |index| (index + 1) % names.len());
+        names.get(next).map(|name| name.to_string())
+    }
+}
+
+fn apply_theme(
+    settings: Res<Settings>,
+    themes: Themes,
+    asset_server: Res<AssetServer>,
+    mut clear_color: ResMut<ClearColor>,
+    mut sprite_query: Query<(Ref<ThemeRole>, &mut Sprite)>,
+    mut text_query: Query<(Ref<ThemeRole>, &mut TextColor)>,
+) {
+    let Some(theme) = themes.active(&settings) else {
+        return;
+    };
+    // Newly spawned entities are always themed, everything else only when the settings change
+    let restyle_all = settings.is_changed();
+    if restyle_all {
+        clear_color.0 = theme.background;
+    }
+    for (role, mut sprite) in &mut sprite_query {
+        if restyle_all || role.is_added() {
+            sprite.color = theme.color(*role);
+            sprite.image = theme
+                .texture(*role)
+                .map_or_else(Handle::default, |path| asset_server.load(path));
+        }
+    }
+    for (role, mut text_color) in &mut text_query {
+        if restyle_all || role.is_added() {
+            text_color.0 = theme.color(*role);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::read_ron_assets;
+    use crate::settings::DisplaySettings;
+
+    #[test]
+    fn every_theme_loads_and_the_default_one_exists() {
+        let themes = read_ron_assets::<Theme>("themes", "theme.ron").unwrap();
+        assert!(themes
+            .iter()
+            .any(|(_, theme)| theme.name == DisplaySettings::default().theme));
+        for (index, (_, theme)) in themes.iter().enumerate() {
+            assert!(
+                themes[..index]
+                    .iter()
+                    .all(|(_, other)| other.name != theme.name),
+                "two themes are called {}",
+                theme.name
+            );
+        }
+    }
+
+    #[test]
+    fn colors_are_read_from_hex() {
+        let theme: Theme = ron::de::from_str(
+            r##"(name: "Test", background: "#000000", court_line: "#ffffff", paddle: "#ff0000",
+                ball: "#00ff00", ui_text: "#0000ff", ball_texture: Some("ball.png"))"##,
+        )
+        .unwrap();
+        assert_eq!(theme.color(ThemeRole::Paddle), Color::srgb(1., 0., 0.));
+        assert_eq!(theme.texture(ThemeRole::Ball), Some("ball.png"));
+        assert_eq!(theme.texture(ThemeRole::Paddle), None);
+        assert!(ron::de::from_str::<Theme>(
+            r##"(name: "Test", background: "black", court_line: "#ffffff", paddle: "#ff0000",
+                ball: "#00ff00", ui_text: "#0000ff")"##,
+        )
+        .is_err());
+    }
+}
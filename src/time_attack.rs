@@ -0,0 +1,221 @@
+use crate::events::{Goal, MatchEnded};
+use crate::game::{decided_winner, handle_round_end, PaddleSide, PlayingState};
+use crate::rules::{GameMode, MatchRules};
+use crate::theme::ThemeRole;
+use crate::ui::{format_clock, spawn_end_screen};
+use crate::{GameSet, GameState};
+use bevy::prelude::*;
+
+pub struct TimeAttackPlugin;
+
+/// This plugin runs time attack matches: the side with the most points when the time limit from the
+/// [`MatchRules`] runs out wins. On a tie the match goes into sudden death, and the next goal decides it.
+impl Plugin for TimeAttackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeAttack>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                start_time_attack.run_if(is_time_attack),
+            )
+            .add_systems(
+                Update,
+                (
+                    run_time_attack
+                        .after(handle_round_end)
+                        .in_set(GameSet::Scoring),
+                    update_time_attack_text.in_set(GameSet::Presentation),
+                )
+                    .run_if(is_time_attack)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PlayingState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), cleanup_time_attack);
+    }
+}
+
+// The clock and the points of the current match, which start from zero unlike the `Score`
+#[derive(Resource, Default)]
+pub struct TimeAttack {
+    remaining: f32,
+    points: [u32; 2],
+    sudden_death: bool,
+}
+
+impl TimeAttack {
+    fn points(&self, side: PaddleSide) -> u32 {
+        self.points[side.index()]
+    }
+}
+
+#[derive(Component)]
+struct TimeAttackText;
+
+// Everything spawned for a time attack match, removed when the match is left
+#[derive(Component)]
+struct TimeAttackEntity;
+
+fn is_time_attack(rules: Res<MatchRules>) -> bool {
+    rules.mode == GameMode::TimeAttack
+}
+
+fn start_time_attack(
+    mut commands: Commands,
+    rules: Res<MatchRules>,
+    mut time_attack: ResMut<TimeAttack>,
+) {
+    *time_attack = TimeAttack {
+        remaining: rules.time_limit as f32 * 60.0,
+        points: [PaddleSide::Left, PaddleSide::Right]
+            .map(|side| rules.handicaps[side.index()].head_start),
+        ..default()
+    };
+    commands.spawn((
+        Text::new(time_attack_label(&time_attack)),
+        TextFont {
+            font_size: 40.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        ThemeRole::UiText,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            left: Val::Px(20.0),
+            ..default()
+        },
+        TimeAttackText,
+        TimeAttackEntity,
+    ));
+}
+
+// Counts the points of the match down to the end of the clock, when the side ahead wins
+fn run_time_attack(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut time_attack: ResMut<TimeAttack>,
+    mut goal_events: EventReader<Goal>,
+    mut match_ended_events: EventWriter<MatchEnded>,
+    mut next_state: ResMut<NextState<PlayingState>>,
+) {
+    for goal in goal_events.read() {
+        if let Some(scorer) = goal.scorer {
+            time_attack.points[scorer.index()] += 1;
+        }
+    }
+    time_attack.remaining = (time_attack.remaining - time.delta_secs()).max(0.);
+    if time_attack.remaining > 0. {
+        return;
+    }
+
+    // Any lead decides the match once the time is up
+    let Some(winner) = decided_winner(
+        &time_attack.points,
+        &[PaddleSide::Left, PaddleSide::Right],
+        0,
+    ) else {
+        time_attack.sudden_death = true;
+        return;
+    };
+    next_state.set(PlayingState::Over);
+    let left = time_attack.points(PaddleSide::Left);
+    let right = time_attack.points(PaddleSide::Right);
+    match_ended_events.send(MatchEnded {
+        winner: Some(winner),
+        points: [left, right, 0, 0],
+    });
+    let mut details = vec![format!("{left} - {right}")];
+    if time_attack.sudden_death {
+        details.push("Decided in sudden death".to_string());
+    }
+    spawn_end_screen(
+        &mut commands,
+        format!("{} wins!", winner.name()),
+        details,
+        TimeAttackEntity,
+    );
+}
+
+fn time_attack_label(time_attack: &TimeAttack) -> String {
+    let clock = if time_attack.sudden_death {
+        "Sudden death".to_string()
+    } else {
+        format_clock(time_attack.remaining)
+    };
+    format!(
+        "Left {} - {} Right   {}",
+        time_attack.points(PaddleSide::Left),
+        time_attack.points(PaddleSide::Right),
+        clock
+    )
+}
+
+fn update_time_attack_text(
+    time_attack: Res<TimeAttack>,
+    mut text_query: Query<&mut Text, With<TimeAttackText>>,
+) {
+    for mut text in &mut text_query {
+        text.0 = time_attack_label(&time_attack);
+    }
+}
+
+fn cleanup_time_attack(
+    mut commands: Commands,
+    time_attack_query: Query<Entity, With<TimeAttackEntity>>,
+) {
+    for entity in time_attack_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn goal_for(app: &mut App, scorer: PaddleSide) {
+        app.world_mut().send_event(Goal {
+            ball: Entity::PLACEHOLDER,
+            conceded: scorer.opposite(),
+            scorer: Some(scorer),
+        });
+    }
+
+    fn ended(app: &App) -> Vec<MatchEnded> {
+        let events = app.world().resource::<Events<MatchEnded>>();
+        let mut cursor = events.get_cursor();
+        cursor.read(events).cloned().collect()
+    }
+
+    #[test]
+    fn a_tie_at_the_end_of_the_clock_goes_to_sudden_death() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                0.25,
+            )))
+            .add_event::<Goal>()
+            .add_event::<MatchEnded>()
+            .init_resource::<NextState<PlayingState>>()
+            .insert_resource(TimeAttack {
+                remaining: 0.5,
+                // A head start counts like any other point
+                points: [1, 0],
+                ..default()
+            })
+            .add_systems(Update, run_time_attack);
+        goal_for(&mut app, PaddleSide::Right);
+        for _ in 0..4 {
+            app.update();
+        }
+        assert!(app.world().resource::<TimeAttack>().sudden_death);
+        assert!(ended(&app).is_empty());
+
+        goal_for(&mut app, PaddleSide::Right);
+        app.update();
+        let ended = ended(&app);
+        assert_eq!(ended.len(), 1);
+        assert_eq!(ended[0].winner, Some(PaddleSide::Right));
+        assert_eq!(ended[0].points, [1, 2, 0, 0]);
+    }
+}
@@ -0,0 +1,992 @@
+use crate::actions::Controller;
+use crate::events::MatchEnded;
+use crate::game::{
+    decided_winner, handle_round_end, MatchExit, MatchStats, PaddleSide, PlayingState, Score,
+};
+use crate::handicap::Handicap;
+use crate::rules::{GameMode, MatchRules};
+use crate::storage::{delete_json, load_json, save_json, write_file};
+use crate::theme::ThemeRole;
+use crate::ui::{next_value, spawn_button, spawn_end_screen};
+use crate::{GameSet, GameState};
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::mem;
+
+pub struct TournamentPlugin;
+
+/// This plugin runs office tournaments: players and computer entrants are entered on the tournament screen,
+/// which then shows the bracket or round-robin schedule between matches and the final standings at the end.
+/// Matches are played with the [`MatchRules`] of the menu, to the number of points the tournament sets and
+/// without the handicaps of the setup screen, which go by screen side rather than by entrant.
+/// The [`Tournament`] is saved after every change, so it survives restarting the game.
+impl Plugin for TournamentPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_json::<Tournament>(SAVE_NAME).unwrap_or_default())
+            .init_resource::<TournamentSession>()
+            .add_systems(
+                Update,
+                (
+                    handle_tournament_escape_key,
+                    handle_name_input,
+                    handle_tournament_click,
+                    refresh_tournament_screen,
+                )
+                    .chain()
+                    .in_set(GameSet::Input)
+                    .run_if(in_state(GameState::Tournament)),
+            )
+            .add_systems(OnExit(GameState::Tournament), cleanup_tournament_screen)
+            .add_systems(
+                Update,
+                finish_fixture
+                    .after(handle_round_end)
+                    .in_set(GameSet::Scoring)
+                    .run_if(|session: Res<TournamentSession>| session.playing.is_some())
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PlayingState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), cleanup_fixture);
+    }
+}
+
+const SAVE_NAME: &str = "tournament";
+const MAX_ENTRANTS: usize = 16;
+const MAX_NAME_LENGTH: usize = 16;
+const POINTS_TO_WIN: [u32; 4] = [3, 5, 7, 11];
+
+#[derive(Resource, Serialize, Deserialize)]
+pub struct Tournament {
+    pub format: TournamentFormat,
+    // Points a match is played to
+    pub points_to_win: u32,
+    pub entrants: Vec<Entrant>,
+    // The fixtures of each round, empty until the tournament is started
+    pub rounds: Vec<Vec<Fixture>>,
+}
+
+impl Default for Tournament {
+    fn default() -> Self {
+        Tournament {
+            format: TournamentFormat::Knockout,
+            points_to_win: 5,
+            entrants: Vec::new(),
+            rounds: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TournamentFormat {
+    // Single elimination, the winner of each match going on to the next round
+    Knockout,
+    // Everyone plays everyone once, and the most wins take the tournament
+    RoundRobin,
+}
+
+impl TournamentFormat {
+    const ALL: [TournamentFormat; 2] = [TournamentFormat::Knockout, TournamentFormat::RoundRobin];
+
+    fn name(&self) -> &'static str {
+        match self {
+            TournamentFormat::Knockout => "knockout",
+            TournamentFormat::RoundRobin => "round robin",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entrant {
+    pub name: String,
+    // Played by the computer instead of a person
+    pub computer: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Fixture {
+    // Indices into the entrants. In a knockout a missing entrant is either a bye in the first round,
+    // or the winner of a match in the round before that has not been played yet.
+    pub entrants: [Option<usize>; 2],
+    // Points of both entrants, once played
+    pub result: Option<[u32; 2]>,
+}
+
+/// An entrant's line in the standings table
+#[derive(Serialize)]
+pub struct Standing {
+    pub name: String,
+    pub played: u32,
+    pub won: u32,
+    pub lost: u32,
+    pub points_for: u32,
+    pub points_against: u32,
+}
+
+impl Tournament {
+    fn is_started(&self) -> bool {
+        !self.rounds.is_empty()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.is_started() && self.next_fixture().is_none()
+    }
+
+    fn start(&mut self) {
+        self.rounds = match self.format {
+            TournamentFormat::Knockout => knockout_rounds(self.entrants.len()),
+            TournamentFormat::RoundRobin => round_robin_rounds(self.entrants.len()),
+        };
+        self.fill_bracket();
+    }
+
+    // The first fixture, in the order of the schedule, that is ready to be played
+    fn next_fixture(&self) -> Option<(usize, usize)> {
+        self.rounds
+            .iter()
+            .enumerate()
+            .find_map(|(round, fixtures)| {
+                fixtures
+                    .iter()
+                    .position(|fixture| {
+                        fixture.result.is_none() && fixture.entrants.iter().all(Option::is_some)
+                    })
+                    .map(|index| (round, index))
+            })
+    }
+
+    fn winner(&self, round: usize, index: usize) -> Option<usize> {
+        let fixture = &self.rounds[round][index];
+        match (fixture.entrants, fixture.result) {
+            ([Some(left), Some(right)], Some([left_points, right_points])) => {
+                Some(if left_points > right_points {
+                    left
+                } else {
+                    right
+                })
+            }
+            // Only the first round of a knockout has byes
+            ([Some(entrant), None], _) | ([None, Some(entrant)], _) if round == 0 => Some(entrant),
+            _ => None,
+        }
+    }
+
+    // Moves the winners of a knockout into the fixtures of the next round
+    fn fill_bracket(&mut self) {
+        if self.format != TournamentFormat::Knockout {
+            return;
+        }
+        for round in 1..self.rounds.len() {
+            for index in 0..self.rounds[round].len() {
+                let entrants = [
+                    self.winner(round - 1, index * 2),
+                    self.winner(round - 1, index * 2 + 1),
+                ];
+                self.rounds[round][index].entrants = entrants;
+            }
+        }
+    }
+
+    fn record(&mut self, round: usize, index: usize, points: [u32; 2]) {
+        self.rounds[round][index].result = Some(points);
+        self.fill_bracket();
+    }
+
+    fn champion(&self) -> Option<String> {
+        match self.format {
+            TournamentFormat::Knockout => self
+                .winner(self.rounds.len().checked_sub(1)?, 0)
+                .map(|entrant| self.entrants[entrant].name.clone()),
+            TournamentFormat::RoundRobin => self
+                .is_finished()
+                .then(|| self.standings().into_iter().next())
+                .flatten()
+                .map(|standing| standing.name),
+        }
+    }
+
+    // Most wins first, then the best point difference
+    fn standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = self
+            .entrants
+            .iter()
+            .map(|entrant| Standing {
+                name: entrant.name.clone(),
+                played: 0,
+                won: 0,
+                lost: 0,
+                points_for: 0,
+                points_against: 0,
+            })
+            .collect();
+        for fixture in self.rounds.iter().flatten() {
+            let ([Some(left), Some(right)], Some(points)) = (fixture.entrants, fixture.result)
+            else {
+                continue;
+            };
+            for (entrant, own, other) in
+                [(left, points[0], points[1]), (right, points[1], points[0])]
+            {
+                let standing = &mut standings[entrant];
+                standing.played += 1;
+                standing.points_for += own;
+                standing.points_against += other;
+                if own > other {
+                    standing.won += 1;
+                } else {
+                    standing.lost += 1;
+                }
+            }
+        }
+        standings.sort_by_key(|standing| {
+            (
+                std::cmp::Reverse(standing.won),
+                std::cmp::Reverse(standing.points_for as i64 - standing.points_against as i64),
+            )
+        });
+        standings
+    }
+
+    fn entrant_name(&self, entrant: Option<usize>) -> &str {
+        entrant.map_or("?", |entrant| self.entrants[entrant].name.as_str())
+    }
+
+    fn fixture_label(&self, round: usize, fixture: &Fixture) -> String {
+        let [left, right] = fixture.entrants;
+        match fixture.result {
+            Some([left_points, right_points]) => format!(
+                "{} {} - {} {}",
+                self.entrant_name(left),
+                left_points,
+                right_points,
+                self.entrant_name(right)
+            ),
+            None if round == 0 && (left.is_none() || right.is_none()) => {
+                format!("{} (bye)", self.entrant_name(left.or(right)))
+            }
+            None => format!(
+                "{} vs {}",
+                self.entrant_name(left),
+                self.entrant_name(right)
+            ),
+        }
+    }
+
+    fn round_title(&self, round: usize) -> String {
+        let remaining = self.rounds.len() - round;
+        match (self.format, remaining) {
+            (TournamentFormat::Knockout, 1) => "Final".to_string(),
+            (TournamentFormat::Knockout, 2) => "Semi-finals".to_string(),
+            _ => format!("Round {}", round + 1),
+        }
+    }
+}
+
+// A bracket as large as the next power of two, with the byes going to the first entrants
+fn knockout_rounds(entrants: usize) -> Vec<Vec<Fixture>> {
+    let size = entrants.next_power_of_two();
+    let seed = |index: usize| (index < entrants).then_some(index);
+    let mut rounds = vec![(0..size / 2)
+        .map(|index| Fixture {
+            entrants: [seed(index), seed(size - 1 - index)],
+            result: None,
+        })
+        .collect::<Vec<_>>()];
+    let mut fixtures = size / 4;
+    while fixtures > 0 {
+        rounds.push(vec![Fixture::default(); fixtures]);
+        fixtures /= 2;
+    }
+    rounds
+}
+
+// The circle method: everyone but the first entrant moves one place each round,
+// and with an odd number of entrants whoever is paired with the empty place sits the round out
+fn round_robin_rounds(entrants: usize) -> Vec<Vec<Fixture>> {
+    let mut places: Vec<Option<usize>> = (0..entrants).map(Some).collect();
+    if places.len() % 2 == 1 {
+        places.push(None);
+    }
+    let count = places.len();
+    let mut rounds = Vec::new();
+    for _ in 1..count {
+        rounds.push(
+            (0..count / 2)
+                .filter_map(|index| match (places[index], places[count - 1 - index]) {
+                    (Some(left), Some(right)) => Some(Fixture {
+                        entrants: [Some(left), Some(right)],
+                        result: None,
+                    }),
+                    _ => None,
+                })
+                .collect(),
+        );
+        places[1..].rotate_right(1);
+    }
+    rounds
+}
+
+fn standings_csv(standings: &[Standing]) -> String {
+    let mut csv = "rank,name,played,won,lost,points_for,points_against\n".to_string();
+    for (rank, standing) in standings.iter().enumerate() {
+        csv.push_str(&format!(
+            "{},\"{}\",{},{},{},{},{}\n",
+            rank + 1,
+            standing.name.replace('"', "\"\""),
+            standing.played,
+            standing.won,
+            standing.lost,
+            standing.points_for,
+            standing.points_against
+        ));
+    }
+    csv
+}
+
+fn save_tournament(tournament: &Tournament) {
+    if tournament.entrants.is_empty() {
+        delete_json(SAVE_NAME);
+    } else {
+        save_json(SAVE_NAME, tournament);
+    }
+}
+
+// What only matters while the game is running
+#[derive(Resource, Default)]
+struct TournamentSession {
+    // The name being typed for a new player, while the name field is open
+    name_input: Option<String>,
+    playing: Option<PlayingFixture>,
+    // The match rules and score from before the tournament took them over, given back once it is left
+    saved_rules: Option<MatchRules>,
+    saved_score: Option<Score>,
+    // Where the last export went, or why it failed
+    message: Option<String>,
+}
+
+struct PlayingFixture {
+    round: usize,
+    index: usize,
+    // The first entrant of the fixture plays on the right, so a person against the computer gets the left
+    swapped: bool,
+}
+
+#[derive(Component, Clone, Copy)]
+enum TournamentButton {
+    AddPlayer,
+    AddComputer,
+    RemoveEntrant,
+    Format,
+    PointsToWin,
+    Start,
+    PlayNext,
+    ExportCsv,
+    ExportJson,
+    // Clears the schedule, keeping the entrants for another go
+    Restart,
+    Back,
+}
+
+#[derive(Component)]
+struct TournamentScreen;
+
+// The end screen of a tournament match
+#[derive(Component)]
+struct FixtureEntity;
+
+fn refresh_tournament_screen(
+    mut commands: Commands,
+    tournament: Res<Tournament>,
+    session: Res<TournamentSession>,
+    screen_query: Query<Entity, With<TournamentScreen>>,
+) {
+    if !tournament.is_changed() && !session.is_changed() && !screen_query.is_empty() {
+        return;
+    }
+    for entity in &screen_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            TournamentScreen,
+        ))
+        .with_children(|children| {
+            spawn_text(children, "Tournament", 60.0);
+            if tournament.is_started() {
+                spawn_schedule(children, &tournament);
+            } else {
+                spawn_entry(children, &tournament, &session);
+            }
+            if let Some(message) = &session.message {
+                spawn_text(children, message.clone(), 20.0);
+            }
+        });
+}
+
+fn spawn_text(parent: &mut ChildBuilder, text: impl Into<String>, font_size: f32) {
+    parent.spawn((
+        Text::new(text),
+        TextFont {
+            font_size,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        ThemeRole::UiText,
+        Node {
+            margin: UiRect::bottom(Val::Px(10.0)),
+            ..default()
+        },
+    ));
+}
+
+fn spawn_row(parent: &mut ChildBuilder, buttons: &[(TournamentButton, String)]) {
+    parent
+        .spawn(Node {
+            flex_direction: FlexDirection::Row,
+            column_gap: Val::Px(20.0),
+            ..default()
+        })
+        .with_children(|row| {
+            for (button, label) in buttons {
+                spawn_button(row, label.clone(), 25.0, *button);
+            }
+        });
+}
+
+// Before the start: the entrants and how the tournament is played
+fn spawn_entry(parent: &mut ChildBuilder, tournament: &Tournament, session: &TournamentSession) {
+    if tournament.entrants.is_empty() {
+        spawn_text(parent, "Add players and computers to enter", 25.0);
+    }
+    for (number, entrant) in tournament.entrants.iter().enumerate() {
+        let kind = if entrant.computer { " (computer)" } else { "" };
+        spawn_text(
+            parent,
+            format!("{}. {}{}", number + 1, entrant.name, kind),
+            25.0,
+        );
+    }
+    if let Some(name) = &session.name_input {
+        spawn_text(
+            parent,
+            format!("Name: {name}_   (Enter to add, Esc to cancel)"),
+            25.0,
+        );
+    }
+
+    spawn_row(
+        parent,
+        &[
+            (TournamentButton::AddPlayer, "Add player".to_string()),
+            (TournamentButton::AddComputer, "Add computer".to_string()),
+            (TournamentButton::RemoveEntrant, "Remove last".to_string()),
+        ],
+    );
+    spawn_row(
+        parent,
+        &[
+            (
+                TournamentButton::Format,
+                format!("Format: {}", tournament.format.name()),
+            ),
+            (
+                TournamentButton::PointsToWin,
+                format!("Play to {} points", tournament.points_to_win),
+            ),
+        ],
+    );
+    let mut last_row = vec![(TournamentButton::Back, "Back".to_string())];
+    if tournament.entrants.len() >= 2 {
+        last_row.insert(0, (TournamentButton::Start, "Start".to_string()));
+    }
+    spawn_row(parent, &last_row);
+}
+
+// After the start: the schedule with its results, and the standings once it is over
+fn spawn_schedule(parent: &mut ChildBuilder, tournament: &Tournament) {
+    parent
+        .spawn(Node {
+            flex_direction: FlexDirection::Row,
+            column_gap: Val::Px(30.0),
+            margin: UiRect::bottom(Val::Px(20.0)),
+            ..default()
+        })
+        .with_children(|columns| {
+            for (round, fixtures) in tournament.rounds.iter().enumerate() {
+                columns
+                    .spawn(Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    })
+                    .with_children(|column| {
+                        spawn_text(column, tournament.round_title(round), 25.0);
+                        for fixture in fixtures {
+                            spawn_text(column, tournament.fixture_label(round, fixture), 20.0);
+                        }
+                    });
+            }
+        });
+
+    if tournament.is_finished() {
+        if let Some(champion) = tournament.champion() {
+            spawn_text(parent, format!("Champion: {champion}"), 40.0);
+        }
+        for (rank, standing) in tournament.standings().iter().enumerate() {
+            spawn_text(
+                parent,
+                format!(
+                    "{}. {}  played {}  won {}  lost {}  points {}-{}",
+                    rank + 1,
+                    standing.name,
+                    standing.played,
+                    standing.won,
+                    standing.lost,
+                    standing.points_for,
+                    standing.points_against
+                ),
+                20.0,
+            );
+        }
+        spawn_row(
+            parent,
+            &[
+                (TournamentButton::ExportCsv, "Export CSV".to_string()),
+                (TournamentButton::ExportJson, "Export JSON".to_string()),
+            ],
+        );
+        spawn_row(
+            parent,
+            &[
+                (TournamentButton::Restart, "New tournament".to_string()),
+                (TournamentButton::Back, "Back".to_string()),
+            ],
+        );
+    } else {
+        if let Some((round, index)) = tournament.next_fixture() {
+            let fixture = &tournament.rounds[round][index];
+            spawn_text(
+                parent,
+                format!("Next: {}", tournament.fixture_label(round, fixture)),
+                30.0,
+            );
+        }
+        spawn_row(
+            parent,
+            &[
+                (TournamentButton::PlayNext, "Play next match".to_string()),
+                (TournamentButton::Restart, "Abandon".to_string()),
+                (TournamentButton::Back, "Back".to_string()),
+            ],
+        );
+    }
+}
+
+fn handle_tournament_click(
+    mut tournament: ResMut<Tournament>,
+    mut session: ResMut<TournamentSession>,
+    mut rules: ResMut<MatchRules>,
+    mut score: ResMut<Score>,
+    mut match_exit: ResMut<MatchExit>,
+    interaction_query: Query<(&Interaction, &TournamentButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, button) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        session.message = None;
+        match button {
+            TournamentButton::AddPlayer => {
+                if tournament.entrants.len() < MAX_ENTRANTS {
+                    session.name_input = Some(String::new());
+                }
+            }
+            TournamentButton::AddComputer => {
+                if tournament.entrants.len() < MAX_ENTRANTS {
+                    let number = tournament
+                        .entrants
+                        .iter()
+                        .filter(|entrant| entrant.computer)
+                        .count()
+                        + 1;
+                    tournament.entrants.push(Entrant {
+                        name: format!("Computer {number}"),
+                        computer: true,
+                    });
+                }
+            }
+            TournamentButton::RemoveEntrant => {
+                tournament.entrants.pop();
+            }
+            TournamentButton::Format => {
+                tournament.format = next_value(&TournamentFormat::ALL, tournament.format)
+            }
+            TournamentButton::PointsToWin => {
+                tournament.points_to_win = next_value(&POINTS_TO_WIN, tournament.points_to_win)
+            }
+            TournamentButton::Start => tournament.start(),
+            TournamentButton::PlayNext => {
+                start_fixture(&tournament, &mut session, &mut rules, &mut score);
+                if session.playing.is_some() {
+                    match_exit.0 = GameState::Tournament;
+                    next_state.set(GameState::Playing);
+                }
+            }
+            TournamentButton::ExportCsv => {
+                let csv = standings_csv(&tournament.standings());
+                session.message = Some(export_message(write_file("standings.csv", &csv)));
+            }
+            TournamentButton::ExportJson => {
+                let result = serde_json::to_string_pretty(&tournament.standings())
+                    .map_err(std::io::Error::from)
+                    .and_then(|json| write_file("standings.json", &json));
+                session.message = Some(export_message(result));
+            }
+            TournamentButton::Restart => tournament.rounds.clear(),
+            TournamentButton::Back => {
+                leave_tournament(&mut session, &mut rules, &mut score, &mut match_exit);
+                next_state.set(GameState::Menu);
+            }
+        }
+        if tournament.is_changed() {
+            save_tournament(&tournament);
+        }
+    }
+}
+
+fn export_message(result: std::io::Result<std::path::PathBuf>) -> String {
+    match result {
+        Ok(path) => format!("Standings saved to {}", path.display()),
+        Err(error) => format!("Could not save the standings: {error}"),
+    }
+}
+
+// Hands the match rules over to the next fixture, keeping the ones picked in the menu and the score for later
+fn start_fixture(
+    tournament: &Tournament,
+    session: &mut TournamentSession,
+    rules: &mut MatchRules,
+    score: &mut Score,
+) {
+    let Some((round, index)) = tournament.next_fixture() else {
+        return;
+    };
+    let [Some(first), Some(second)] = tournament.rounds[round][index].entrants else {
+        return;
+    };
+    let first_computer = tournament.entrants[first].computer;
+    let second_computer = tournament.entrants[second].computer;
+    let swapped = first_computer && !second_computer;
+
+    if session.saved_rules.is_none() {
+        session.saved_rules = Some(rules.clone());
+    }
+    // Every match starts from nothing, so the score shown is the one of the match
+    let saved_score = mem::take(score);
+    session.saved_score.get_or_insert(saved_score);
+    rules.mode = GameMode::Classic;
+    rules.handicaps = [Handicap::default(); 4];
+    let (left, right) = match (first_computer, second_computer) {
+        // Two people share the keyboard, each with their own keys
+        (false, false) => (Controller::Keys, Controller::Keys),
+        (true, true) => (Controller::Ai, Controller::Ai),
+        _ => (Controller::Player, Controller::Ai),
+    };
+    rules.controllers[PaddleSide::Left.index()] = left;
+    rules.controllers[PaddleSide::Right.index()] = right;
+    session.playing = Some(PlayingFixture {
+        round,
+        index,
+        swapped,
+    });
+}
+
+fn leave_tournament(
+    session: &mut TournamentSession,
+    rules: &mut MatchRules,
+    score: &mut Score,
+    match_exit: &mut MatchExit,
+) {
+    if let Some(saved_rules) = session.saved_rules.take() {
+        *rules = saved_rules;
+    }
+    if let Some(saved_score) = session.saved_score.take() {
+        *score = saved_score;
+    }
+    session.name_input = None;
+    session.message = None;
+    match_exit.0 = GameState::Menu;
+}
+
+fn handle_name_input(
+    mut tournament: ResMut<Tournament>,
+    mut session: ResMut<TournamentSession>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+) {
+    if session.name_input.is_none() {
+        keyboard_events.clear();
+        return;
+    }
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        let Some(name) = session.name_input.as_mut() else {
+            break;
+        };
+        match &event.logical_key {
+            Key::Character(text) => {
+                for character in text.chars().filter(|character| !character.is_control()) {
+                    if name.chars().count() < MAX_NAME_LENGTH {
+                        name.push(character);
+                    }
+                }
+            }
+            Key::Space if name.chars().count() < MAX_NAME_LENGTH => name.push(' '),
+            Key::Backspace => {
+                name.pop();
+            }
+            Key::Enter => {
+                let name = name.trim().to_string();
+                session.name_input = None;
+                if !name.is_empty() && tournament.entrants.len() < MAX_ENTRANTS {
+                    tournament.entrants.push(Entrant {
+                        name,
+                        computer: false,
+                    });
+                    save_tournament(&tournament);
+                }
+            }
+            Key::Escape => session.name_input = None,
+            _ => {}
+        }
+    }
+}
+
+// Escape closes the name field first, and only leaves the screen when it is not open
+fn handle_tournament_escape_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut session: ResMut<TournamentSession>,
+    mut rules: ResMut<MatchRules>,
+    mut score: ResMut<Score>,
+    mut match_exit: ResMut<MatchExit>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) && session.name_input.is_none() {
+        leave_tournament(&mut session, &mut rules, &mut score, &mut match_exit);
+        next_state.set(GameState::Menu);
+    }
+}
+
+fn cleanup_tournament_screen(
+    mut commands: Commands,
+    screen_query: Query<Entity, With<TournamentScreen>>,
+) {
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn finish_fixture(
+    mut commands: Commands,
+    score: Res<Score>,
+    stats: Res<MatchStats>,
+    session: Res<TournamentSession>,
+    mut tournament: ResMut<Tournament>,
+    mut match_ended_events: EventWriter<MatchEnded>,
+    mut next_state: ResMut<NextState<PlayingState>>,
+) {
+    let Some(playing) = &session.playing else {
+        return;
+    };
+    let [left, right, ..] = stats.match_points(&score);
+    let points = [left, right];
+    let sides = [PaddleSide::Left, PaddleSide::Right];
+    let Some(winner) = decided_winner(&points, &sides, tournament.points_to_win) else {
+        return;
+    };
+    next_state.set(PlayingState::Over);
+    match_ended_events.send(MatchEnded {
+        winner: Some(winner),
+        points: [left, right, 0, 0],
+    });
+    let points = if playing.swapped {
+        [points[1], points[0]]
+    } else {
+        points
+    };
+    tournament.record(playing.round, playing.index, points);
+    save_tournament(&tournament);
+
+    let fixture = &tournament.rounds[playing.round][playing.index];
+    let winner = tournament.winner(playing.round, playing.index);
+    spawn_end_screen(
+        &mut commands,
+        format!("{} wins!", tournament.entrant_name(winner)),
+        vec![tournament.fixture_label(playing.round, fixture)],
+        FixtureEntity,
+    );
+}
+
+fn cleanup_fixture(
+    mut commands: Commands,
+    mut session: ResMut<TournamentSession>,
+    fixture_query: Query<Entity, With<FixtureEntity>>,
+) {
+    for entity in fixture_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    session.playing = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    fn tournament(format: TournamentFormat, names: &[&str]) -> Tournament {
+        let mut tournament = Tournament {
+            format,
+            entrants: names
+                .iter()
+                .map(|name| Entrant {
+                    name: name.to_string(),
+                    computer: false,
+                })
+                .collect(),
+            ..default()
+        };
+        tournament.start();
+        tournament
+    }
+
+    fn entrants(round: &[Fixture]) -> Vec<[Option<usize>; 2]> {
+        round.iter().map(|fixture| fixture.entrants).collect()
+    }
+
+    #[test]
+    fn knockout_pairs_first_with_last() {
+        let rounds = knockout_rounds(4);
+        assert_eq!(rounds.len(), 2);
+        assert_eq!(
+            entrants(&rounds[0]),
+            [[Some(0), Some(3)], [Some(1), Some(2)]]
+        );
+        assert_eq!(entrants(&rounds[1]), [[None, None]]);
+    }
+
+    #[test]
+    fn knockout_gives_byes_to_the_first_entrants() {
+        let rounds = knockout_rounds(5);
+        assert_eq!(rounds.iter().map(Vec::len).collect::<Vec<_>>(), [4, 2, 1]);
+        assert_eq!(
+            entrants(&rounds[0]),
+            [
+                [Some(0), None],
+                [Some(1), None],
+                [Some(2), None],
+                [Some(3), Some(4)]
+            ]
+        );
+    }
+
+    #[test]
+    fn knockout_moves_winners_on_to_the_final() {
+        let mut tournament = tournament(TournamentFormat::Knockout, &["Ann", "Bo", "Cy"]);
+        // Ann has a bye straight into the final
+        assert_eq!(tournament.rounds[1][0].entrants, [Some(0), None]);
+        assert_eq!(tournament.next_fixture(), Some((0, 1)));
+        assert_eq!(tournament.champion(), None);
+
+        tournament.record(0, 1, [3, 5]);
+        assert_eq!(tournament.rounds[1][0].entrants, [Some(0), Some(2)]);
+        tournament.record(1, 0, [2, 5]);
+        assert!(tournament.is_finished());
+        assert_eq!(tournament.champion().as_deref(), Some("Cy"));
+    }
+
+    #[test]
+    fn round_robin_plays_every_pair_once() {
+        for count in [2, 4, 5, 7] {
+            let rounds = round_robin_rounds(count);
+            let mut pairs = HashSet::new();
+            for round in &rounds {
+                let mut playing = HashSet::new();
+                for fixture in round {
+                    let [Some(left), Some(right)] = fixture.entrants else {
+                        panic!("a fixture without two entrants");
+                    };
+                    assert!(playing.insert(left) && playing.insert(right));
+                    assert!(pairs.insert((left.min(right), left.max(right))));
+                }
+                // With an odd number of entrants one of them sits each round out
+                assert_eq!(playing.len(), count - count % 2);
+            }
+            assert_eq!(pairs.len(), count * (count - 1) / 2);
+            assert_eq!(rounds.len(), count - 1 + count % 2);
+        }
+    }
+
+    #[test]
+    fn standings_rank_wins_then_point_difference() {
+        let mut tournament = tournament(TournamentFormat::RoundRobin, &["Ann", "Bo", "Cy"]);
+        let mut results = HashMap::new();
+        // Ann and Bo beat Cy, Bo by more, and Ann beats Bo
+        results.insert((0, 2), [5, 4]);
+        results.insert((1, 2), [5, 0]);
+        results.insert((0, 1), [5, 3]);
+        while let Some((round, index)) = tournament.next_fixture() {
+            assert_eq!(tournament.champion(), None);
+            let [Some(left), Some(right)] = tournament.rounds[round][index].entrants else {
+                unreachable!();
+            };
+            let points = match results.get(&(left, right)) {
+                Some(points) => *points,
+                None => {
+                    let [a, b] = results[&(right, left)];
+                    [b, a]
+                }
+            };
+            tournament.record(round, index, points);
+        }
+
+        let standings = tournament.standings();
+        let names: Vec<_> = standings
+            .iter()
+            .map(|standing| standing.name.as_str())
+            .collect();
+        assert_eq!(names, ["Ann", "Bo", "Cy"]);
+        let ann = &standings[0];
+        assert_eq!((ann.played, ann.won, ann.lost), (2, 2, 0));
+        assert_eq!((ann.points_for, ann.points_against), (10, 7));
+        let bo = &standings[1];
+        assert_eq!((bo.won, bo.points_for, bo.points_against), (1, 8, 5));
+        assert_eq!(tournament.champion().as_deref(), Some("Ann"));
+    }
+
+    #[test]
+    fn standings_csv_quotes_names() {
+        let standings = [Standing {
+            name: "Ann \"Ace\", Jr".to_string(),
+            played: 2,
+            won: 1,
+            lost: 1,
+            points_for: 8,
+            points_against: 9,
+        }];
+        assert_eq!(
+            standings_csv(&standings),
+            "rank,name,played,won,lost,points_for,points_against\n\
+             1,\"Ann \"\"Ace\"\", Jr\",2,1,1,8,9\n"
+        );
+    }
+}
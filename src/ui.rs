@@ -0,0 +1,183 @@
+use crate::theme::ThemeRole;
+use crate::{GameSet, GameState};
+use bevy::prelude::*;
+
+pub struct UiPlugin;
+
+/// This plugin holds the button behaviour shared by all menu screens
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                update_button_colors.in_set(GameSet::Presentation),
+                handle_change_state_click.in_set(GameSet::Input),
+            ),
+        );
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct ButtonColors {
+    pub normal: Color,
+    pub hovered: Color,
+}
+
+impl Default for ButtonColors {
+    fn default() -> Self {
+        ButtonColors {
+            normal: Color::linear_rgb(0.15, 0.15, 0.15),
+            hovered: Color::linear_rgb(0.25, 0.25, 0.25),
+        }
+    }
+}
+
+// Buttons with this component move the game to the given state when pressed
+#[derive(Component)]
+pub struct ChangeState(pub GameState);
+
+/// Spawns a text button in the style of the menu, with `marker` deciding what pressing it does
+pub fn spawn_button(
+    parent: &mut ChildBuilder,
+    label: impl Into<String>,
+    font_size: f32,
+    marker: impl Bundle,
+) -> Entity {
+    let button_colors = ButtonColors::default();
+    parent
+        .spawn((
+            Button,
+            Node {
+                min_width: Val::Px(210.0),
+                height: Val::Px(50.0),
+                padding: UiRect::horizontal(Val::Px(20.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                margin: UiRect::bottom(Val::Px(20.0)),
+                ..default()
+            },
+            BackgroundColor(button_colors.normal),
+            button_colors,
+            marker,
+        ))
+        .with_child((
+            Text::new(label),
+            TextFont {
+                font_size,
+                ..default()
+            },
+            TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+        ))
+        .id()
+}
+
+/// Spawns the screen shown over the court once a match is over: a title, a line for each of the `details`
+/// and how to leave the match. `marker` lets the plugin that spawned it clean it up.
+pub fn spawn_end_screen(
+    commands: &mut Commands,
+    title: impl Into<String>,
+    details: Vec<String>,
+    marker: impl Bundle,
+) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            marker,
+        ))
+        .with_children(|children| {
+            let title = (title.into(), 80.0);
+            let details = details.into_iter().map(|line| (line, 30.0));
+            let hint = ("Esc to continue".to_string(), 30.0);
+            for (text, font_size) in std::iter::once(title).chain(details).chain([hint]) {
+                children.spawn((
+                    Text::new(text),
+                    TextFont {
+                        font_size,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    ThemeRole::UiText,
+                ));
+            }
+        });
+}
+
+pub fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+// The value after `current` in `values`, wrapping around to the first
+pub fn next_value<T: Copy + PartialEq>(values: &[T], current: T) -> T {
+    values
+        .iter()
+        .position(|value| *value == current)
+        .map_or(values[0], |index| values[(index + 1) % values.len()])
+}
+
+fn update_button_colors(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &ButtonColors),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color, button_colors) in &mut interaction_query {
+        match *interaction {
+            Interaction::Hovered => {
+                *color = button_colors.hovered.into();
+            }
+            Interaction::None => {
+                *color = button_colors.normal.into();
+            }
+            Interaction::Pressed => {}
+        }
+    }
+}
+
+fn handle_change_state_click(
+    mut next_state: ResMut<NextState<GameState>>,
+    interaction_query: Query<(&Interaction, &ChangeState), (Changed<Interaction>, With<Button>)>,
+) {
+    for (interaction, change_state) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            next_state.set(change_state.0.clone());
+        }
+    }
+}
+
+// Seconds as minutes and seconds, like 1:05
+pub fn format_clock(seconds: f32) -> String {
+    let seconds = seconds.max(0.).ceil() as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_value_wraps_around() {
+        assert_eq!(next_value(&[1, 2, 3], 2), 3);
+        assert_eq!(next_value(&[1, 2, 3], 3), 1);
+        // A value that isn't one of the choices starts them over
+        assert_eq!(next_value(&[1, 2, 3], 7), 1);
+    }
+
+    #[test]
+    fn clock_rounds_up_to_whole_seconds() {
+        assert_eq!(format_clock(65.), "1:05");
+        assert_eq!(format_clock(0.2), "0:01");
+        assert_eq!(format_clock(-3.), "0:00");
+    }
+}